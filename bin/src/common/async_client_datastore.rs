@@ -0,0 +1,416 @@
+use crate::autogen;
+use capnp::Error as CapnpError;
+use capnp_rpc::rpc_twoparty_capnp::Side;
+use capnp_rpc::{twoparty, RpcSystem};
+use crate::converters;
+use crate::errors::Error;
+use crate::server::PROTOCOL_VERSION;
+use futures::{future, Future};
+use indradb;
+use serde_json::value::Value as JsonValue;
+use std::net::SocketAddr;
+use tokio_core::net::TcpStream;
+use tokio_core::reactor::Handle;
+use tokio_io::{AsyncRead, AsyncWrite};
+use uuid::Uuid;
+
+/// The future returned by the async client's requests.
+pub type AsyncResult<T> = Box<dyn Future<Item = T, Error = Error>>;
+
+/// A client that makes requests without blocking, for use from code that
+/// already runs on a tokio reactor. Each request is sent as soon as its
+/// method is called, and returns a future that resolves to its result.
+/// Unlike `ClientDatastore`, server errors are returned rather than
+/// causing a panic.
+#[derive(Clone)]
+pub struct AsyncClientDatastore {
+    client: autogen::service::Client,
+}
+
+impl AsyncClientDatastore {
+    /// Connects to a server over TCP, and checks that it speaks a compatible
+    /// protocol version.
+    ///
+    /// # Arguments
+    /// * `addr` - The address the server is listening on.
+    /// * `handle` - A handle to the reactor that will run the client.
+    pub fn connect(addr: &SocketAddr, handle: &Handle) -> impl Future<Item = Self, Error = Error> {
+        let handle = handle.clone();
+
+        TcpStream::connect(addr, &handle)
+            .map_err(Error::from)
+            .and_then(move |stream| {
+                stream.set_nodelay(true)?;
+                Ok(Self::from_stream(stream, &handle))
+            })
+            .and_then(|datastore| datastore.handshake().map(move |_| datastore))
+    }
+
+    /// Creates a client from an already-established connection, e.g. one
+    /// that has been wrapped in TLS. Call `handshake` before making any
+    /// other requests.
+    ///
+    /// # Arguments
+    /// * `stream` - The connection to the server.
+    /// * `handle` - A handle to the reactor that will run the client.
+    pub fn from_stream<S>(stream: S, handle: &Handle) -> Self
+    where
+        S: AsyncRead + AsyncWrite + 'static,
+    {
+        let (reader, writer) = stream.split();
+        let rpc_network = Box::new(twoparty::VatNetwork::new(
+            reader,
+            writer,
+            Side::Client,
+            Default::default(),
+        ));
+        let mut rpc_system = RpcSystem::new(rpc_network, None);
+        let client: autogen::service::Client = rpc_system.bootstrap(Side::Server);
+        handle.spawn(rpc_system.map_err(|_e| ()));
+        Self { client }
+    }
+
+    /// Exchanges protocol versions with the server. Fails with
+    /// `Error::ProtocolMismatch` if the server doesn't accept this client's
+    /// version.
+    pub fn handshake(&self) -> AsyncResult<()> {
+        self.handshake_with_version(PROTOCOL_VERSION)
+    }
+
+    pub(crate) fn handshake_with_version(&self, client: u32) -> AsyncResult<()> {
+        let mut req = self.client.handshake_request();
+        req.get().set_version(client);
+
+        let f = req.send().promise.map_err(Error::from).and_then(move |res| {
+            let res = res.get()?;
+
+            if res.get_accepted() {
+                Ok(())
+            } else {
+                Err(Error::ProtocolMismatch {
+                    client,
+                    server: res.get_version(),
+                })
+            }
+        });
+
+        Box::new(f)
+    }
+
+    /// Checks whether the server is up. This doesn't touch the datastore,
+    /// so it's suitable as a liveness check.
+    pub fn ping(&self) -> AsyncResult<bool> {
+        let req = self.client.ping_request();
+        let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_ready()));
+        Box::new(f.map_err(Error::from))
+    }
+
+    /// Checks whether the server's datastore is responding, so it's
+    /// suitable as a readiness check.
+    pub fn ready(&self) -> AsyncResult<bool> {
+        let req = self.client.ready_request();
+        let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_ready()));
+        Box::new(f.map_err(Error::from))
+    }
+
+    /// Bulk inserts many vertices, edges, and/or properties. See
+    /// `Datastore::bulk_insert`.
+    pub fn bulk_insert<I>(&self, items: I) -> AsyncResult<()>
+    where
+        I: Iterator<Item = indradb::BulkInsertItem>,
+    {
+        let items: Vec<indradb::BulkInsertItem> = items.collect();
+        let mut req = self.client.bulk_insert_request();
+
+        if let Err(err) = converters::from_bulk_insert_items(&items, req.get().init_items(items.len() as u32)) {
+            return Box::new(future::err(err.into()));
+        }
+
+        let f = req.send().promise.and_then(move |res| {
+            res.get()?;
+            Ok(())
+        });
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    /// Creates a new transaction. The transaction is created on the server
+    /// along with its first request, so this doesn't need to wait.
+    pub fn transaction(&self) -> AsyncClientTransaction {
+        let trans = self.client.transaction_request().send().pipeline.get_transaction();
+        AsyncClientTransaction { trans }
+    }
+}
+
+/// A transaction for `AsyncClientDatastore`. Its methods mirror those of
+/// `indradb::Transaction`, but return futures.
+#[derive(Clone)]
+pub struct AsyncClientTransaction {
+    trans: autogen::transaction::Client,
+}
+
+impl AsyncClientTransaction {
+    pub fn create_vertex(&self, v: &indradb::Vertex) -> AsyncResult<bool> {
+        let mut req = self.trans.create_vertex_request();
+        converters::from_vertex(v, req.get().init_vertex());
+
+        let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_result()));
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn create_vertex_from_type(&self, t: indradb::Type) -> AsyncResult<Uuid> {
+        let mut req = self.trans.create_vertex_from_type_request();
+        req.get().set_t(&t.0);
+
+        let f = req.send().promise.and_then(move |res| {
+            let bytes = res.get()?.get_result()?;
+            converters::map_capnp_err(Uuid::from_slice(bytes))
+        });
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn get_vertices<Q: Into<indradb::VertexQuery>>(&self, q: Q) -> AsyncResult<Vec<indradb::Vertex>> {
+        let mut req = self.trans.get_vertices_request();
+        converters::from_vertex_query(&q.into(), req.get().init_q());
+
+        let f = req.send().promise.and_then(move |res| {
+            let list = res.get()?.get_result()?;
+            let list: Result<Vec<indradb::Vertex>, CapnpError> =
+                list.into_iter().map(|reader| converters::to_vertex(&reader)).collect();
+            list
+        });
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn delete_vertices<Q: Into<indradb::VertexQuery>>(&self, q: Q) -> AsyncResult<()> {
+        let mut req = self.trans.delete_vertices_request();
+        converters::from_vertex_query(&q.into(), req.get().init_q());
+
+        let f = req.send().promise.and_then(move |res| {
+            res.get()?;
+            Ok(())
+        });
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn get_vertex_count(&self) -> AsyncResult<u64> {
+        let req = self.trans.get_vertex_count_request();
+
+        let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_result()));
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn count_vertices(&self, t: Option<&indradb::Type>) -> AsyncResult<u64> {
+        let mut req = self.trans.count_vertices_request();
+
+        if let Some(t) = t {
+            req.get().set_t(&t.0);
+        }
+
+        let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_result()));
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn delete_vertices_by_type(&self, t: indradb::Type) -> AsyncResult<u64> {
+        let mut req = self.trans.delete_vertices_by_type_request();
+        req.get().set_t(&t.0);
+
+        let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_result()));
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn create_edge(&self, e: &indradb::EdgeKey) -> AsyncResult<bool> {
+        let mut req = self.trans.create_edge_request();
+        converters::from_edge_key(e, req.get().init_key());
+
+        let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_result()));
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn create_edges(&self, keys: &[indradb::EdgeKey]) -> AsyncResult<Vec<bool>> {
+        let mut req = self.trans.create_edges_request();
+
+        {
+            let mut builder = req.get().init_keys(keys.len() as u32);
+
+            for (i, key) in keys.iter().enumerate() {
+                converters::from_edge_key(key, builder.reborrow().get(i as u32));
+            }
+        }
+
+        let f = req.send().promise.and_then(move |res| {
+            let list = res.get()?.get_result()?;
+            let list: Vec<bool> = list.into_iter().collect();
+            Ok(list)
+        });
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn get_edges<Q: Into<indradb::EdgeQuery>>(&self, q: Q) -> AsyncResult<Vec<indradb::Edge>> {
+        let mut req = self.trans.get_edges_request();
+        converters::from_edge_query(&q.into(), req.get().init_q());
+
+        let f = req.send().promise.and_then(move |res| {
+            let list = res.get()?.get_result()?;
+            let list: Result<Vec<indradb::Edge>, CapnpError> =
+                list.into_iter().map(|reader| converters::to_edge(&reader)).collect();
+            list
+        });
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn has_edge(&self, key: &indradb::EdgeKey) -> AsyncResult<bool> {
+        let mut req = self.trans.has_edge_request();
+        converters::from_edge_key(key, req.get().init_key());
+
+        let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_result()));
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn delete_edges<Q: Into<indradb::EdgeQuery>>(&self, q: Q) -> AsyncResult<()> {
+        let mut req = self.trans.delete_edges_request();
+        converters::from_edge_query(&q.into(), req.get().init_q());
+
+        let f = req.send().promise.and_then(move |res| {
+            res.get()?;
+            Ok(())
+        });
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn get_edge_count(
+        &self,
+        id: Uuid,
+        t: Option<&indradb::Type>,
+        direction: indradb::EdgeDirection,
+    ) -> AsyncResult<u64> {
+        let mut req = self.trans.get_edge_count_request();
+        req.get().set_id(id.as_bytes());
+
+        if let Some(t) = t {
+            req.get().set_t(&t.0);
+        }
+
+        req.get().set_direction(converters::from_edge_direction(direction));
+
+        let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_result()));
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn count_edges(&self, t: Option<&indradb::Type>) -> AsyncResult<u64> {
+        let mut req = self.trans.count_edges_request();
+
+        if let Some(t) = t {
+            req.get().set_t(&t.0);
+        }
+
+        let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_result()));
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn rename_edge_type(&self, from: indradb::Type, to: indradb::Type) -> AsyncResult<u64> {
+        let mut req = self.trans.rename_edge_type_request();
+        req.get().set_from(&from.0);
+        req.get().set_to(&to.0);
+
+        let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_result()));
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn get_vertex_properties(&self, q: indradb::VertexPropertyQuery) -> AsyncResult<Vec<indradb::VertexProperty>> {
+        let mut req = self.trans.get_vertex_properties_request();
+        converters::from_vertex_property_query(&q, req.get().init_q());
+
+        let f = req.send().promise.and_then(move |res| {
+            let list = res.get()?.get_result()?;
+            let list: Result<Vec<indradb::VertexProperty>, CapnpError> = list
+                .into_iter()
+                .map(|reader| converters::to_vertex_property(&reader))
+                .collect();
+            list
+        });
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn set_vertex_properties(&self, q: indradb::VertexPropertyQuery, value: &JsonValue) -> AsyncResult<()> {
+        let mut req = self.trans.set_vertex_properties_request();
+        converters::from_vertex_property_query(&q, req.get().init_q());
+        req.get().set_value(&value.to_string());
+
+        let f = req.send().promise.and_then(move |res| {
+            res.get()?;
+            Ok(())
+        });
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn delete_vertex_properties(&self, q: indradb::VertexPropertyQuery) -> AsyncResult<()> {
+        let mut req = self.trans.delete_vertex_properties_request();
+        converters::from_vertex_property_query(&q, req.get().init_q());
+
+        let f = req.send().promise.and_then(move |res| {
+            res.get()?;
+            Ok(())
+        });
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn get_edge_properties(&self, q: indradb::EdgePropertyQuery) -> AsyncResult<Vec<indradb::EdgeProperty>> {
+        let mut req = self.trans.get_edge_properties_request();
+        converters::from_edge_property_query(&q, req.get().init_q());
+
+        let f = req.send().promise.and_then(move |res| {
+            let list = res.get()?.get_result()?;
+            let list: Result<Vec<indradb::EdgeProperty>, CapnpError> = list
+                .into_iter()
+                .map(|reader| converters::to_edge_property(&reader))
+                .collect();
+            list
+        });
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn set_edge_properties(&self, q: indradb::EdgePropertyQuery, value: &JsonValue) -> AsyncResult<()> {
+        let mut req = self.trans.set_edge_properties_request();
+        converters::from_edge_property_query(&q, req.get().init_q());
+        req.get().set_value(&value.to_string());
+
+        let f = req.send().promise.and_then(move |res| {
+            res.get()?;
+            Ok(())
+        });
+
+        Box::new(f.map_err(Error::from))
+    }
+
+    pub fn delete_edge_properties(&self, q: indradb::EdgePropertyQuery) -> AsyncResult<()> {
+        let mut req = self.trans.delete_edge_properties_request();
+        converters::from_edge_property_query(&q, req.get().init_q());
+
+        let f = req.send().promise.and_then(move |res| {
+            res.get()?;
+            Ok(())
+        });
+
+        Box::new(f.map_err(Error::from))
+    }
+}
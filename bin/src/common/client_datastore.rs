@@ -1,28 +1,65 @@
-use crate::autogen;
-use capnp::Error as CapnpError;
-use capnp_rpc::rpc_twoparty_capnp::Side;
-use capnp_rpc::{twoparty, RpcSystem};
-use crate::converters;
+use crate::async_client_datastore::{AsyncClientDatastore, AsyncClientTransaction, AsyncResult};
+use futures::future::Either;
 use futures::Future;
 use indradb;
 use serde_json::value::Value as JsonValue;
 use std::cell::RefCell;
+use std::fs::File;
+use std::io::BufReader;
 use std::net::ToSocketAddrs;
 use std::rc::Rc;
+use std::sync::Arc;
 use std::thread::sleep;
 use std::time::Duration;
 use tokio_core::net::TcpStream;
-use tokio_core::reactor::Core;
-use tokio_io::AsyncRead;
+use tokio_core::reactor::{Core, Timeout};
+use tokio_rustls::rustls::ClientConfig;
+use tokio_rustls::TlsConnector;
 use uuid::Uuid;
+use webpki::DNSNameRef;
 
+/// A client that blocks on each request. It runs its own reactor on top of
+/// `AsyncClientDatastore`.
 pub struct ClientDatastore {
     core: Rc<RefCell<Core>>,
-    client: autogen::service::Client,
+    inner: AsyncClientDatastore,
+    timeout: Option<Duration>,
 }
 
 impl ClientDatastore {
     pub fn new(port: u16) -> Self {
+        Self::connect(port, None, None)
+    }
+
+    /// Creates a client whose transaction requests fail with
+    /// `Error::Timeout` if they take longer than `timeout`.
+    pub fn with_timeout(port: u16, timeout: Duration) -> Self {
+        Self::connect(port, Some(timeout), None)
+    }
+
+    /// Creates a client that connects over TLS, for servers started with
+    /// `server::start_tls`. The server's certificate must be valid for
+    /// `domain`, and signed by one of the certificates in `ca_cert_path`.
+    /// This will panic if the CA certificates can't be read.
+    ///
+    /// # Arguments
+    /// * `port` - The port the server is listening on.
+    /// * `ca_cert_path` - The path to the PEM-encoded certificates to trust.
+    /// * `domain` - The domain name to verify the server's certificate
+    ///   against.
+    pub fn with_tls(port: u16, ca_cert_path: &str, domain: &str) -> Self {
+        let mut config = ClientConfig::new();
+        let mut reader = BufReader::new(File::open(ca_cert_path).unwrap());
+        config
+            .root_store
+            .add_pem_file(&mut reader)
+            .expect("Could not parse the CA certificates");
+
+        let connector = TlsConnector::from(Arc::new(config));
+        Self::connect(port, None, Some((connector, domain)))
+    }
+
+    fn connect(port: u16, timeout: Option<Duration>, tls: Option<(TlsConnector, &str)>) -> Self {
         let mut core = Core::new().unwrap();
         let handle = core.handle();
         let addr = format!("127.0.0.1:{}", port).to_socket_addrs().unwrap().next().unwrap();
@@ -30,24 +67,26 @@ impl ClientDatastore {
         for _ in 0..5 {
             if let Ok(stream) = core.run(TcpStream::connect(&addr, &handle)) {
                 stream.set_nodelay(true).unwrap();
-                let (reader, writer) = stream.split();
-                let rpc_network = Box::new(twoparty::VatNetwork::new(
-                    reader,
-                    writer,
-                    Side::Client,
-                    Default::default(),
-                ));
-                let mut rpc_system = RpcSystem::new(rpc_network, None);
-                let client: autogen::service::Client = rpc_system.bootstrap(Side::Server);
-                handle.spawn(rpc_system.map_err(|_e| ()));
-
-                let req = client.ping_request();
-                let res = core.run(req.send().promise).unwrap();
-
-                if res.get().unwrap().get_ready() {
+
+                let inner = match tls {
+                    Some((ref connector, ref domain)) => {
+                        let domain = DNSNameRef::try_from_ascii_str(domain).expect("Invalid domain name");
+                        let stream = core
+                            .run(connector.connect(domain, stream))
+                            .expect("Could not complete the TLS handshake");
+                        AsyncClientDatastore::from_stream(stream, &handle)
+                    }
+                    None => AsyncClientDatastore::from_stream(stream, &handle),
+                };
+
+                if core.run(inner.ping()).unwrap() {
+                    core.run(inner.handshake())
+                        .unwrap_or_else(|err| panic!("Could not connect to the server: {}", err));
+
                     return Self {
                         core: Rc::new(RefCell::new(core)),
-                        client,
+                        inner,
+                        timeout,
                     };
                 }
             }
@@ -57,6 +96,19 @@ impl ClientDatastore {
 
         panic!("Could not connect to the server after a few seconds");
     }
+
+    /// Checks whether the server is up. This doesn't touch the datastore,
+    /// so it's suitable as a liveness check. Returns false if the request
+    /// fails.
+    pub fn ping(&self) -> bool {
+        self.core.borrow_mut().run(self.inner.ping()).unwrap_or(false)
+    }
+
+    /// Checks whether the server's datastore is responding, so it's
+    /// suitable as a readiness check. Returns false if the request fails.
+    pub fn ready(&self) -> bool {
+        self.core.borrow_mut().run(self.inner.ready()).unwrap_or(false)
+    }
 }
 
 impl indradb::Datastore for ClientDatastore {
@@ -66,157 +118,95 @@ impl indradb::Datastore for ClientDatastore {
     where
         I: Iterator<Item = indradb::BulkInsertItem>,
     {
-        let items: Vec<indradb::BulkInsertItem> = items.collect();
-        let mut req = self.client.bulk_insert_request();
-
-        converters::from_bulk_insert_items(
-            &items,
-            req.get().init_items(items.len() as u32),
-        ).unwrap();
-
-        let f = req.send().promise.and_then(move |res| {
-            res.get()?;
-            Ok(())
-        });
-
-        Ok(self.core.borrow_mut().run(f).unwrap())
+        Ok(self.core.borrow_mut().run(self.inner.bulk_insert(items)).unwrap())
     }
 
     fn transaction(&self) -> Result<ClientTransaction, indradb::Error> {
-        let trans = self.client.transaction_request().send().pipeline.get_transaction();
-        Ok(ClientTransaction::new(self.core.clone(), trans))
+        Ok(ClientTransaction::new(
+            self.core.clone(),
+            self.inner.transaction(),
+            self.timeout,
+        ))
     }
 }
 
 pub struct ClientTransaction {
     core: Rc<RefCell<Core>>,
-    trans: RefCell<autogen::transaction::Client>,
+    inner: AsyncClientTransaction,
+    timeout: Option<Duration>,
 }
 
 impl ClientTransaction {
-    fn new(core: Rc<RefCell<Core>>, trans: autogen::transaction::Client) -> Self {
-        ClientTransaction {
-            core,
-            trans: RefCell::new(trans),
-        }
+    fn new(core: Rc<RefCell<Core>>, inner: AsyncClientTransaction, timeout: Option<Duration>) -> Self {
+        ClientTransaction { core, inner, timeout }
     }
 }
 
 impl ClientTransaction {
-    fn execute<F, G>(&self, f: F) -> Result<G, indradb::Error>
-    where
-        F: FnOnce(&mut autogen::transaction::Client) -> Box<Future<Item = G, Error = CapnpError>>,
-    {
-        let future = f(&mut self.trans.borrow_mut());
-        Ok(self.core.borrow_mut().run(future).unwrap())
+    fn execute<G>(&self, future: AsyncResult<G>) -> Result<G, indradb::Error> {
+        let mut core = self.core.borrow_mut();
+
+        match self.timeout {
+            Some(after) => {
+                // If the timeout fires first, the request future is dropped,
+                // which cancels the in-flight request.
+                let timeout = Timeout::new(after, &core.handle()).unwrap();
+
+                match core.run(future.select2(timeout)) {
+                    Ok(Either::A((value, _))) => Ok(value),
+                    Ok(Either::B(_)) => Err(indradb::Error::Timeout { after }),
+                    Err(Either::A((err, _))) => panic!("Request failed: {:?}", err),
+                    Err(Either::B((err, _))) => panic!("Request timer failed: {:?}", err),
+                }
+            }
+            None => Ok(core.run(future).unwrap()),
+        }
     }
 }
 
 impl indradb::Transaction for ClientTransaction {
     fn create_vertex(&self, v: &indradb::Vertex) -> Result<bool, indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.create_vertex_request();
-            converters::from_vertex(v, req.get().init_vertex());
-
-            let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_result()));
-
-            Box::new(f)
-        })
+        self.execute(self.inner.create_vertex(v))
     }
 
     fn create_vertex_from_type(&self, t: indradb::Type) -> Result<Uuid, indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.create_vertex_from_type_request();
-            req.get().set_t(&t.0);
-
-            let f = req.send().promise.and_then(move |res| {
-                let bytes = res.get()?.get_result()?;
-                Ok(Uuid::from_slice(bytes).unwrap())
-            });
-
-            Box::new(f)
-        })
+        self.execute(self.inner.create_vertex_from_type(t))
     }
 
     fn get_vertices<Q: Into<indradb::VertexQuery>>(&self, q: Q) -> Result<Vec<indradb::Vertex>, indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.get_vertices_request();
-            converters::from_vertex_query(&q.into(), req.get().init_q());
-
-            let f = req.send().promise.and_then(move |res| {
-                let list = res.get()?.get_result()?;
-                let list: Result<Vec<indradb::Vertex>, CapnpError> =
-                    list.into_iter().map(|reader| converters::to_vertex(&reader)).collect();
-                list
-            });
-
-            Box::new(f)
-        })
+        self.execute(self.inner.get_vertices(q))
     }
 
     fn delete_vertices<Q: Into<indradb::VertexQuery>>(&self, q: Q) -> Result<(), indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.delete_vertices_request();
-            converters::from_vertex_query(&q.into(), req.get().init_q());
-
-            let f = req.send().promise.and_then(move |res| {
-                res.get()?;
-                Ok(())
-            });
-
-            Box::new(f)
-        })
+        self.execute(self.inner.delete_vertices(q))
     }
 
     fn get_vertex_count(&self) -> Result<u64, indradb::Error> {
-        self.execute(move |trans| {
-            let req = trans.get_vertex_count_request();
-
-            let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_result()));
+        self.execute(self.inner.get_vertex_count())
+    }
 
-            Box::new(f)
-        })
+    fn count_vertices(&self, t: Option<&indradb::Type>) -> Result<u64, indradb::Error> {
+        self.execute(self.inner.count_vertices(t))
     }
 
     fn create_edge(&self, e: &indradb::EdgeKey) -> Result<bool, indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.create_edge_request();
-            converters::from_edge_key(e, req.get().init_key());
-
-            let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_result()));
+        self.execute(self.inner.create_edge(e))
+    }
 
-            Box::new(f)
-        })
+    fn create_edges(&self, keys: &[indradb::EdgeKey]) -> Result<Vec<bool>, indradb::Error> {
+        self.execute(self.inner.create_edges(keys))
     }
 
     fn get_edges<Q: Into<indradb::EdgeQuery>>(&self, q: Q) -> Result<Vec<indradb::Edge>, indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.get_edges_request();
-            converters::from_edge_query(&q.into(), req.get().init_q());
-
-            let f = req.send().promise.and_then(move |res| {
-                let list = res.get()?.get_result()?;
-                let list: Result<Vec<indradb::Edge>, CapnpError> =
-                    list.into_iter().map(|reader| converters::to_edge(&reader)).collect();
-                list
-            });
-
-            Box::new(f)
-        })
+        self.execute(self.inner.get_edges(q))
     }
 
-    fn delete_edges<Q: Into<indradb::EdgeQuery>>(&self, q: Q) -> Result<(), indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.delete_edges_request();
-            converters::from_edge_query(&q.into(), req.get().init_q());
-
-            let f = req.send().promise.and_then(move |res| {
-                res.get()?;
-                Ok(())
-            });
+    fn has_edge(&self, key: &indradb::EdgeKey) -> Result<bool, indradb::Error> {
+        self.execute(self.inner.has_edge(key))
+    }
 
-            Box::new(f)
-        })
+    fn delete_edges<Q: Into<indradb::EdgeQuery>>(&self, q: Q) -> Result<(), indradb::Error> {
+        self.execute(self.inner.delete_edges(q))
     }
 
     fn get_edge_count(
@@ -225,116 +215,45 @@ impl indradb::Transaction for ClientTransaction {
         t: Option<&indradb::Type>,
         direction: indradb::EdgeDirection,
     ) -> Result<u64, indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.get_edge_count_request();
-            req.get().set_id(id.as_bytes());
-
-            if let Some(t) = t {
-                req.get().set_t(&t.0);
-            }
+        self.execute(self.inner.get_edge_count(id, t, direction))
+    }
 
-            req.get().set_direction(converters::from_edge_direction(direction));
+    fn count_edges(&self, t: Option<&indradb::Type>) -> Result<u64, indradb::Error> {
+        self.execute(self.inner.count_edges(t))
+    }
 
-            let f = req.send().promise.and_then(move |res| Ok(res.get()?.get_result()));
+    fn delete_vertices_by_type(&self, t: indradb::Type) -> Result<u64, indradb::Error> {
+        self.execute(self.inner.delete_vertices_by_type(t))
+    }
 
-            Box::new(f)
-        })
+    fn rename_edge_type(&self, from: indradb::Type, to: indradb::Type) -> Result<u64, indradb::Error> {
+        self.execute(self.inner.rename_edge_type(from, to))
     }
 
     fn get_vertex_properties(
         &self,
         q: indradb::VertexPropertyQuery,
     ) -> Result<Vec<indradb::VertexProperty>, indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.get_vertex_properties_request();
-            converters::from_vertex_property_query(&q, req.get().init_q());
-
-            let f = req.send().promise.and_then(move |res| {
-                let list = res.get()?.get_result()?;
-                let list: Result<Vec<indradb::VertexProperty>, CapnpError> = list
-                    .into_iter()
-                    .map(|reader| converters::to_vertex_property(&reader))
-                    .collect();
-                list
-            });
-
-            Box::new(f)
-        })
+        self.execute(self.inner.get_vertex_properties(q))
     }
 
     fn set_vertex_properties(&self, q: indradb::VertexPropertyQuery, value: &JsonValue) -> Result<(), indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.set_vertex_properties_request();
-            converters::from_vertex_property_query(&q, req.get().init_q());
-            req.get().set_value(&value.to_string());
-
-            let f = req.send().promise.and_then(move |res| {
-                res.get()?;
-                Ok(())
-            });
-
-            Box::new(f)
-        })
+        self.execute(self.inner.set_vertex_properties(q, value))
     }
 
     fn delete_vertex_properties(&self, q: indradb::VertexPropertyQuery) -> Result<(), indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.delete_vertex_properties_request();
-            converters::from_vertex_property_query(&q, req.get().init_q());
-
-            let f = req.send().promise.and_then(move |res| {
-                res.get()?;
-                Ok(())
-            });
-
-            Box::new(f)
-        })
+        self.execute(self.inner.delete_vertex_properties(q))
     }
 
     fn get_edge_properties(&self, q: indradb::EdgePropertyQuery) -> Result<Vec<indradb::EdgeProperty>, indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.get_edge_properties_request();
-            converters::from_edge_property_query(&q, req.get().init_q());
-
-            let f = req.send().promise.and_then(move |res| {
-                let list = res.get()?.get_result()?;
-                let list: Result<Vec<indradb::EdgeProperty>, CapnpError> = list
-                    .into_iter()
-                    .map(|reader| converters::to_edge_property(&reader))
-                    .collect();
-                list
-            });
-
-            Box::new(f)
-        })
+        self.execute(self.inner.get_edge_properties(q))
     }
 
     fn set_edge_properties(&self, q: indradb::EdgePropertyQuery, value: &JsonValue) -> Result<(), indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.set_edge_properties_request();
-            converters::from_edge_property_query(&q, req.get().init_q());
-            req.get().set_value(&value.to_string());
-
-            let f = req.send().promise.and_then(move |res| {
-                res.get()?;
-                Ok(())
-            });
-
-            Box::new(f)
-        })
+        self.execute(self.inner.set_edge_properties(q, value))
     }
 
     fn delete_edge_properties(&self, q: indradb::EdgePropertyQuery) -> Result<(), indradb::Error> {
-        self.execute(move |trans| {
-            let mut req = trans.delete_edge_properties_request();
-            converters::from_edge_property_query(&q, req.get().init_q());
-
-            let f = req.send().promise.and_then(move |res| {
-                res.get()?;
-                Ok(())
-            });
-
-            Box::new(f)
-        })
+        self.execute(self.inner.delete_edge_properties(q))
     }
 }
@@ -28,14 +28,15 @@ pub fn to_vertex<'a>(reader: &autogen::vertex::Reader<'a>) -> Result<indradb::Ve
 }
 
 pub fn from_edge<'a>(edge: &indradb::Edge, mut builder: autogen::edge::Builder<'a>) -> Result<(), CapnpError> {
-    builder.set_created_datetime(edge.created_datetime.timestamp() as u64);
+    builder.set_created_datetime(edge.created_datetime.timestamp_nanos() as u64);
     from_edge_key(&edge.key, builder.init_key());
     Ok(())
 }
 
 pub fn to_edge<'a>(reader: &autogen::edge::Reader<'a>) -> Result<indradb::Edge, CapnpError> {
     let key = to_edge_key(&reader.get_key()?)?;
-    let created_datetime = Utc.timestamp(reader.get_created_datetime() as i64, 0);
+    let timestamp = reader.get_created_datetime();
+    let created_datetime = Utc.timestamp((timestamp / NANOS_PER_SEC) as i64, (timestamp % NANOS_PER_SEC) as u32);
     Ok(indradb::Edge::new(key, created_datetime))
 }
 
@@ -92,6 +93,15 @@ pub fn from_vertex_query<'a>(q: &indradb::VertexQuery, builder: autogen::vertex_
                 builder.set_t(&t.0);
             }
 
+            if let Some(ref type_prefix) = q.type_prefix {
+                builder.set_type_prefix(type_prefix);
+            }
+
+            if let Some(ref id_range) = q.id_range {
+                builder.set_id_range_start(id_range.start.as_bytes());
+                builder.set_id_range_end(id_range.end.as_bytes());
+            }
+
             builder.set_limit(q.limit);
         }
         indradb::VertexQuery::Specific(q) => {
@@ -130,6 +140,20 @@ pub fn to_vertex_query<'a>(reader: &autogen::vertex_query::Reader<'a>) -> Result
                 range = range.t(map_capnp_err(indradb::Type::new(t_str))?);
             }
 
+            let type_prefix = params.get_type_prefix()?;
+            if type_prefix != "" {
+                range = range.with_type_prefix(type_prefix);
+            }
+
+            let id_range_start_bytes = params.get_id_range_start()?;
+            let id_range_end_bytes = params.get_id_range_end()?;
+            if !id_range_start_bytes.is_empty() && !id_range_end_bytes.is_empty() {
+                range = range.with_id_range(
+                    map_capnp_err(Uuid::from_slice(id_range_start_bytes))?,
+                    map_capnp_err(Uuid::from_slice(id_range_end_bytes))?,
+                );
+            }
+
             Ok(range.into())
         }
         autogen::vertex_query::Specific(params) => {
@@ -197,6 +221,25 @@ pub fn from_edge_query<'a>(q: &indradb::EdgeQuery, builder: autogen::edge_query:
                 builder.set_low(low.timestamp_nanos() as u64);
             }
 
+            if let Some(type_prefix) = &pipe.type_prefix {
+                builder.set_type_prefix(type_prefix);
+            }
+
+            if let Some(types) = &pipe.types {
+                let mut type_in = builder.reborrow().init_type_in(types.len() as u32);
+
+                for (i, t) in types.iter().enumerate() {
+                    type_in.set(i as u32, &t.0);
+                }
+            }
+
+            builder.set_undirected(pipe.undirected);
+
+            if let Some(sort) = pipe.sort {
+                builder.set_sort_by(from_sort_key(sort));
+                builder.set_sort_ascending(sort.ascending());
+            }
+
             builder.set_limit(pipe.limit);
             from_vertex_query(&pipe.inner, builder.init_inner());
         }
@@ -232,6 +275,30 @@ pub fn to_edge_query<'a>(reader: &autogen::edge_query::Reader<'a>) -> Result<ind
                 pipe = pipe.low(low);
             }
 
+            let type_prefix = params.get_type_prefix()?;
+            if type_prefix != "" {
+                pipe = pipe.with_type_prefix(type_prefix);
+            }
+
+            // An unset list means there's no filter, as opposed to an
+            // empty one, which matches nothing
+            if params.has_type_in() {
+                let types: Result<Vec<indradb::Type>, CapnpError> = params
+                    .get_type_in()?
+                    .iter()
+                    .map(|t| map_capnp_err(indradb::Type::new(t?)))
+                    .collect();
+                pipe = pipe.with_type_in(types?);
+            }
+
+            if params.get_undirected() {
+                pipe = pipe.undirected();
+            }
+
+            if let Some(sort) = to_sort_key(params.get_sort_by()?, params.get_sort_ascending()) {
+                pipe = pipe.sort_by(sort);
+            }
+
             Ok(indradb::EdgeQuery::Pipe(pipe))
         }
     }
@@ -332,6 +399,21 @@ pub fn to_edge_direction(direction: autogen::EdgeDirection) -> indradb::EdgeDire
     }
 }
 
+pub fn from_sort_key(sort: indradb::SortKey) -> autogen::SortKey {
+    match sort {
+        indradb::SortKey::UpdatedAt { .. } => autogen::SortKey::UpdatedAt,
+        indradb::SortKey::Id { .. } => autogen::SortKey::Id,
+    }
+}
+
+pub fn to_sort_key(sort: autogen::SortKey, ascending: bool) -> Option<indradb::SortKey> {
+    match sort {
+        autogen::SortKey::Unsorted => None,
+        autogen::SortKey::UpdatedAt => Some(indradb::SortKey::UpdatedAt { ascending }),
+        autogen::SortKey::Id => Some(indradb::SortKey::Id { ascending }),
+    }
+}
+
 pub fn to_optional_datetime(timestamp: u64) -> Option<DateTime<Utc>> {
     if timestamp == 0 {
         None
@@ -1,3 +1,4 @@
+use capnp::{Error as CapnpError, ErrorKind as CapnpErrorKind};
 use std::io;
 use std::result::Result as StdResult;
 
@@ -7,6 +8,24 @@ pub enum Error {
     Io { inner: io::Error },
     #[fail(display = "could not parse")]
     CouldNotParse,
+    #[fail(
+        display = "unsupported datastore scheme `{}`; expected `memory` or `rocksdb`",
+        scheme
+    )]
+    UnsupportedScheme { scheme: String },
+    #[fail(display = "invalid TLS certificate or key")]
+    InvalidTls,
+    #[fail(display = "rpc error: {}", inner)]
+    Rpc { inner: CapnpError },
+    #[fail(display = "rate limited")]
+    RateLimited,
+    #[fail(
+        display = "client protocol version {} is incompatible with server protocol version {}",
+        client, server
+    )]
+    ProtocolMismatch { client: u32, server: u32 },
+    #[fail(display = "datastore error: {}", inner)]
+    Datastore { inner: indradb::Error },
 }
 
 impl From<io::Error> for Error {
@@ -15,4 +34,47 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<indradb::Error> for Error {
+    fn from(err: indradb::Error) -> Self {
+        Error::Datastore { inner: err }
+    }
+}
+
+impl From<CapnpError> for Error {
+    fn from(err: CapnpError) -> Self {
+        // The server only reports overloading when a connection exceeds its
+        // rate limit
+        match err.kind {
+            CapnpErrorKind::Overloaded => Error::RateLimited,
+            CapnpErrorKind::Failed => match parse_protocol_mismatch(&err.description) {
+                Some((client, server)) => Error::ProtocolMismatch { client, server },
+                None => Error::Rpc { inner: err },
+            },
+            _ => Error::Rpc { inner: err },
+        }
+    }
+}
+
+// The server fails requests on connections that haven't completed a
+// handshake with this prefix, followed by the client's and server's
+// protocol versions. The description may be prefixed further by the RPC
+// system by the time it reaches the client.
+const PROTOCOL_MISMATCH_PREFIX: &str = "protocol mismatch: ";
+
+// Creates the error the server fails requests with when the client hasn't
+// completed a handshake. Clients map it to `Error::ProtocolMismatch`.
+pub(crate) fn protocol_mismatch_rpc_error(client: u32, server: u32) -> CapnpError {
+    CapnpError::failed(format!("{}{} {}", PROTOCOL_MISMATCH_PREFIX, client, server))
+}
+
+fn parse_protocol_mismatch(description: &str) -> Option<(u32, u32)> {
+    let start = description.find(PROTOCOL_MISMATCH_PREFIX)? + PROTOCOL_MISMATCH_PREFIX.len();
+    let mut versions = description[start..].split(' ').map(|version| version.parse());
+
+    match (versions.next(), versions.next()) {
+        (Some(Ok(client)), Some(Ok(server))) => Some((client, server)),
+        _ => None,
+    }
+}
+
 pub type Result<T> = StdResult<T, Error>;
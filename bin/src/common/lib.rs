@@ -7,12 +7,15 @@ extern crate core;
 extern crate failure;
 extern crate futures;
 extern crate futures_cpupool;
+extern crate num_cpus;
 #[cfg(test)]
 #[macro_use]
 extern crate lazy_static;
 extern crate serde_json;
 extern crate tokio_core;
 extern crate tokio_io;
+extern crate tokio_rustls;
+extern crate webpki;
 extern crate uuid;
 
 #[cfg(test)]
@@ -24,6 +27,7 @@ extern crate indradb;
 pub mod autogen;
 #[macro_use]
 pub mod converters;
+pub mod async_client_datastore;
 pub mod client_datastore;
 pub mod errors;
 pub mod server;
@@ -31,4 +35,5 @@ pub mod server;
 #[cfg(test)]
 mod tests;
 
+pub use crate::async_client_datastore::{AsyncClientDatastore, AsyncClientTransaction};
 pub use crate::client_datastore::ClientDatastore;
@@ -6,35 +6,164 @@ use capnp_rpc::twoparty::VatNetwork;
 use capnp_rpc::{RpcSystem, Server};
 use crate::converters;
 use crate::errors;
+use futures::future;
+use futures::sync::oneshot;
 use futures::{Future, Stream};
-use futures_cpupool::CpuPool;
+use futures_cpupool::{Builder as CpuPoolBuilder, CpuPool};
 use indradb;
+use num_cpus;
 use indradb::{
-    Datastore as IndraDbDatastore, Edge, EdgeProperty, MemoryDatastore, RocksdbDatastore,
-    Transaction as IndraDbTransaction, Type, Vertex, VertexProperty,
+    BulkInsertItem, Datastore as IndraDbDatastore, Edge, EdgeKey, EdgeProperty, EdgePropertyQuery, EdgeQuery,
+    LimitedDatastore, MemoryDatastore, RocksdbDatastore, SpecificVertexQuery, Transaction as IndraDbTransaction, Type,
+    Vertex, VertexProperty, VertexPropertyQuery, VertexQuery,
 };
 use serde_json;
+use serde_json::Value as JsonValue;
+use std::cell::RefCell;
 use std::env;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
 use std::net::SocketAddr;
 use std::net::ToSocketAddrs;
-use std::sync::Arc;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tokio_core::net::TcpListener;
 use tokio_core::reactor::Core;
-use tokio_io::AsyncRead;
+use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_rustls::rustls::internal::pemfile;
+use tokio_rustls::rustls::{NoClientAuth, ServerConfig};
+use tokio_rustls::TlsAcceptor;
 use uuid::Uuid;
 
+/// The version of the protocol spoken between clients and the server. This
+/// is bumped whenever the schema changes in a way that older clients or
+/// servers would misread.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// A token bucket, which allows bursts of up to a second's worth of
+// requests.
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Limits the rate of requests on a connection. The limiter is shared by
+/// the connection's service and all of its transactions.
+#[derive(Clone)]
+struct RateLimiter(Option<Rc<RefCell<TokenBucket>>>);
+
+impl RateLimiter {
+    fn new(requests_per_second: Option<u32>) -> Self {
+        RateLimiter(requests_per_second.map(|rate| {
+            let rate = f64::from(rate);
+
+            Rc::new(RefCell::new(TokenBucket {
+                rate,
+                tokens: rate,
+                last_refill: Instant::now(),
+            }))
+        }))
+    }
+
+    // Takes a token for a request, or returns an overloaded error if there
+    // are none left. Clients map the error to `Error::RateLimited`.
+    fn acquire(&self) -> Result<(), CapnpError> {
+        let mut bucket = match self.0 {
+            Some(ref bucket) => bucket.borrow_mut(),
+            None => return Ok(()),
+        };
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill);
+        let elapsed_secs = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1_000_000_000.0;
+        bucket.tokens = (bucket.tokens + elapsed_secs * bucket.rate).min(bucket.rate);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(CapnpError::overloaded("rate limit exceeded".to_string()))
+        }
+    }
+}
+
+/// A write made through the server. See `ServerOptions::events`.
+///
+/// Deletes and property changes are reported with the query they were made
+/// with, rather than with the items they affected.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MutationEvent {
+    BulkInserted(Vec<BulkInsertItem>),
+    VertexCreated(Vertex),
+    VerticesDeleted(VertexQuery),
+    VerticesDeletedByType(Type),
+    EdgeCreated(EdgeKey),
+    EdgesDeleted(EdgeQuery),
+    EdgeTypeRenamed { from: Type, to: Type },
+    VertexPropertiesSet(VertexPropertyQuery, JsonValue),
+    VertexPropertiesDeleted(VertexPropertyQuery),
+    EdgePropertiesSet(EdgePropertyQuery, JsonValue),
+    EdgePropertiesDeleted(EdgePropertyQuery),
+}
+
+// Reports writes to the sender configured in `ServerOptions::events`, if
+// there is one.
+#[derive(Clone)]
+struct EventSender(Option<Sender<MutationEvent>>);
+
+impl EventSender {
+    fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    fn send(&self, event: MutationEvent) {
+        if let Some(ref sender) = self.0 {
+            // Delivery is best-effort, so it's fine if nothing's listening
+            let _ = sender.send(event);
+        }
+    }
+}
+
 struct Service<D: IndraDbDatastore<Trans = T> + Send + Sync + 'static, T: IndraDbTransaction + Send + Sync + 'static> {
     datastore: Arc<D>,
     pool: CpuPool,
+    rate_limiter: RateLimiter,
+    events: EventSender,
+    // The protocol version the client sent in its latest handshake, if it
+    // has sent one
+    client_version: Option<u32>,
 }
 
 impl<D: IndraDbDatastore<Trans = T> + Send + Sync + 'static, T: IndraDbTransaction + Send + Sync + 'static>
     Service<D, T>
 {
-    fn new(datastore: D, worker_count: usize) -> Self {
+    fn new(datastore: Arc<D>, pool: CpuPool, rate_limiter: RateLimiter, events: EventSender) -> Self {
         Self {
-            datastore: Arc::new(datastore),
-            pool: CpuPool::new(worker_count),
+            datastore,
+            pool,
+            rate_limiter,
+            events,
+            client_version: None,
+        }
+    }
+
+    // Fails requests until the client has completed a handshake with a
+    // matching version. Clients that never send one predate the handshake,
+    // so they're reported as version 0. Clients map the error to
+    // `Error::ProtocolMismatch`.
+    fn check_handshake(&self) -> Result<(), CapnpError> {
+        match self.client_version {
+            Some(PROTOCOL_VERSION) => Ok(()),
+            client_version => Err(errors::protocol_mismatch_rpc_error(
+                client_version.unwrap_or(0),
+                PROTOCOL_VERSION,
+            )),
         }
     }
 }
@@ -47,23 +176,83 @@ impl<D: IndraDbDatastore<Trans = T> + Send + Sync + 'static, T: IndraDbTransacti
         _: autogen::service::PingParams,
         mut res: autogen::service::PingResults,
     ) -> Promise<(), CapnpError> {
+        // Pings aren't rate limited, since they don't use the workers, and
+        // liveness checks should keep working for busy connections
         res.get().set_ready(true);
         Promise::ok(())
     }
 
+    fn handshake(
+        &mut self,
+        req: autogen::service::HandshakeParams,
+        mut res: autogen::service::HandshakeResults,
+    ) -> Promise<(), CapnpError> {
+        let client_version = pry!(req.get()).get_version();
+        self.client_version = Some(client_version);
+        let mut res = res.get();
+        res.set_version(PROTOCOL_VERSION);
+        res.set_accepted(client_version == PROTOCOL_VERSION);
+        Promise::ok(())
+    }
+
+    fn ready(
+        &mut self,
+        _: autogen::service::ReadyParams,
+        mut res: autogen::service::ReadyResults,
+    ) -> Promise<(), CapnpError> {
+        pry!(self.check_handshake());
+        pry!(self.rate_limiter.acquire());
+
+        let datastore = self.datastore.clone();
+
+        let f = self
+            .pool
+            .spawn_fn(move || -> Result<bool, CapnpError> {
+                // Looking up a single, almost certainly missing vertex is
+                // about the cheapest request that still reaches the
+                // underlying database
+                let result = datastore
+                    .transaction()
+                    .and_then(|trans| trans.get_vertices(SpecificVertexQuery::single(Uuid::default())));
+                Ok(result.is_ok())
+            })
+            .and_then(move |ready| -> Result<(), CapnpError> {
+                res.get().set_ready(ready);
+                Ok(())
+            });
+
+        Promise::from_future(f)
+    }
+
     fn bulk_insert(
         &mut self,
         req: autogen::service::BulkInsertParams,
         mut res: autogen::service::BulkInsertResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.check_handshake());
+        pry!(self.rate_limiter.acquire());
+
         let datastore = self.datastore.clone();
+        let events = self.events.clone();
         let cnp_items = pry!(pry!(req.get()).get_items());
         let items = pry!(converters::to_bulk_insert_items(&cnp_items));
 
+        // Only copy the items if there's something to report them to
+        let inserted = if events.is_enabled() {
+            Some(items.as_slice().to_vec())
+        } else {
+            None
+        };
+
         let f = self
             .pool
             .spawn_fn(move || -> Result<(), CapnpError> {
                 converters::map_capnp_err(datastore.bulk_insert(items))?;
+
+                if let Some(inserted) = inserted {
+                    events.send(MutationEvent::BulkInserted(inserted));
+                }
+
                 Ok(())
             })
             .and_then(move |_| -> Result<(), CapnpError> {
@@ -79,8 +268,11 @@ impl<D: IndraDbDatastore<Trans = T> + Send + Sync + 'static, T: IndraDbTransacti
         _: autogen::service::TransactionParams,
         mut res: autogen::service::TransactionResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.check_handshake());
+        pry!(self.rate_limiter.acquire());
+
         let trans = pry!(converters::map_capnp_err(self.datastore.transaction()));
-        let trans_server = Transaction::new(self.pool.clone(), trans);
+        let trans_server = Transaction::new(self.pool.clone(), trans, self.rate_limiter.clone(), self.events.clone());
         let trans_client = autogen::transaction::ToClient::new(trans_server).into_client::<Server>();
         res.get().set_transaction(trans_client);
         Promise::ok(())
@@ -90,13 +282,17 @@ impl<D: IndraDbDatastore<Trans = T> + Send + Sync + 'static, T: IndraDbTransacti
 struct Transaction<T: IndraDbTransaction + Send + Sync + 'static> {
     pool: CpuPool,
     trans: Arc<T>,
+    rate_limiter: RateLimiter,
+    events: EventSender,
 }
 
 impl<T: IndraDbTransaction + Send + Sync + 'static> Transaction<T> {
-    fn new(pool: CpuPool, trans: T) -> Self {
+    fn new(pool: CpuPool, trans: T, rate_limiter: RateLimiter, events: EventSender) -> Self {
         Self {
             pool,
             trans: Arc::new(trans),
+            rate_limiter,
+            events,
         }
     }
 }
@@ -107,13 +303,24 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         req: autogen::transaction::CreateVertexParams,
         mut res: autogen::transaction::CreateVertexResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
+        let events = self.events.clone();
         let cnp_vertex = pry!(pry!(req.get()).get_vertex());
         let vertex = pry!(converters::to_vertex(&cnp_vertex));
 
         let f = self
             .pool
-            .spawn_fn(move || -> Result<bool, CapnpError> { converters::map_capnp_err(trans.create_vertex(&vertex)) })
+            .spawn_fn(move || -> Result<bool, CapnpError> {
+                let created = converters::map_capnp_err(trans.create_vertex(&vertex))?;
+
+                if created {
+                    events.send(MutationEvent::VertexCreated(vertex));
+                }
+
+                Ok(created)
+            })
             .and_then(move |created| -> Result<(), CapnpError> {
                 res.get().set_result(created);
                 Ok(())
@@ -127,14 +334,19 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         req: autogen::transaction::CreateVertexFromTypeParams,
         mut res: autogen::transaction::CreateVertexFromTypeResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
+        let events = self.events.clone();
         let cnp_t = pry!(pry!(req.get()).get_t());
         let t = pry!(converters::map_capnp_err(indradb::Type::new(cnp_t)));
 
         let f = self
             .pool
             .spawn_fn(move || -> Result<Uuid, CapnpError> {
-                converters::map_capnp_err(trans.create_vertex_from_type(t))
+                let id = converters::map_capnp_err(trans.create_vertex_from_type(t.clone()))?;
+                events.send(MutationEvent::VertexCreated(Vertex::with_id(id, t)));
+                Ok(id)
             })
             .and_then(move |id| -> Result<(), CapnpError> {
                 res.get().set_result(id.as_bytes());
@@ -149,6 +361,8 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         req: autogen::transaction::GetVerticesParams,
         mut res: autogen::transaction::GetVerticesResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
         let cnp_q = pry!(pry!(req.get()).get_q());
         let q = pry!(converters::to_vertex_query(&cnp_q));
@@ -174,14 +388,18 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         req: autogen::transaction::DeleteVerticesParams,
         mut res: autogen::transaction::DeleteVerticesResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
+        let events = self.events.clone();
         let cnp_q = pry!(pry!(req.get()).get_q());
         let q = pry!(converters::to_vertex_query(&cnp_q));
 
         let f = self
             .pool
             .spawn_fn(move || -> Result<(), CapnpError> {
-                converters::map_capnp_err(trans.delete_vertices(q))?;
+                converters::map_capnp_err(trans.delete_vertices(q.clone()))?;
+                events.send(MutationEvent::VerticesDeleted(q));
                 Ok(())
             })
             .and_then(move |_| -> Result<(), CapnpError> {
@@ -197,6 +415,8 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         _: autogen::transaction::GetVertexCountParams,
         mut res: autogen::transaction::GetVertexCountResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
 
         let f = self
@@ -210,18 +430,55 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         Promise::from_future(f)
     }
 
+    fn count_vertices(
+        &mut self,
+        req: autogen::transaction::CountVerticesParams,
+        mut res: autogen::transaction::CountVerticesResults,
+    ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
+        let trans = self.trans.clone();
+        let t = match pry!(pry!(req.get()).get_t()) {
+            "" => None,
+            value => Some(pry!(converters::map_capnp_err(Type::new(value)))),
+        };
+
+        let f = self
+            .pool
+            .spawn_fn(move || -> Result<u64, CapnpError> {
+                converters::map_capnp_err(trans.count_vertices(t.as_ref()))
+            })
+            .and_then(move |count| -> Result<(), CapnpError> {
+                res.get().set_result(count);
+                Ok(())
+            });
+
+        Promise::from_future(f)
+    }
+
     fn create_edge(
         &mut self,
         req: autogen::transaction::CreateEdgeParams,
         mut res: autogen::transaction::CreateEdgeResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
+        let events = self.events.clone();
         let cnp_edge_key = pry!(pry!(req.get()).get_key());
         let edge_key = pry!(converters::to_edge_key(&cnp_edge_key));
 
         let f = self
             .pool
-            .spawn_fn(move || -> Result<bool, CapnpError> { converters::map_capnp_err(trans.create_edge(&edge_key)) })
+            .spawn_fn(move || -> Result<bool, CapnpError> {
+                let created = converters::map_capnp_err(trans.create_edge(&edge_key))?;
+
+                if created {
+                    events.send(MutationEvent::EdgeCreated(edge_key));
+                }
+
+                Ok(created)
+            })
             .and_then(move |created| -> Result<(), CapnpError> {
                 res.get().set_result(created);
                 Ok(())
@@ -230,11 +487,55 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         Promise::from_future(f)
     }
 
+    fn create_edges(
+        &mut self,
+        req: autogen::transaction::CreateEdgesParams,
+        mut res: autogen::transaction::CreateEdgesResults,
+    ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
+        let trans = self.trans.clone();
+        let events = self.events.clone();
+        let cnp_edge_keys = pry!(pry!(req.get()).get_keys());
+        let edge_keys: Result<Vec<EdgeKey>, CapnpError> = cnp_edge_keys
+            .into_iter()
+            .map(|reader| converters::to_edge_key(&reader))
+            .collect();
+        let edge_keys = pry!(edge_keys);
+
+        let f = self
+            .pool
+            .spawn_fn(move || -> Result<Vec<bool>, CapnpError> {
+                let results = converters::map_capnp_err(trans.create_edges(&edge_keys))?;
+
+                for (edge_key, created) in edge_keys.into_iter().zip(&results) {
+                    if *created {
+                        events.send(MutationEvent::EdgeCreated(edge_key));
+                    }
+                }
+
+                Ok(results)
+            })
+            .and_then(move |results| -> Result<(), CapnpError> {
+                let mut res = res.get().init_result(results.len() as u32);
+
+                for (i, created) in results.into_iter().enumerate() {
+                    res.set(i as u32, created);
+                }
+
+                Ok(())
+            });
+
+        Promise::from_future(f)
+    }
+
     fn get_edges(
         &mut self,
         req: autogen::transaction::GetEdgesParams,
         mut res: autogen::transaction::GetEdgesResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
         let cnp_q = pry!(pry!(req.get()).get_q());
         let q = pry!(converters::to_edge_query(&cnp_q));
@@ -255,19 +556,45 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         Promise::from_future(f)
     }
 
+    fn has_edge(
+        &mut self,
+        req: autogen::transaction::HasEdgeParams,
+        mut res: autogen::transaction::HasEdgeResults,
+    ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
+        let trans = self.trans.clone();
+        let cnp_edge_key = pry!(pry!(req.get()).get_key());
+        let edge_key = pry!(converters::to_edge_key(&cnp_edge_key));
+
+        let f = self
+            .pool
+            .spawn_fn(move || -> Result<bool, CapnpError> { converters::map_capnp_err(trans.has_edge(&edge_key)) })
+            .and_then(move |exists| -> Result<(), CapnpError> {
+                res.get().set_result(exists);
+                Ok(())
+            });
+
+        Promise::from_future(f)
+    }
+
     fn delete_edges(
         &mut self,
         req: autogen::transaction::DeleteEdgesParams,
         mut res: autogen::transaction::DeleteEdgesResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
+        let events = self.events.clone();
         let cnp_q = pry!(pry!(req.get()).get_q());
         let q = pry!(converters::to_edge_query(&cnp_q));
 
         let f = self
             .pool
             .spawn_fn(move || -> Result<(), CapnpError> {
-                converters::map_capnp_err(trans.delete_edges(q))?;
+                converters::map_capnp_err(trans.delete_edges(q.clone()))?;
+                events.send(MutationEvent::EdgesDeleted(q));
                 Ok(())
             })
             .and_then(move |_| -> Result<(), CapnpError> {
@@ -283,6 +610,8 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         req: autogen::transaction::GetEdgeCountParams,
         mut res: autogen::transaction::GetEdgeCountResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
         let params = pry!(req.get());
         let id = pry!(converters::map_capnp_err(Uuid::from_slice(pry!(params.get_id()))));
@@ -305,11 +634,92 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         Promise::from_future(f)
     }
 
+    fn count_edges(
+        &mut self,
+        req: autogen::transaction::CountEdgesParams,
+        mut res: autogen::transaction::CountEdgesResults,
+    ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
+        let trans = self.trans.clone();
+        let t = match pry!(pry!(req.get()).get_t()) {
+            "" => None,
+            value => Some(pry!(converters::map_capnp_err(Type::new(value)))),
+        };
+
+        let f = self
+            .pool
+            .spawn_fn(move || -> Result<u64, CapnpError> { converters::map_capnp_err(trans.count_edges(t.as_ref())) })
+            .and_then(move |count| -> Result<(), CapnpError> {
+                res.get().set_result(count);
+                Ok(())
+            });
+
+        Promise::from_future(f)
+    }
+
+    fn delete_vertices_by_type(
+        &mut self,
+        req: autogen::transaction::DeleteVerticesByTypeParams,
+        mut res: autogen::transaction::DeleteVerticesByTypeResults,
+    ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
+        let trans = self.trans.clone();
+        let events = self.events.clone();
+        let params = pry!(req.get());
+        let t = pry!(converters::map_capnp_err(Type::new(pry!(params.get_t()))));
+
+        let f = self
+            .pool
+            .spawn_fn(move || -> Result<u64, CapnpError> {
+                let count = converters::map_capnp_err(trans.delete_vertices_by_type(t.clone()))?;
+                events.send(MutationEvent::VerticesDeletedByType(t));
+                Ok(count)
+            })
+            .and_then(move |count| -> Result<(), CapnpError> {
+                res.get().set_result(count);
+                Ok(())
+            });
+
+        Promise::from_future(f)
+    }
+
+    fn rename_edge_type(
+        &mut self,
+        req: autogen::transaction::RenameEdgeTypeParams,
+        mut res: autogen::transaction::RenameEdgeTypeResults,
+    ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
+        let trans = self.trans.clone();
+        let events = self.events.clone();
+        let params = pry!(req.get());
+        let from = pry!(converters::map_capnp_err(Type::new(pry!(params.get_from()))));
+        let to = pry!(converters::map_capnp_err(Type::new(pry!(params.get_to()))));
+
+        let f = self
+            .pool
+            .spawn_fn(move || -> Result<u64, CapnpError> {
+                let count = converters::map_capnp_err(trans.rename_edge_type(from.clone(), to.clone()))?;
+                events.send(MutationEvent::EdgeTypeRenamed { from, to });
+                Ok(count)
+            })
+            .and_then(move |count| -> Result<(), CapnpError> {
+                res.get().set_result(count);
+                Ok(())
+            });
+
+        Promise::from_future(f)
+    }
+
     fn get_vertex_properties(
         &mut self,
         req: autogen::transaction::GetVertexPropertiesParams,
         mut res: autogen::transaction::GetVertexPropertiesResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
         let params = pry!(req.get());
         let cnp_q = pry!(params.get_q());
@@ -338,7 +748,10 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         req: autogen::transaction::SetVertexPropertiesParams,
         mut res: autogen::transaction::SetVertexPropertiesResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
+        let events = self.events.clone();
         let params = pry!(req.get());
         let cnp_q = pry!(params.get_q());
         let q = pry!(converters::to_vertex_property_query(&cnp_q));
@@ -348,7 +761,9 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         let f = self
             .pool
             .spawn_fn(move || -> Result<(), CapnpError> {
-                converters::map_capnp_err(trans.set_vertex_properties(q, &value))
+                converters::map_capnp_err(trans.set_vertex_properties(q.clone(), &value))?;
+                events.send(MutationEvent::VertexPropertiesSet(q, value));
+                Ok(())
             })
             .and_then(move |_| -> Result<(), CapnpError> {
                 res.get().set_result(());
@@ -363,7 +778,10 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         req: autogen::transaction::DeleteVertexPropertiesParams,
         mut res: autogen::transaction::DeleteVertexPropertiesResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
+        let events = self.events.clone();
         let params = pry!(req.get());
         let cnp_q = pry!(params.get_q());
         let q = pry!(converters::to_vertex_property_query(&cnp_q));
@@ -371,7 +789,9 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         let f = self
             .pool
             .spawn_fn(move || -> Result<(), CapnpError> {
-                converters::map_capnp_err(trans.delete_vertex_properties(q))
+                converters::map_capnp_err(trans.delete_vertex_properties(q.clone()))?;
+                events.send(MutationEvent::VertexPropertiesDeleted(q));
+                Ok(())
             })
             .and_then(move |_| -> Result<(), CapnpError> {
                 res.get().set_result(());
@@ -386,6 +806,8 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         req: autogen::transaction::GetEdgePropertiesParams,
         mut res: autogen::transaction::GetEdgePropertiesResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
         let params = pry!(req.get());
         let cnp_q = pry!(params.get_q());
@@ -414,7 +836,10 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         req: autogen::transaction::SetEdgePropertiesParams,
         mut res: autogen::transaction::SetEdgePropertiesResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
+        let events = self.events.clone();
         let params = pry!(req.get());
         let cnp_q = pry!(params.get_q());
         let q = pry!(converters::to_edge_property_query(&cnp_q));
@@ -424,7 +849,9 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         let f = self
             .pool
             .spawn_fn(move || -> Result<(), CapnpError> {
-                converters::map_capnp_err(trans.set_edge_properties(q, &value))
+                converters::map_capnp_err(trans.set_edge_properties(q.clone(), &value))?;
+                events.send(MutationEvent::EdgePropertiesSet(q, value));
+                Ok(())
             })
             .and_then(move |_| -> Result<(), CapnpError> {
                 res.get().set_result(());
@@ -439,14 +866,21 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
         req: autogen::transaction::DeleteEdgePropertiesParams,
         mut res: autogen::transaction::DeleteEdgePropertiesResults,
     ) -> Promise<(), CapnpError> {
+        pry!(self.rate_limiter.acquire());
+
         let trans = self.trans.clone();
+        let events = self.events.clone();
         let params = pry!(req.get());
         let cnp_q = pry!(params.get_q());
         let q = pry!(converters::to_edge_property_query(&cnp_q));
 
         let f = self
             .pool
-            .spawn_fn(move || -> Result<(), CapnpError> { converters::map_capnp_err(trans.delete_edge_properties(q)) })
+            .spawn_fn(move || -> Result<(), CapnpError> {
+                converters::map_capnp_err(trans.delete_edge_properties(q.clone()))?;
+                events.send(MutationEvent::EdgePropertiesDeleted(q));
+                Ok(())
+            })
             .and_then(move |_| -> Result<(), CapnpError> {
                 res.get().set_result(());
                 Ok(())
@@ -456,55 +890,304 @@ impl<T: IndraDbTransaction + Send + Sync + 'static> autogen::transaction::Server
     }
 }
 
-fn run<D, T>(addr: SocketAddr, datastore: D, worker_count: usize) -> Result<(), errors::Error>
+/// A kind of datastore, as parsed from a connection string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DatastoreKind {
+    /// An in-memory datastore, from `memory://`.
+    Memory,
+    /// A RocksDB datastore, from `rocksdb://<path>`.
+    Rocksdb { path: String },
+}
+
+/// Parses a datastore connection string, e.g. `memory://` or
+/// `rocksdb:///var/lib/indradb`.
+///
+/// # Errors
+/// Returns `Error::UnsupportedScheme` if the connection string uses a scheme
+/// other than `memory` or `rocksdb`, or `Error::CouldNotParse` if it has no
+/// scheme at all, or is otherwise malformed.
+pub fn parse_datastore_uri(s: &str) -> Result<DatastoreKind, errors::Error> {
+    if s.starts_with("rocksdb://") {
+        Ok(DatastoreKind::Rocksdb {
+            path: s[10..].to_string(),
+        })
+    } else if s == "memory://" {
+        Ok(DatastoreKind::Memory)
+    } else {
+        match s.find("://") {
+            Some(i) if &s[..i] != "memory" => Err(errors::Error::UnsupportedScheme {
+                scheme: s[..i].to_string(),
+            }),
+            _ => Err(errors::Error::CouldNotParse),
+        }
+    }
+}
+
+/// Loads the certificate chain and private key for serving over TLS.
+///
+/// # Arguments
+/// * `cert_path` - The path to the PEM-encoded certificate chain.
+/// * `key_path` - The path to the PEM-encoded private key, in either PKCS #8
+///   or RSA format.
+///
+/// # Errors
+/// Returns `Error::InvalidTls` if either file can't be parsed, or if the key
+/// doesn't match the certificate.
+pub fn load_tls_config(cert_path: &str, key_path: &str) -> Result<Arc<ServerConfig>, errors::Error> {
+    let certs = pemfile::certs(&mut BufReader::new(File::open(cert_path)?)).map_err(|_| errors::Error::InvalidTls)?;
+    let mut keys = pemfile::pkcs8_private_keys(&mut BufReader::new(File::open(key_path)?))
+        .map_err(|_| errors::Error::InvalidTls)?;
+
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(&mut BufReader::new(File::open(key_path)?))
+            .map_err(|_| errors::Error::InvalidTls)?;
+    }
+
+    let key = keys.into_iter().next().ok_or(errors::Error::InvalidTls)?;
+    let mut config = ServerConfig::new(NoClientAuth::new());
+    config
+        .set_single_cert(certs, key)
+        .map_err(|_| errors::Error::InvalidTls)?;
+    Ok(Arc::new(config))
+}
+
+fn serve_connection<S>(stream: S, service: autogen::service::Client) -> impl Future<Item = (), Error = ()>
+where
+    S: AsyncRead + AsyncWrite + 'static,
+{
+    let (reader, writer) = stream.split();
+    let rpc_network = VatNetwork::new(reader, writer, Side::Server, Default::default());
+    let rpc_system = RpcSystem::new(Box::new(rpc_network), Some(service.client));
+    rpc_system.map_err(|_| ())
+}
+
+fn run<D, T, S>(
+    addr: SocketAddr,
+    datastore: D,
+    worker_count: usize,
+    options: ServerOptions,
+    shutdown: S,
+) -> Result<(), errors::Error>
 where
     D: IndraDbDatastore<Trans = T> + Send + Sync + 'static,
     T: IndraDbTransaction + Send + Sync + 'static,
+    S: Future<Item = (), Error = ()>,
 {
     let mut core = Core::new().unwrap();
     let handle = core.handle();
     let socket = TcpListener::bind(&addr, &handle)?;
 
-    let service = autogen::service::ToClient::new(Service::new(datastore, worker_count)).into_client::<Server>();
+    // Workers only stop once every handle to the pool is gone and the work
+    // already queued on it is done, so shutdown waits to hear from each
+    let (stopped_sender, stopped_receiver) = mpsc::channel();
+    let stopped_sender = Mutex::new(stopped_sender);
+    let pool = CpuPoolBuilder::new()
+        .pool_size(worker_count)
+        .before_stop(move || {
+            let _ = stopped_sender.lock().unwrap().send(());
+        })
+        .create();
+
+    let datastore = Arc::new(datastore);
+    let service_datastore = datastore.clone();
+    let service_pool = pool.clone();
+    let rate_limit = options.rate_limit;
+    let events = EventSender(options.events);
+    let tls_acceptor = options.tls.map(TlsAcceptor::from);
 
     let done = socket.incoming().for_each(move |(socket, _)| {
         socket.set_nodelay(true)?;
-        let (reader, writer) = socket.split();
-        let rpc_network = VatNetwork::new(reader, writer, Side::Server, Default::default());
-        let rpc_system = RpcSystem::new(Box::new(rpc_network), Some(service.clone().client));
-        handle.spawn(rpc_system.map_err(|_| ()));
+
+        // Each connection gets its own service, so that it can be rate
+        // limited separately
+        let service = Service::new(
+            service_datastore.clone(),
+            service_pool.clone(),
+            RateLimiter::new(rate_limit),
+            events.clone(),
+        );
+        let service = autogen::service::ToClient::new(service).into_client::<Server>();
+
+        match tls_acceptor {
+            Some(ref tls_acceptor) => {
+                // Connections that fail the handshake are just dropped
+                let f = tls_acceptor
+                    .accept(socket)
+                    .map_err(|_| ())
+                    .and_then(move |stream| serve_connection(stream, service));
+
+                handle.spawn(f);
+            }
+            None => handle.spawn(serve_connection(socket, service)),
+        }
+
         Ok(())
     });
 
-    core.run(done).unwrap();
+    // Stop accepting connections once the shutdown signal fires. Dropping
+    // the core afterwards closes the listener and any open connections,
+    // along with the transactions they had open.
+    let shutdown = shutdown.then(|_| -> Result<(), io::Error> { Ok(()) });
+    core.run(done.select(shutdown)).map_err(|(err, _)| err)?;
+    drop(core);
+
+    // Wait for requests that were already running on the workers, which
+    // hold their own references to the datastore
+    drop(pool);
+
+    for _ in 0..worker_count {
+        let _ = stopped_receiver.recv();
+    }
+
+    datastore.flush()?;
     Ok(())
 }
 
+/// The number of workers used when the worker count isn't specified: two
+/// per available CPU.
+pub fn default_worker_count() -> usize {
+    num_cpus::get() * 2
+}
+
+/// Starts the server with `default_worker_count()` workers.
+pub fn start_auto(binding: &str, connection_string: &str) -> Result<(), errors::Error> {
+    start(binding, connection_string, default_worker_count())
+}
+
 pub fn start(binding: &str, connection_string: &str, worker_count: usize) -> Result<(), errors::Error> {
+    let shutdown = future::empty::<(), ()>();
+    serve(
+        binding,
+        connection_string,
+        worker_count,
+        ServerOptions::default(),
+        shutdown,
+    )
+}
+
+/// Starts the server with optional behavior, such as TLS or rate limiting,
+/// configured.
+///
+/// # Arguments
+/// * `binding` - The address to listen on.
+/// * `connection_string` - The datastore connection string.
+/// * `worker_count` - The number of workers.
+/// * `options` - The optional behavior to enable.
+pub fn start_with_options(
+    binding: &str,
+    connection_string: &str,
+    worker_count: usize,
+    options: ServerOptions,
+) -> Result<(), errors::Error> {
+    let shutdown = future::empty::<(), ()>();
+    serve(binding, connection_string, worker_count, options, shutdown)
+}
+
+/// Starts the server, accepting connections over TLS only.
+///
+/// # Arguments
+/// * `binding` - The address to listen on.
+/// * `connection_string` - The datastore connection string.
+/// * `worker_count` - The number of workers.
+/// * `cert_path` - The path to the PEM-encoded certificate chain.
+/// * `key_path` - The path to the PEM-encoded private key.
+pub fn start_tls(
+    binding: &str,
+    connection_string: &str,
+    worker_count: usize,
+    cert_path: &str,
+    key_path: &str,
+) -> Result<(), errors::Error> {
+    let options = ServerOptions {
+        tls: Some(load_tls_config(cert_path, key_path)?),
+        ..ServerOptions::default()
+    };
+
+    start_with_options(binding, connection_string, worker_count, options)
+}
+
+/// Starts the server, and runs it until `shutdown` receives a value or its
+/// sender is dropped. Once this returns, the port is released, requests
+/// that were already running have finished, and the datastore has been
+/// flushed and closed.
+pub fn start_with_shutdown(
+    binding: &str,
+    connection_string: &str,
+    worker_count: usize,
+    shutdown: oneshot::Receiver<()>,
+) -> Result<(), errors::Error> {
+    let shutdown = shutdown.then(|_| -> Result<(), ()> { Ok(()) });
+    serve(
+        binding,
+        connection_string,
+        worker_count,
+        ServerOptions::default(),
+        shutdown,
+    )
+}
+
+/// Optional server behavior, which is all off by default.
+#[derive(Clone, Default)]
+pub struct ServerOptions {
+    /// Serve over TLS only, with this configuration. See
+    /// `load_tls_config`.
+    pub tls: Option<Arc<ServerConfig>>,
+
+    /// Limit each connection to this many requests per second. Connections
+    /// can also burst up to this many requests at once. Requests beyond the
+    /// limit fail, and clients see them as `Error::RateLimited`. Pings
+    /// aren't limited.
+    pub rate_limit: Option<u32>,
+
+    /// Limit queries to returning this many results. Queries that would
+    /// return more fail with `Error::ResultTooLarge` on the server. See
+    /// `indradb::LimitedDatastore`.
+    pub max_results: Option<u32>,
+
+    /// Report each successful write made through the server to this
+    /// channel. Delivery is best-effort: events are dropped if the receiver
+    /// is gone.
+    pub events: Option<Sender<MutationEvent>>,
+}
+
+fn serve<S>(
+    binding: &str,
+    connection_string: &str,
+    worker_count: usize,
+    options: ServerOptions,
+    shutdown: S,
+) -> Result<(), errors::Error>
+where
+    S: Future<Item = (), Error = ()>,
+{
     let addr = binding
         .to_socket_addrs()?
         .next()
         .ok_or_else(|| -> errors::Error { errors::Error::CouldNotParse })?;
 
-    if connection_string.starts_with("rocksdb://") {
-        let path = &connection_string[10..connection_string.len()];
+    let kind = parse_datastore_uri(connection_string)?;
+    let max_results = options.max_results;
 
-        let max_open_files_str = env::var("ROCKSDB_MAX_OPEN_FILES").unwrap_or_else(|_| "512".to_string());
-        let max_open_files = max_open_files_str.parse::<i32>().expect(
-            "Could not parse environment variable `ROCKSDB_MAX_OPEN_FILES`: must be an \
-             i32",
-        );
+    match kind {
+        DatastoreKind::Rocksdb { path } => {
+            let max_open_files_str = env::var("ROCKSDB_MAX_OPEN_FILES").unwrap_or_else(|_| "512".to_string());
+            let max_open_files = max_open_files_str.parse::<i32>().expect(
+                "Could not parse environment variable `ROCKSDB_MAX_OPEN_FILES`: must be an \
+                 i32",
+            );
 
-        let bulk_load_optimized = env::var("ROCKSDB_BULK_LOAD_OPTIMIZED").unwrap_or_else(|_| "".to_string()) == "true";
+            let bulk_load_optimized =
+                env::var("ROCKSDB_BULK_LOAD_OPTIMIZED").unwrap_or_else(|_| "".to_string()) == "true";
 
-        let datastore = RocksdbDatastore::new(path, Some(max_open_files), bulk_load_optimized)
-            .expect("Expected to be able to create the RocksDB datastore");
+            let datastore = RocksdbDatastore::new(&path, Some(max_open_files), bulk_load_optimized)
+                .expect("Expected to be able to create the RocksDB datastore");
 
-        run(addr, datastore, worker_count)
-    } else if connection_string == "memory://" {
-        let datastore = MemoryDatastore::default();
-        run(addr, datastore, worker_count)
-    } else {
-        panic!("Cannot parse environment variable `DATABASE_URL`");
+            let datastore = LimitedDatastore::new(datastore, max_results);
+            run(addr, datastore, worker_count, options, shutdown)
+        }
+        DatastoreKind::Memory => {
+            let datastore = LimitedDatastore::new(MemoryDatastore::default(), max_results);
+            run(addr, datastore, worker_count, options, shutdown)
+        }
     }
 }
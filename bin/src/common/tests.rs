@@ -1,11 +1,21 @@
+use crate::async_client_datastore::AsyncClientDatastore;
 use crate::client_datastore::ClientDatastore;
 use indradb::util::generate_temporary_path;
-use indradb::{Datastore, Transaction};
+use indradb::{BulkInsertItem, Datastore, Error, RangeVertexQuery, SpecificVertexQuery, Transaction, Type, Vertex};
+use crate::errors;
 use crate::server;
+use futures::future;
+use futures::sync::oneshot;
+use futures::Future;
 use std::panic::catch_unwind;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
+use std::sync::mpsc;
 use std::thread::spawn;
+use std::time::Duration;
+use std::u32;
+use tokio_core::net::TcpStream;
+use tokio_core::reactor::Core;
 
 const START_PORT: u16 = 27616;
 
@@ -36,8 +46,334 @@ fn should_create_rocksdb_datastore() {
     assert_eq!(count, 0);
 }
 
+#[test]
+fn should_restart_after_shutdown() {
+    let port = (*CURRENT_PORT).fetch_add(1, Ordering::SeqCst);
+
+    for _ in 0..2 {
+        let (shutdown_sender, shutdown_receiver) = oneshot::channel();
+
+        let server_thread = spawn(move || {
+            server::start_with_shutdown(&format!("127.0.0.1:{}", port), "memory://", 1, shutdown_receiver)
+        });
+
+        {
+            let datastore = ClientDatastore::new(port as u16);
+            let trans = datastore.transaction().unwrap();
+            assert_eq!(trans.get_vertex_count().unwrap(), 0);
+        }
+
+        shutdown_sender.send(()).unwrap();
+        server_thread.join().unwrap().unwrap();
+    }
+}
+
+#[test]
+fn should_persist_rocksdb_writes_across_restarts() {
+    let port = (*CURRENT_PORT).fetch_add(1, Ordering::SeqCst);
+    let connection_string = format!("rocksdb://{}", generate_temporary_path());
+    let t = Type::new("test_vertex_type").unwrap();
+
+    for expected_count in 0..2 {
+        let (shutdown_sender, shutdown_receiver) = oneshot::channel();
+        let server_connection_string = connection_string.clone();
+
+        // The second server can only open the database if the first one
+        // released its lock
+        let server_thread = spawn(move || {
+            server::start_with_shutdown(
+                &format!("127.0.0.1:{}", port),
+                &server_connection_string,
+                1,
+                shutdown_receiver,
+            )
+        });
+
+        {
+            let datastore = ClientDatastore::new(port as u16);
+            let trans = datastore.transaction().unwrap();
+            assert_eq!(trans.get_vertex_count().unwrap(), expected_count);
+            trans.create_vertex_from_type(t.clone()).unwrap();
+        }
+
+        shutdown_sender.send(()).unwrap();
+        server_thread.join().unwrap().unwrap();
+    }
+}
+
+#[test]
+fn should_report_liveness_and_readiness() {
+    let port = (*CURRENT_PORT).fetch_add(1, Ordering::SeqCst);
+    spawn(move || server::start(&format!("127.0.0.1:{}", port), "memory://", 1));
+
+    let datastore = ClientDatastore::new(port as u16);
+    assert!(datastore.ping());
+    assert!(datastore.ready());
+}
+
+#[test]
+fn should_run_requests_asynchronously() {
+    let port = (*CURRENT_PORT).fetch_add(1, Ordering::SeqCst);
+    spawn(move || server::start(&format!("127.0.0.1:{}", port), "memory://", 1));
+
+    // The sync client waits for the server to come up
+    ClientDatastore::new(port as u16);
+
+    let mut core = Core::new().unwrap();
+    let addr = format!("127.0.0.1:{}", port).parse().unwrap();
+    let t = Type::new("test_vertex_type").unwrap();
+
+    let f = AsyncClientDatastore::connect(&addr, &core.handle()).and_then(move |datastore| {
+        let trans = datastore.transaction();
+
+        trans
+            .create_vertex_from_type(t.clone())
+            .and_then(move |id| {
+                trans
+                    .get_vertices(SpecificVertexQuery::single(id))
+                    .map(move |vertices| (id, vertices))
+            })
+            .map(move |(id, vertices)| (id, t, vertices))
+    });
+
+    let (id, t, vertices) = core.run(f).unwrap();
+    assert_eq!(vertices.len(), 1);
+    assert_eq!(vertices[0].id, id);
+    assert_eq!(vertices[0].t, t);
+}
+
+#[test]
+fn should_reject_incompatible_protocol_versions() {
+    let port = (*CURRENT_PORT).fetch_add(1, Ordering::SeqCst);
+    spawn(move || server::start(&format!("127.0.0.1:{}", port), "memory://", 1));
+
+    // The sync client waits for the server to come up, and handshakes with
+    // the current version
+    ClientDatastore::new(port as u16);
+
+    let mut core = Core::new().unwrap();
+    let handle = core.handle();
+    let addr = format!("127.0.0.1:{}", port).parse().unwrap();
+    let datastore = core.run(AsyncClientDatastore::connect(&addr, &handle)).unwrap();
+    let client_version = server::PROTOCOL_VERSION + 1;
+
+    match core.run(datastore.handshake_with_version(client_version)) {
+        Err(errors::Error::ProtocolMismatch { client, server }) => {
+            assert_eq!(client, client_version);
+            assert_eq!(server, server::PROTOCOL_VERSION);
+        }
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn should_reject_requests_before_a_matching_handshake() {
+    let port = (*CURRENT_PORT).fetch_add(1, Ordering::SeqCst);
+    spawn(move || server::start(&format!("127.0.0.1:{}", port), "memory://", 1));
+
+    // The sync client waits for the server to come up
+    ClientDatastore::new(port as u16);
+
+    let mut core = Core::new().unwrap();
+    let handle = core.handle();
+    let addr = format!("127.0.0.1:{}", port).parse().unwrap();
+    let stream = core.run(TcpStream::connect(&addr, &handle)).unwrap();
+    let datastore = AsyncClientDatastore::from_stream(stream, &handle);
+
+    // Clients that skip the handshake are treated like old clients that
+    // don't know about it
+    match core.run(datastore.transaction().get_vertex_count()) {
+        Err(errors::Error::ProtocolMismatch { client, server }) => {
+            assert_eq!(client, 0);
+            assert_eq!(server, server::PROTOCOL_VERSION);
+        }
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    let client_version = server::PROTOCOL_VERSION + 1;
+    assert!(core.run(datastore.handshake_with_version(client_version)).is_err());
+
+    match core.run(datastore.transaction().get_vertex_count()) {
+        Err(errors::Error::ProtocolMismatch { client, server }) => {
+            assert_eq!(client, client_version);
+            assert_eq!(server, server::PROTOCOL_VERSION);
+        }
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    match core.run(datastore.bulk_insert(Vec::<BulkInsertItem>::new().into_iter())) {
+        Err(errors::Error::ProtocolMismatch { .. }) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    core.run(datastore.handshake()).unwrap();
+    assert_eq!(core.run(datastore.transaction().get_vertex_count()).unwrap(), 0);
+}
+
+#[test]
+fn should_rate_limit_connections() {
+    let port = (*CURRENT_PORT).fetch_add(1, Ordering::SeqCst);
+
+    spawn(move || {
+        let options = server::ServerOptions {
+            rate_limit: Some(2),
+            ..server::ServerOptions::default()
+        };
+
+        server::start_with_options(&format!("127.0.0.1:{}", port), "memory://", 1, options)
+    });
+
+    // The sync client waits for the server to come up. It only pings, which
+    // isn't rate limited, and connections are limited separately anyways.
+    ClientDatastore::new(port as u16);
+
+    let mut core = Core::new().unwrap();
+    let handle = core.handle();
+    let addr = format!("127.0.0.1:{}", port).parse().unwrap();
+    let datastore = core.run(AsyncClientDatastore::connect(&addr, &handle)).unwrap();
+
+    // Creating the transaction and getting the count use up the burst
+    let trans = datastore.transaction();
+    assert_eq!(core.run(trans.get_vertex_count()).unwrap(), 0);
+
+    let requests: Vec<_> = (0..10).map(|_| trans.get_vertex_count()).collect();
+
+    match core.run(future::join_all(requests)) {
+        Err(errors::Error::RateLimited) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn should_report_mutation_events() {
+    let port = (*CURRENT_PORT).fetch_add(1, Ordering::SeqCst);
+    let (sender, receiver) = mpsc::channel();
+
+    spawn(move || {
+        let options = server::ServerOptions {
+            events: Some(sender),
+            ..server::ServerOptions::default()
+        };
+
+        server::start_with_options(&format!("127.0.0.1:{}", port), "memory://", 1, options)
+    });
+
+    let datastore = ClientDatastore::new(port as u16);
+    let trans = datastore.transaction().unwrap();
+    let t = Type::new("test_vertex_type").unwrap();
+    let id = trans.create_vertex_from_type(t.clone()).unwrap();
+
+    match receiver.recv_timeout(Duration::from_secs(5)).unwrap() {
+        server::MutationEvent::VertexCreated(ref vertex) if vertex.id == id && vertex.t == t => (),
+        event => panic!("Unexpected event: {:?}", event),
+    }
+}
+
+#[test]
+fn should_connect_over_tls() {
+    let port = (*CURRENT_PORT).fetch_add(1, Ordering::SeqCst);
+    let tls_path = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/tls");
+
+    // The server certificate is for `localhost`, and is signed by a test CA
+    // rather than being self-signed, since a self-signed CA certificate
+    // can't also be used as the server certificate
+    spawn(move || {
+        server::start_tls(
+            &format!("127.0.0.1:{}", port),
+            "memory://",
+            1,
+            &format!("{}/cert.pem", tls_path),
+            &format!("{}/key.pem", tls_path),
+        )
+    });
+
+    let datastore = ClientDatastore::with_tls(port as u16, &format!("{}/ca.pem", tls_path), "localhost");
+    let trans = datastore.transaction().unwrap();
+    let t = Type::new("test_vertex_type").unwrap();
+    let id = trans.create_vertex_from_type(t.clone()).unwrap();
+    let vertices = trans.get_vertices(SpecificVertexQuery::single(id)).unwrap();
+    assert_eq!(vertices.len(), 1);
+    assert_eq!(vertices[0].t, t);
+}
+
+#[test]
+fn should_reject_invalid_tls_files() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/tls/ca.pem");
+
+    match server::load_tls_config(path, path) {
+        Err(errors::Error::InvalidTls) => (),
+        result => panic!("Unexpected result: {:?}", result.map(|_| ())),
+    }
+}
+
 #[test]
 fn should_panic_on_bad_connection_string() {
     let result = catch_unwind(|| server::start("127.0.0.1:9999", "foo://", 1));
     assert!(result.is_err());
 }
+
+#[test]
+fn should_have_a_positive_default_worker_count() {
+    assert!(server::default_worker_count() >= 1);
+}
+
+#[test]
+fn should_parse_datastore_uris() {
+    assert_eq!(
+        server::parse_datastore_uri("memory://").unwrap(),
+        server::DatastoreKind::Memory
+    );
+    assert_eq!(
+        server::parse_datastore_uri("rocksdb:///tmp/indradb").unwrap(),
+        server::DatastoreKind::Rocksdb {
+            path: "/tmp/indradb".to_string()
+        }
+    );
+
+    match server::parse_datastore_uri("postgres://localhost/indradb") {
+        Err(errors::Error::UnsupportedScheme { ref scheme }) if scheme == "postgres" => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    match server::parse_datastore_uri("memory://foo") {
+        Err(errors::Error::CouldNotParse) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    match server::parse_datastore_uri("foo") {
+        Err(errors::Error::CouldNotParse) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn should_not_start_with_an_unsupported_datastore() {
+    let port = (*CURRENT_PORT).fetch_add(1, Ordering::SeqCst);
+
+    match server::start(&format!("127.0.0.1:{}", port), "postgres://localhost/indradb", 1) {
+        Err(errors::Error::UnsupportedScheme { ref scheme }) if scheme == "postgres" => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn should_time_out_slow_requests() {
+    let port = (*CURRENT_PORT).fetch_add(1, Ordering::SeqCst);
+    spawn(move || server::start(&format!("127.0.0.1:{}", port), "memory://", 1));
+
+    let t = Type::new("test_vertex_type").unwrap();
+    let datastore = ClientDatastore::new(port as u16);
+    datastore
+        .bulk_insert((0..10_000).map(|_| BulkInsertItem::Vertex(Vertex::new(t.clone()))))
+        .unwrap();
+
+    // Fetching every vertex can't make the round trip before a timeout this
+    // short fires
+    let datastore = ClientDatastore::with_timeout(port as u16, Duration::new(0, 1));
+    let trans = datastore.transaction().unwrap();
+
+    match trans.get_vertices(RangeVertexQuery::new(u32::MAX)) {
+        Err(Error::Timeout { .. }) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
@@ -5,7 +5,6 @@ extern crate common;
 extern crate core;
 extern crate futures;
 extern crate indradb;
-extern crate num_cpus;
 extern crate serde_json;
 extern crate uuid;
 
@@ -29,8 +28,32 @@ fn main() {
         Ok(value) => value
             .parse::<usize>()
             .expect("Could not parse environment variable `WORKER_COUNT`"),
-        Err(_) => num_cpus::get() * 2,
+        Err(_) => common::server::default_worker_count(),
     };
 
-    common::server::start(&binding, &connection_string, worker_count).expect("Expected to be able to start the server");
+    let mut options = common::server::ServerOptions::default();
+
+    // Serve over TLS if a certificate and key are configured
+    if let (Ok(cert_path), Ok(key_path)) = (env::var("TLS_CERT_PATH"), env::var("TLS_KEY_PATH")) {
+        let tls =
+            common::server::load_tls_config(&cert_path, &key_path).expect("Could not load the TLS certificate and key");
+        options.tls = Some(tls);
+    }
+
+    if let Ok(value) = env::var("RATE_LIMIT") {
+        let rate_limit = value
+            .parse::<u32>()
+            .expect("Could not parse environment variable `RATE_LIMIT`");
+        options.rate_limit = Some(rate_limit);
+    }
+
+    if let Ok(value) = env::var("MAX_RESULTS") {
+        let max_results = value
+            .parse::<u32>()
+            .expect("Could not parse environment variable `MAX_RESULTS`");
+        options.max_results = Some(max_results);
+    }
+
+    common::server::start_with_options(&binding, &connection_string, worker_count, options)
+        .expect("Expected to be able to start the server");
 }
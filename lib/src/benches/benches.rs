@@ -1,6 +1,11 @@
-use crate::models::{EdgeDirection, EdgeKey, SpecificEdgeQuery, SpecificVertexQuery, Type, Vertex};
+use crate::models::{
+    BulkInsertItem, EdgeDirection, EdgeKey, RangeVertexQuery, SpecificEdgeQuery, SpecificVertexQuery, Type, Vertex,
+    VertexQueryExt,
+};
+use crate::testkit::random_graph;
 use test::Bencher;
 use crate::traits::{Datastore, Transaction};
+use std::u32;
 
 pub fn bench_create_vertex<D: Datastore>(b: &mut Bencher, datastore: &mut D) {
     let t = Type::new("bench_create_vertex").unwrap();
@@ -12,6 +17,27 @@ pub fn bench_create_vertex<D: Datastore>(b: &mut Bencher, datastore: &mut D) {
     });
 }
 
+pub fn bench_create_vertices_individually<D: Datastore>(b: &mut Bencher, datastore: &mut D) {
+    let t = Type::new("bench_create_vertices_individually").unwrap();
+
+    b.iter(|| {
+        let trans = datastore.transaction().unwrap();
+
+        for _ in 0..10_000 {
+            trans.create_vertex(&Vertex::new(t.clone())).unwrap();
+        }
+    });
+}
+
+pub fn bench_bulk_insert_vertices<D: Datastore>(b: &mut Bencher, datastore: &mut D) {
+    let t = Type::new("bench_bulk_insert_vertices").unwrap();
+
+    b.iter(|| {
+        let items = (0..10_000).map(|_| BulkInsertItem::Vertex(Vertex::new(t.clone())));
+        datastore.bulk_insert(items).unwrap();
+    });
+}
+
 pub fn bench_get_vertices<D: Datastore>(b: &mut Bencher, datastore: &mut D) {
     let id = {
         let trans = datastore.transaction().unwrap();
@@ -47,6 +73,27 @@ pub fn bench_create_edge<D: Datastore>(b: &mut Bencher, datastore: &mut D) {
     });
 }
 
+pub fn bench_create_edges_individually<D: Datastore>(b: &mut Bencher, datastore: &mut D) {
+    let keys = create_edge_keys(datastore, "bench_create_edges_individually", 10_000);
+
+    b.iter(|| {
+        let trans = datastore.transaction().unwrap();
+
+        for key in &keys {
+            trans.create_edge(key).unwrap();
+        }
+    });
+}
+
+pub fn bench_create_edges<D: Datastore>(b: &mut Bencher, datastore: &mut D) {
+    let keys = create_edge_keys(datastore, "bench_create_edges", 10_000);
+
+    b.iter(|| {
+        let trans = datastore.transaction().unwrap();
+        trans.create_edges(&keys).unwrap();
+    });
+}
+
 pub fn bench_get_edges<D: Datastore>(b: &mut Bencher, datastore: &mut D) {
     let t = Type::new("bench_get_edges").unwrap();
 
@@ -89,3 +136,69 @@ pub fn bench_get_edge_count<D: Datastore>(b: &mut Bencher, datastore: &mut D) {
             .unwrap();
     });
 }
+
+pub fn bench_get_random_graph_edges<D: Datastore>(b: &mut Bencher, datastore: &mut D) {
+    let (vertices, edges) = random_graph(0, 1_000, 5.0);
+
+    let items = vertices
+        .into_iter()
+        .map(BulkInsertItem::Vertex)
+        .chain(edges.into_iter().map(|edge| BulkInsertItem::Edge(edge.key)));
+    datastore.bulk_insert(items).unwrap();
+
+    b.iter(|| {
+        let trans = datastore.transaction().unwrap();
+        let q = RangeVertexQuery::new(u32::MAX).outbound(u32::MAX);
+        trans.get_edges(q).unwrap();
+    });
+}
+
+// Counts one of two interleaved edge types across 100k edges. Datastores
+// that compare types in place, rather than reading each edge's type out
+// first, only pay for the edges that match.
+pub fn bench_count_edges_by_type<D: Datastore>(b: &mut Bencher, datastore: &mut D) {
+    let vertex_t = Type::new("bench_count_edges_by_type").unwrap();
+    let matching_t = Type::new("bench_count_edges_by_type_matching").unwrap();
+    let other_t = Type::new("bench_count_edges_by_type_other").unwrap();
+    let outbound_v = Vertex::new(vertex_t.clone());
+    let inbound_vs: Vec<Vertex> = (0..100_000).map(|_| Vertex::new(vertex_t.clone())).collect();
+
+    let edges: Vec<BulkInsertItem> = inbound_vs
+        .iter()
+        .enumerate()
+        .map(|(i, inbound_v)| {
+            let t = if i % 2 == 0 { &matching_t } else { &other_t };
+            BulkInsertItem::Edge(EdgeKey::new(outbound_v.id, t.clone(), inbound_v.id))
+        })
+        .collect();
+
+    let items = inbound_vs
+        .into_iter()
+        .chain(vec![outbound_v])
+        .map(BulkInsertItem::Vertex)
+        .chain(edges);
+    datastore.bulk_insert(items).unwrap();
+
+    b.iter(|| {
+        let trans = datastore.transaction().unwrap();
+        assert_eq!(trans.count_edges(Some(&matching_t)).unwrap(), 50_000);
+    });
+}
+
+fn create_edge_keys<D: Datastore>(datastore: &mut D, t: &str, count: usize) -> Vec<EdgeKey> {
+    let t = Type::new(t).unwrap();
+    let outbound_v = Vertex::new(t.clone());
+    let inbound_vs: Vec<Vertex> = (0..count).map(|_| Vertex::new(t.clone())).collect();
+
+    let keys = inbound_vs
+        .iter()
+        .map(|inbound_v| EdgeKey::new(outbound_v.id, t.clone(), inbound_v.id))
+        .collect();
+
+    let items = inbound_vs
+        .into_iter()
+        .chain(vec![outbound_v])
+        .map(BulkInsertItem::Vertex);
+    datastore.bulk_insert(items).unwrap();
+    keys
+}
@@ -17,9 +17,15 @@ macro_rules! define_bench {
 macro_rules! full_bench_impl {
     ($code:expr) => {
         define_bench!(bench_create_vertex, $code);
+        define_bench!(bench_create_vertices_individually, $code);
+        define_bench!(bench_bulk_insert_vertices, $code);
         define_bench!(bench_get_vertices, $code);
         define_bench!(bench_create_edge, $code);
+        define_bench!(bench_create_edges_individually, $code);
+        define_bench!(bench_create_edges, $code);
         define_bench!(bench_get_edges, $code);
         define_bench!(bench_get_edge_count, $code);
+        define_bench!(bench_get_random_graph_edges, $code);
+        define_bench!(bench_count_edges_by_type, $code);
     };
 }
@@ -0,0 +1,478 @@
+//! A read cache for datastores.
+//!
+//! Wrap any datastore in a `CachingDatastore` to have lookups of vertices
+//! and edges by id or key served from a size-bounded LRU cache. Writes made
+//! through the wrapper invalidate the affected entries, but writes made to
+//! the underlying datastore by other means do not, so the wrapper should be
+//! the only way the datastore is written to.
+
+use chrono::offset::Utc;
+use chrono::DateTime;
+use crate::errors::Result;
+use crate::models;
+use crate::stats::DatastoreStats;
+use crate::traits::{Datastore, Transaction};
+use serde_json::value::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+// A least-recently-used cache. Recency is tracked with a monotonically
+// increasing tick per access, so the oldest entry is the first in `order`.
+#[derive(Debug)]
+struct LruCache<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+    order: BTreeMap<u64, K>,
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> LruCache<K, V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &K) -> Option<V> {
+        self.tick += 1;
+
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                self.order.remove(&entry.1);
+                self.order.insert(self.tick, key.clone());
+                entry.1 = self.tick;
+                Some(entry.0.clone())
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.remove(&key);
+
+        if self.entries.len() >= self.capacity {
+            let oldest = self.order.keys().next().cloned();
+
+            if let Some(oldest) = oldest {
+                if let Some(oldest_key) = self.order.remove(&oldest) {
+                    self.entries.remove(&oldest_key);
+                }
+            }
+        }
+
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, self.tick));
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some((_, tick)) = self.entries.remove(key) {
+            self.order.remove(&tick);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+// Missing items are cached too (as `None`), so repeated lookups of items
+// that don't exist don't hit the underlying datastore either.
+#[derive(Debug)]
+struct Cache {
+    vertices: LruCache<Uuid, Option<models::Type>>,
+    edges: LruCache<models::EdgeKey, Option<DateTime<Utc>>>,
+}
+
+impl Cache {
+    fn clear(&mut self) {
+        self.vertices.clear();
+        self.edges.clear();
+    }
+}
+
+/// A datastore that caches vertex and edge lookups made against an
+/// underlying datastore.
+///
+/// Only queries for specific vertices or edges are cached; all other
+/// queries, counts and properties go straight to the underlying datastore.
+/// Cache misses and writes are serialized, so that a write can't race with
+/// a lookup repopulating the cache with stale data.
+#[derive(Debug)]
+pub struct CachingDatastore<D: Datastore> {
+    datastore: D,
+    cache: Arc<Mutex<Cache>>,
+}
+
+impl<D: Datastore> CachingDatastore<D> {
+    /// Wraps a datastore with a cache.
+    ///
+    /// # Arguments
+    /// * `datastore` - The datastore to wrap.
+    /// * `capacity` - The maximum number of vertices to cache. Up to the same
+    ///   number of edges are cached as well.
+    pub fn new(datastore: D, capacity: usize) -> Self {
+        Self {
+            datastore,
+            cache: Arc::new(Mutex::new(Cache {
+                vertices: LruCache::new(capacity),
+                edges: LruCache::new(capacity),
+            })),
+        }
+    }
+
+    /// Gets the underlying datastore.
+    pub fn inner(&self) -> &D {
+        &self.datastore
+    }
+}
+
+impl<D: Datastore> Datastore for CachingDatastore<D> {
+    type Trans = CachingTransaction<D::Trans>;
+
+    fn transaction(&self) -> Result<Self::Trans> {
+        Ok(CachingTransaction {
+            trans: self.datastore.transaction()?,
+            cache: Arc::clone(&self.cache),
+        })
+    }
+
+    fn bulk_insert<I>(&self, items: I) -> Result<()>
+    where
+        I: Iterator<Item = models::BulkInsertItem>,
+    {
+        let mut cache = self.cache.lock().unwrap();
+        let result = self.datastore.bulk_insert(items);
+        cache.clear();
+        result
+    }
+
+    fn clear(&self) -> Result<()> {
+        let mut cache = self.cache.lock().unwrap();
+        let result = self.datastore.clear();
+        cache.clear();
+        result
+    }
+
+    fn compact(&self) -> Result<()> {
+        self.datastore.compact()
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.datastore.flush()
+    }
+
+    fn stats(&self) -> Result<DatastoreStats> {
+        self.datastore.stats()
+    }
+
+    fn vertices_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Vertex>> + 'a> {
+        self.datastore.vertices_iter()
+    }
+
+    fn edges_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Edge>> + 'a> {
+        self.datastore.edges_iter()
+    }
+}
+
+/// A transaction that caches vertex and edge lookups in the cache of the
+/// datastore it was created from.
+#[derive(Debug)]
+pub struct CachingTransaction<T: Transaction> {
+    trans: T,
+    cache: Arc<Mutex<Cache>>,
+}
+
+impl<T: Transaction> Transaction for CachingTransaction<T> {
+    fn create_vertex(&self, vertex: &models::Vertex) -> Result<bool> {
+        let mut cache = self.cache.lock().unwrap();
+        let result = self.trans.create_vertex(vertex);
+        cache.vertices.remove(&vertex.id);
+        result
+    }
+
+    fn create_vertex_from_type(&self, t: models::Type) -> Result<Uuid> {
+        let mut cache = self.cache.lock().unwrap();
+        let id = self.trans.create_vertex_from_type(t)?;
+        cache.vertices.remove(&id);
+        Ok(id)
+    }
+
+    fn get_or_create_vertex(&self, id: Uuid, t: models::Type) -> Result<(models::Vertex, bool)> {
+        let mut cache = self.cache.lock().unwrap();
+        let result = self.trans.get_or_create_vertex(id, t);
+        cache.vertices.remove(&id);
+        result
+    }
+
+    fn get_vertices<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<Vec<models::Vertex>> {
+        let ids = match q.into() {
+            models::VertexQuery::Specific(specific) => specific.ids,
+            q => return self.trans.get_vertices(q),
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        let mut found = HashMap::new();
+        let mut misses = Vec::new();
+
+        for id in &ids {
+            match cache.vertices.get(id) {
+                Some(t) => {
+                    found.insert(*id, t);
+                }
+                None => misses.push(*id),
+            }
+        }
+
+        if !misses.is_empty() {
+            let q = models::SpecificVertexQuery::new(misses.clone());
+            let vertices = self.trans.get_vertices(q)?;
+
+            for id in &misses {
+                found.insert(*id, None);
+            }
+
+            for vertex in vertices {
+                found.insert(vertex.id, Some(vertex.t));
+            }
+
+            for id in misses {
+                cache.vertices.insert(id, found[&id].clone());
+            }
+        }
+
+        Ok(ids
+            .into_iter()
+            .filter_map(|id| found[&id].clone().map(|t| models::Vertex::with_id(id, t)))
+            .collect())
+    }
+
+    fn delete_vertices<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<()> {
+        // Deleting vertices also deletes their edges, which could be
+        // anywhere in the cache
+        let mut cache = self.cache.lock().unwrap();
+        let result = self.trans.delete_vertices(q);
+        cache.clear();
+        result
+    }
+
+    fn delete_vertices_by_type(&self, t: models::Type) -> Result<u64> {
+        let mut cache = self.cache.lock().unwrap();
+        let result = self.trans.delete_vertices_by_type(t);
+        cache.clear();
+        result
+    }
+
+    fn get_vertex_count(&self) -> Result<u64> {
+        self.trans.get_vertex_count()
+    }
+
+    fn count_vertices(&self, t: Option<&models::Type>) -> Result<u64> {
+        self.trans.count_vertices(t)
+    }
+
+    fn which_vertices_exist(&self, ids: &[Uuid]) -> Result<Vec<bool>> {
+        let vertices = self.get_vertices(models::SpecificVertexQuery::new(ids.to_vec()))?;
+        let existing: HashSet<Uuid> = vertices.into_iter().map(|vertex| vertex.id).collect();
+        Ok(ids.iter().map(|id| existing.contains(id)).collect())
+    }
+
+    fn create_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        let mut cache = self.cache.lock().unwrap();
+        let result = self.trans.create_edge(key);
+        cache.edges.remove(key);
+        result
+    }
+
+    fn create_edges(&self, keys: &[models::EdgeKey]) -> Result<Vec<bool>> {
+        let mut cache = self.cache.lock().unwrap();
+        let result = self.trans.create_edges(keys);
+
+        for key in keys {
+            cache.edges.remove(key);
+        }
+
+        result
+    }
+
+    fn get_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<Vec<models::Edge>> {
+        let keys = match q.into() {
+            models::EdgeQuery::Specific(specific) => specific.keys,
+            q => return self.trans.get_edges(q),
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        let mut found = HashMap::new();
+        let mut misses = Vec::new();
+
+        for key in &keys {
+            match cache.edges.get(key) {
+                Some(created_datetime) => {
+                    found.insert(key.clone(), created_datetime);
+                }
+                None => misses.push(key.clone()),
+            }
+        }
+
+        if !misses.is_empty() {
+            let q = models::SpecificEdgeQuery::new(misses.clone());
+            let edges = self.trans.get_edges(q)?;
+
+            for key in &misses {
+                found.insert(key.clone(), None);
+            }
+
+            for edge in edges {
+                found.insert(edge.key, Some(edge.created_datetime));
+            }
+
+            for key in misses {
+                let created_datetime = found[&key];
+                cache.edges.insert(key, created_datetime);
+            }
+        }
+
+        Ok(keys
+            .into_iter()
+            .filter_map(|key| found[&key].map(|created_datetime| models::Edge::new(key, created_datetime)))
+            .collect())
+    }
+
+    fn has_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        let edges = self.get_edges(models::SpecificEdgeQuery::single(key.clone()))?;
+        Ok(!edges.is_empty())
+    }
+
+    fn delete_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<()> {
+        let mut cache = self.cache.lock().unwrap();
+        let result = self.trans.delete_edges(q);
+        cache.edges.clear();
+        result
+    }
+
+    fn get_edge_count(&self, id: Uuid, t: Option<&models::Type>, direction: models::EdgeDirection) -> Result<u64> {
+        self.trans.get_edge_count(id, t, direction)
+    }
+
+    fn count_edges(&self, t: Option<&models::Type>) -> Result<u64> {
+        self.trans.count_edges(t)
+    }
+
+    fn edges_by_time(
+        &self,
+        after: Option<(DateTime<Utc>, models::EdgeKey)>,
+        limit: usize,
+    ) -> Result<Vec<models::Edge>> {
+        self.trans.edges_by_time(after, limit)
+    }
+
+    fn rename_edge_type(&self, from: models::Type, to: models::Type) -> Result<u64> {
+        let mut cache = self.cache.lock().unwrap();
+        let result = self.trans.rename_edge_type(from, to);
+        cache.edges.clear();
+        result
+    }
+
+    fn get_vertex_properties(&self, q: models::VertexPropertyQuery) -> Result<Vec<models::VertexProperty>> {
+        self.trans.get_vertex_properties(q)
+    }
+
+    fn set_vertex_properties(&self, q: models::VertexPropertyQuery, value: &JsonValue) -> Result<()> {
+        self.trans.set_vertex_properties(q, value)
+    }
+
+    fn delete_vertex_properties(&self, q: models::VertexPropertyQuery) -> Result<()> {
+        self.trans.delete_vertex_properties(q)
+    }
+
+    fn get_edge_properties(&self, q: models::EdgePropertyQuery) -> Result<Vec<models::EdgeProperty>> {
+        self.trans.get_edge_properties(q)
+    }
+
+    fn set_edge_properties(&self, q: models::EdgePropertyQuery, value: &JsonValue) -> Result<()> {
+        self.trans.set_edge_properties(q, value)
+    }
+
+    fn delete_edge_properties(&self, q: models::EdgePropertyQuery) -> Result<()> {
+        self.trans.delete_edge_properties(q)
+    }
+
+    fn get_edge_property_names(&self, key: &models::EdgeKey) -> Result<Vec<String>> {
+        self.trans.get_edge_property_names(key)
+    }
+}
+
+#[cfg(feature = "test-suite")]
+full_test_impl!(CachingDatastore::new(crate::memory::MemoryDatastore::default(), 100));
+
+#[cfg(test)]
+mod tests {
+    use super::{CachingDatastore, LruCache};
+    use crate::memory::MemoryDatastore;
+    use crate::metrics::{CountingMetricsSink, MetricsDatastore, OpKind};
+    use crate::models::{SpecificVertexQuery, Type, Vertex};
+    use crate::traits::{Datastore, Transaction};
+
+    #[test]
+    fn should_evict_least_recently_used_entries() {
+        let mut cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        assert_eq!(cache.get(&1), Some("a"));
+        cache.insert(3, "c");
+        assert_eq!(cache.get(&1), Some("a"));
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&3), Some("c"));
+    }
+
+    #[test]
+    fn should_serve_repeated_reads_from_the_cache() {
+        let inner = MetricsDatastore::with_sink(MemoryDatastore::default(), CountingMetricsSink::default());
+        let datastore = CachingDatastore::new(inner, 10);
+        let trans = datastore.transaction().unwrap();
+        let t = Type::new("test_vertex_type").unwrap();
+        let id = trans.create_vertex_from_type(t.clone()).unwrap();
+        let sink = datastore.inner().sink();
+
+        let vertices = trans.get_vertices(SpecificVertexQuery::single(id)).unwrap();
+        assert_eq!(vertices.len(), 1);
+        assert_eq!(sink.op_count(OpKind::VertexQuery), 1);
+
+        let vertices = trans.get_vertices(SpecificVertexQuery::single(id)).unwrap();
+        assert_eq!(vertices.len(), 1);
+        assert_eq!(vertices[0].t, t);
+        assert_eq!(sink.op_count(OpKind::VertexQuery), 1);
+    }
+
+    #[test]
+    fn should_invalidate_on_write() {
+        let inner = MetricsDatastore::with_sink(MemoryDatastore::default(), CountingMetricsSink::default());
+        let datastore = CachingDatastore::new(inner, 10);
+        let trans = datastore.transaction().unwrap();
+        let t = Type::new("test_vertex_type").unwrap();
+        let vertex = Vertex::new(t);
+        let q = SpecificVertexQuery::single(vertex.id);
+
+        // Cache that the vertex is missing
+        assert_eq!(trans.get_vertices(q.clone()).unwrap().len(), 0);
+        assert_eq!(trans.get_vertices(q.clone()).unwrap().len(), 0);
+        assert_eq!(datastore.inner().sink().op_count(OpKind::VertexQuery), 1);
+
+        trans.create_vertex(&vertex).unwrap();
+        assert_eq!(trans.get_vertices(q.clone()).unwrap().len(), 1);
+        assert_eq!(datastore.inner().sink().op_count(OpKind::VertexQuery), 2);
+
+        trans.delete_vertices(q.clone()).unwrap();
+        assert_eq!(trans.get_vertices(q.clone()).unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,276 @@
+//! Computing the differences between two datastores.
+
+use crate::errors::Result;
+use crate::models;
+use crate::models::VertexQueryExt;
+use crate::traits::{Datastore, Transaction};
+use std::cmp;
+use std::cmp::Ordering;
+use std::u32;
+use uuid::Uuid;
+
+const PAGE_SIZE: u32 = 1000;
+
+/// The differences between two datastores, from the perspective of the
+/// first: applying the differences to the first datastore would make it
+/// match the second.
+#[derive(Clone, Debug, Default)]
+pub struct Diff {
+    /// Vertices that are only in the second datastore.
+    pub added_vertices: Vec<models::Vertex>,
+
+    /// Vertices that are only in the first datastore.
+    pub removed_vertices: Vec<models::Vertex>,
+
+    /// Vertices that are in both datastores, but with different types. These
+    /// are the vertices as they are in the second datastore.
+    pub changed_vertices: Vec<models::Vertex>,
+
+    /// Edges that are only in the second datastore.
+    pub added_edges: Vec<models::Edge>,
+
+    /// Edges that are only in the first datastore.
+    pub removed_edges: Vec<models::Edge>,
+
+    /// Edges that are in both datastores, but that aren't exactly equal (see
+    /// `Edge::eq_exact`). These are the edges as they are in the second
+    /// datastore.
+    pub changed_edges: Vec<models::Edge>,
+}
+
+impl Diff {
+    /// Whether the datastores were found to be the same.
+    pub fn is_empty(&self) -> bool {
+        self.added_vertices.is_empty()
+            && self.removed_vertices.is_empty()
+            && self.changed_vertices.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+            && self.changed_edges.is_empty()
+    }
+}
+
+// Gets a page of vertices whose ids are greater than `after`, along with
+// whether the page was full, i.e. whether there may be more vertices after
+// it.
+pub(crate) fn get_vertex_page<T: Transaction>(trans: &T, after: Option<Uuid>) -> Result<(Vec<models::Vertex>, bool)> {
+    let mut q = models::RangeVertexQuery::new(PAGE_SIZE);
+
+    if let Some(after) = after {
+        q = q.start_id(after);
+    }
+
+    let vertices = trans.get_vertices(q)?;
+    let full = vertices.len() == PAGE_SIZE as usize;
+    // Datastores differ on whether `start_id` is inclusive
+    let vertices = vertices
+        .into_iter()
+        .filter(|vertex| after.map_or(true, |after| vertex.id > after))
+        .collect();
+    Ok((vertices, full))
+}
+
+pub(crate) fn get_sorted_outbound_edges<T: Transaction>(
+    trans: &T,
+    vertices: &[models::Vertex],
+) -> Result<Vec<models::Edge>> {
+    if vertices.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let ids = vertices.iter().map(|vertex| vertex.id).collect();
+    let mut edges = trans.get_edges(models::SpecificVertexQuery::new(ids).outbound(u32::MAX))?;
+    edges.sort_by(|first, second| first.key.cmp(&second.key));
+    Ok(edges)
+}
+
+// Merges two sorted lists, calling `f` with each pair of items with the
+// same sort key. At most one of the pair is `None`.
+fn merge<T, K, G, F>(first: Vec<T>, second: Vec<T>, key: G, mut f: F)
+where
+    K: Ord,
+    G: Fn(&T) -> K,
+    F: FnMut(Option<T>, Option<T>),
+{
+    let mut first = first.into_iter().peekable();
+    let mut second = second.into_iter().peekable();
+
+    loop {
+        let ordering = match (first.peek(), second.peek()) {
+            (Some(first_item), Some(second_item)) => key(first_item).cmp(&key(second_item)),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return,
+        };
+
+        match ordering {
+            Ordering::Less => f(first.next(), None),
+            Ordering::Greater => f(None, second.next()),
+            Ordering::Equal => f(first.next(), second.next()),
+        }
+    }
+}
+
+/// Computes the differences between two datastores. See `Datastore::diff`.
+pub(crate) fn diff<A: Datastore, B: Datastore>(first: &A, second: &B) -> Result<Diff> {
+    let first_trans = first.transaction()?;
+    let second_trans = second.transaction()?;
+    let mut diff = Diff::default();
+    let mut after = None;
+
+    // Vertices are compared a window of ids at a time. Each window ends at
+    // the last id of whichever page ends first, so that both sides are
+    // complete within it; the rest of the other page is fetched again for
+    // the next window.
+    loop {
+        let (mut first_vertices, first_full) = get_vertex_page(&first_trans, after)?;
+        let (mut second_vertices, second_full) = get_vertex_page(&second_trans, after)?;
+
+        let window_end = match (first_full, second_full) {
+            // Full pages can't be empty, since filtering removes at most one
+            // vertex
+            (true, true) => Some(cmp::min(
+                first_vertices.last().unwrap().id,
+                second_vertices.last().unwrap().id,
+            )),
+            (true, false) => first_vertices.last().map(|vertex| vertex.id),
+            (false, true) => second_vertices.last().map(|vertex| vertex.id),
+            (false, false) => None,
+        };
+
+        if let Some(window_end) = window_end {
+            first_vertices.retain(|vertex| vertex.id <= window_end);
+            second_vertices.retain(|vertex| vertex.id <= window_end);
+        }
+
+        let first_edges = get_sorted_outbound_edges(&first_trans, &first_vertices)?;
+        let second_edges = get_sorted_outbound_edges(&second_trans, &second_vertices)?;
+
+        merge(
+            first_vertices,
+            second_vertices,
+            |vertex| vertex.id,
+            |first_vertex, second_vertex| match (first_vertex, second_vertex) {
+                (Some(first_vertex), Some(second_vertex)) => {
+                    if first_vertex.t != second_vertex.t {
+                        diff.changed_vertices.push(second_vertex);
+                    }
+                }
+                (Some(first_vertex), None) => diff.removed_vertices.push(first_vertex),
+                (None, Some(second_vertex)) => diff.added_vertices.push(second_vertex),
+                (None, None) => unreachable!(),
+            },
+        );
+
+        merge(
+            first_edges,
+            second_edges,
+            |edge| edge.key.clone(),
+            |first_edge, second_edge| match (first_edge, second_edge) {
+                (Some(first_edge), Some(second_edge)) => {
+                    if !first_edge.eq_exact(&second_edge) {
+                        diff.changed_edges.push(second_edge);
+                    }
+                }
+                (Some(first_edge), None) => diff.removed_edges.push(first_edge),
+                (None, Some(second_edge)) => diff.added_edges.push(second_edge),
+                (None, None) => unreachable!(),
+            },
+        );
+
+        match window_end {
+            Some(window_end) => after = Some(window_end),
+            None => return Ok(diff),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory::MemoryDatastore;
+    use crate::models::{EdgeKey, Type, Vertex};
+    use crate::traits::{Datastore, Transaction};
+    use crate::util::generate_temporary_path;
+    use std::fs;
+
+    #[test]
+    fn should_diff_identical_datastores() {
+        let first = MemoryDatastore::default();
+        let second = MemoryDatastore::default();
+        let t = Type::new("test_vertex_type").unwrap();
+        let vertex = Vertex::new(t);
+        first.transaction().unwrap().create_vertex(&vertex).unwrap();
+        second.transaction().unwrap().create_vertex(&vertex).unwrap();
+        assert!(first.diff(&second).unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_diff_vertices() {
+        let first = MemoryDatastore::default();
+        let second = MemoryDatastore::default();
+        let t = Type::new("test_vertex_type").unwrap();
+        let other_t = Type::new("other_vertex_type").unwrap();
+        let removed = Vertex::new(t.clone());
+        let added = Vertex::new(t.clone());
+        let changed = Vertex::new(t.clone());
+
+        let first_trans = first.transaction().unwrap();
+        first_trans.create_vertex(&removed).unwrap();
+        first_trans.create_vertex(&changed).unwrap();
+
+        let second_trans = second.transaction().unwrap();
+        second_trans.create_vertex(&added).unwrap();
+        second_trans
+            .create_vertex(&Vertex::with_id(changed.id, other_t.clone()))
+            .unwrap();
+
+        let diff = first.diff(&second).unwrap();
+        assert_eq!(diff.added_vertices, vec![added]);
+        assert_eq!(diff.removed_vertices, vec![removed]);
+        assert_eq!(diff.changed_vertices.len(), 1);
+        assert_eq!(diff.changed_vertices[0].id, changed.id);
+        assert_eq!(diff.changed_vertices[0].t, other_t);
+        assert!(diff.added_edges.is_empty());
+        assert!(diff.removed_edges.is_empty());
+        assert!(diff.changed_edges.is_empty());
+    }
+
+    #[test]
+    fn should_diff_edges() {
+        let path = generate_temporary_path();
+        let first = MemoryDatastore::default();
+        let vertex_t = Type::new("test_vertex_type").unwrap();
+        let edge_t = Type::new("test_edge_type").unwrap();
+
+        let first_trans = first.transaction().unwrap();
+        let outbound_id = first_trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        let inbound_id = first_trans.create_vertex_from_type(vertex_t).unwrap();
+        let changed_key = EdgeKey::new(outbound_id, edge_t.clone(), inbound_id);
+        first_trans.create_edge(&changed_key).unwrap();
+        first_trans
+            .create_edge(&EdgeKey::new(inbound_id, edge_t.clone(), inbound_id))
+            .unwrap();
+
+        // Copy the datastore, so that the edges' datetimes match
+        first.save(&path).unwrap();
+        let second = MemoryDatastore::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(first.diff(&second).unwrap().is_empty());
+
+        // Recreating an edge updates its datetime
+        let second_trans = second.transaction().unwrap();
+        let added_key = EdgeKey::new(inbound_id, edge_t, outbound_id);
+        second_trans.create_edge(&added_key).unwrap();
+        second_trans.create_edge(&changed_key).unwrap();
+
+        let diff = first.diff(&second).unwrap();
+        assert!(diff.added_vertices.is_empty());
+        assert!(diff.removed_vertices.is_empty());
+        assert!(diff.changed_vertices.is_empty());
+        assert_eq!(diff.added_edges.len(), 1);
+        assert_eq!(diff.added_edges[0].key, added_key);
+        assert!(diff.removed_edges.is_empty());
+        assert_eq!(diff.changed_edges.len(), 1);
+        assert_eq!(diff.changed_edges[0].key, changed_key);
+    }
+}
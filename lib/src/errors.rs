@@ -1,7 +1,11 @@
+use failure::Fail;
 #[cfg(feature = "rocksdb-datastore")]
 use rocksdb::Error as RocksDbError;
 use serde_json::Error as JsonError;
+use std::error::Error as StdError;
+use std::io::Error as IoError;
 use std::result::Result as StdResult;
+use std::time::Duration;
 
 #[derive(Debug, Fail)]
 pub enum Error {
@@ -12,6 +16,16 @@ pub enum Error {
     Rocksdb { inner: RocksDbError },
     #[fail(display = "UUID already taken")]
     UuidTaken,
+    #[fail(display = "i/o error: {}", inner)]
+    Io { inner: IoError },
+    #[fail(display = "invalid snapshot")]
+    InvalidSnapshot,
+    #[fail(display = "request timed out after {:?}", after)]
+    Timeout { after: Duration },
+    #[fail(display = "query returned more than {} results", limit)]
+    ResultTooLarge { limit: u32 },
+    #[fail(display = "validation error: {}", inner)]
+    Validation { inner: ValidationError },
 }
 
 impl From<JsonError> for Error {
@@ -20,18 +34,49 @@ impl From<JsonError> for Error {
     }
 }
 
+impl From<IoError> for Error {
+    fn from(err: IoError) -> Self {
+        Error::Io { inner: err }
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(err: ValidationError) -> Self {
+        Error::Validation { inner: err }
+    }
+}
+
+#[cfg(feature = "rocksdb-datastore")]
 impl From<RocksDbError> for Error {
     fn from(err: RocksDbError) -> Self {
         Error::Rocksdb { inner: err }
     }
 }
 
+// `Error` can't implement `std::error::Error` directly, because failure
+// already implements `Fail` for every `std::error::Error`. Instead, the
+// error is boxed through failure's compatibility wrapper, so `?` works in
+// functions returning `Box<dyn std::error::Error>`.
+impl From<Error> for Box<dyn StdError> {
+    fn from(err: Error) -> Self {
+        Box::new(err.compat())
+    }
+}
+
+impl From<Error> for Box<dyn StdError + Send + Sync> {
+    fn from(err: Error) -> Self {
+        Box::new(err.compat())
+    }
+}
+
 pub type Result<T> = StdResult<T, Error>;
 
 #[derive(Debug, Fail)]
 pub enum ValidationError {
 	#[fail(display = "invalid value")]
     InvalidValue,
+    #[fail(display = "invalid character in type: {:?}", offending)]
+    InvalidType { offending: char },
     #[fail(display = "value too long")]
     ValueTooLong,
     #[fail(display = "could not increment the UUID")]
@@ -39,3 +84,50 @@ pub enum ValidationError {
 }
 
 pub type ValidationResult<T> = StdResult<T, ValidationError>;
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+    use crate::models::Type;
+    use std::error::Error as StdError;
+
+    fn fail() -> Result<(), Error> {
+        Err(Error::UuidTaken)
+    }
+
+    fn fail_boxed() -> Result<(), Box<dyn StdError>> {
+        fail()?;
+        Ok(())
+    }
+
+    fn fail_boxed_send_sync() -> Result<(), Box<dyn StdError + Send + Sync>> {
+        fail()?;
+        Ok(())
+    }
+
+    #[test]
+    fn should_convert_to_boxed_std_error() {
+        let err = fail_boxed().unwrap_err();
+        assert_eq!(err.to_string(), "UUID already taken");
+        let err = fail_boxed_send_sync().unwrap_err();
+        assert_eq!(err.to_string(), "UUID already taken");
+    }
+
+    fn validate(t: &str) -> Result<Type, Error> {
+        let t = Type::new(t)?;
+        Ok(t)
+    }
+
+    #[test]
+    fn should_convert_from_validation_error() {
+        assert_eq!(validate("foo").unwrap(), Type("foo".to_string()));
+
+        match validate("$") {
+            Err(Error::Validation { .. }) => (),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        let err = validate("$").unwrap_err();
+        assert_eq!(err.to_string(), "validation error: invalid character in type: '$'");
+    }
+}
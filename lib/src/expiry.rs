@@ -0,0 +1,469 @@
+//! Edges that expire.
+//!
+//! Wrap any datastore in an `ExpiringDatastore` to be able to create edges
+//! with a time to live, e.g. for ephemeral relationships like "currently
+//! viewing". An edge's expiry is stored in a reserved edge property, and
+//! expired edges are filtered out of every query made through the wrapper,
+//! so they disappear as soon as they expire. They're only physically
+//! deleted by `ExpiringDatastore::sweep`, which should be run periodically.
+
+use chrono::offset::Utc;
+use chrono::{DateTime, Duration as ChronoDuration};
+use crate::errors::{Result, ValidationError};
+use crate::models;
+use crate::models::{EdgeQueryExt, VertexQueryExt};
+use crate::stats::DatastoreStats;
+use crate::traits::{Datastore, Transaction};
+use serde_json::value::Value as JsonValue;
+use std::cmp;
+use std::collections::HashSet;
+use std::time::Duration;
+use std::u32;
+use uuid::Uuid;
+
+/// The name of the edge property that edge expiries are stored in.
+pub const EDGE_EXPIRY_PROPERTY: &str = "indradb-edge-expiry";
+
+// How many edges to check for expiry at a time when sweeping
+const SWEEP_BATCH_SIZE: usize = 1_000;
+
+/// A datastore whose edges can be given a time to live.
+#[derive(Debug)]
+pub struct ExpiringDatastore<D: Datastore> {
+    datastore: D,
+}
+
+impl<D: Datastore> ExpiringDatastore<D> {
+    /// Wraps a datastore so that its edges can expire.
+    ///
+    /// # Arguments
+    /// * `datastore` - The datastore to wrap.
+    pub fn new(datastore: D) -> Self {
+        Self { datastore }
+    }
+
+    /// Gets the underlying datastore. Expired edges that haven't been swept
+    /// are still visible through it.
+    pub fn inner(&self) -> &D {
+        &self.datastore
+    }
+
+    /// Deletes every edge that has expired, and returns how many were
+    /// deleted. This reads through all of the edges, so it's meant to be
+    /// run periodically, e.g. from a timer, rather than after each write.
+    /// Follow it with `Datastore::compact` to reclaim the space.
+    pub fn sweep(&self) -> Result<u64> {
+        let trans = self.datastore.transaction()?;
+        let mut keys = Vec::with_capacity(SWEEP_BATCH_SIZE);
+        let mut count = 0;
+
+        for item in self.datastore.edges_iter() {
+            keys.push(item?.key);
+
+            if keys.len() == SWEEP_BATCH_SIZE {
+                count += sweep_keys(&trans, keys.split_off(0))?;
+            }
+        }
+
+        count += sweep_keys(&trans, keys)?;
+        Ok(count)
+    }
+}
+
+fn sweep_keys<T: Transaction>(trans: &T, keys: Vec<models::EdgeKey>) -> Result<u64> {
+    let expired = expired_keys(trans, keys)?;
+    let count = expired.len() as u64;
+
+    if !expired.is_empty() {
+        trans.delete_edges(models::SpecificEdgeQuery::new(expired))?;
+    }
+
+    Ok(count)
+}
+
+// Gets which of the edges with the given keys have expired
+fn expired_keys<T: Transaction>(trans: &T, keys: Vec<models::EdgeKey>) -> Result<Vec<models::EdgeKey>> {
+    if keys.is_empty() {
+        return Ok(keys);
+    }
+
+    let now = Utc::now();
+    let q = models::SpecificEdgeQuery::new(keys).property(EDGE_EXPIRY_PROPERTY);
+
+    Ok(trans
+        .get_edge_properties(q)?
+        .into_iter()
+        .filter(|property| parse_expiry(&property.value).map_or(false, |expiry| expiry <= now))
+        .map(|property| property.key)
+        .collect())
+}
+
+fn parse_expiry(value: &JsonValue) -> Option<DateTime<Utc>> {
+    let s = value.as_str()?;
+    let expiry = DateTime::parse_from_rfc3339(s).ok()?;
+    Some(expiry.with_timezone(&Utc))
+}
+
+impl<D: Datastore> Datastore for ExpiringDatastore<D> {
+    type Trans = ExpiringTransaction<D::Trans>;
+
+    fn transaction(&self) -> Result<Self::Trans> {
+        Ok(ExpiringTransaction {
+            trans: self.datastore.transaction()?,
+        })
+    }
+
+    fn bulk_insert<I>(&self, items: I) -> Result<()>
+    where
+        I: Iterator<Item = models::BulkInsertItem>,
+    {
+        self.datastore.bulk_insert(items)
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.datastore.clear()
+    }
+
+    fn compact(&self) -> Result<()> {
+        self.datastore.compact()
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.datastore.flush()
+    }
+
+    // The underlying datastore still counts expired edges that haven't been
+    // swept yet
+    fn stats(&self) -> Result<DatastoreStats> {
+        let mut stats = self.datastore.stats()?;
+        stats.edge_count = self.transaction()?.count_edges(None)?;
+        Ok(stats)
+    }
+}
+
+/// A transaction that hides expired edges.
+#[derive(Debug)]
+pub struct ExpiringTransaction<T: Transaction> {
+    trans: T,
+}
+
+impl<T: Transaction> ExpiringTransaction<T> {
+    /// Creates a new edge, or updates an existing one, like
+    /// `Transaction::create_edge`. If `ttl` is set, the edge expires once
+    /// that much time has passed. Otherwise, the edge never expires, even
+    /// if it previously had a time to live.
+    ///
+    /// # Arguments
+    /// * `key`: The edge to create.
+    /// * `ttl`: How long the edge should live for.
+    ///
+    /// # Errors
+    /// Returns `ValidationError::InvalidValue` if `ttl` is too large to
+    /// represent.
+    pub fn create_edge_with_ttl(&self, key: &models::EdgeKey, ttl: Option<Duration>) -> Result<bool> {
+        let expiry = match ttl {
+            Some(ttl) => {
+                let ttl = ChronoDuration::from_std(ttl).map_err(|_| ValidationError::InvalidValue)?;
+                let expiry = Utc::now()
+                    .checked_add_signed(ttl)
+                    .ok_or(ValidationError::InvalidValue)?;
+                Some(expiry)
+            }
+            None => None,
+        };
+
+        // An expired edge that hasn't been swept yet could otherwise be
+        // deduplicated against
+        if !expired_keys(&self.trans, vec![key.clone()])?.is_empty() {
+            let q = models::SpecificEdgeQuery::single(key.clone());
+            self.trans.delete_edges(q)?;
+        }
+
+        if !self.trans.create_edge(key)? {
+            return Ok(false);
+        }
+
+        let q = models::SpecificEdgeQuery::single(key.clone()).property(EDGE_EXPIRY_PROPERTY);
+
+        match expiry {
+            Some(expiry) => {
+                let value = JsonValue::String(expiry.to_rfc3339());
+                self.trans.set_edge_properties(q, &value)?
+            }
+            None => self.trans.delete_edge_properties(q)?,
+        }
+
+        Ok(true)
+    }
+
+    fn unexpired(&self, edges: Vec<models::Edge>) -> Result<Vec<models::Edge>> {
+        let keys = edges.iter().map(|edge| edge.key.clone()).collect();
+        let expired: HashSet<models::EdgeKey> = expired_keys(&self.trans, keys)?.into_iter().collect();
+
+        if expired.is_empty() {
+            return Ok(edges);
+        }
+
+        Ok(edges.into_iter().filter(|edge| !expired.contains(&edge.key)).collect())
+    }
+
+    // Gets up to `limit` unexpired edges. Expired edges count towards the
+    // limits of the underlying datastore's queries, so if any are filtered
+    // out, the query is rerun with a larger limit to make up for them.
+    fn fetch_unexpired<F>(&self, limit: usize, fetch: F) -> Result<Vec<models::Edge>>
+    where
+        F: Fn(usize) -> Result<Vec<models::Edge>>,
+    {
+        let mut fetch_limit = limit;
+
+        loop {
+            let edges = fetch(fetch_limit)?;
+            let exhausted = edges.len() < fetch_limit;
+            let mut edges = self.unexpired(edges)?;
+
+            if exhausted || edges.len() >= limit {
+                edges.truncate(limit);
+                return Ok(edges);
+            }
+
+            fetch_limit = fetch_limit.saturating_mul(2);
+        }
+    }
+
+    // Rewrites a query so that it doesn't pipe through expired edges
+    fn resolve_vertex_query(&self, q: models::VertexQuery) -> Result<models::VertexQuery> {
+        match q {
+            models::VertexQuery::Pipe(pipe) => {
+                let inner = self.resolve_edge_query(*pipe.inner)?;
+
+                Ok(models::PipeVertexQuery {
+                    inner: Box::new(inner),
+                    ..pipe
+                }
+                .into())
+            }
+            q => Ok(q),
+        }
+    }
+
+    fn resolve_edge_query(&self, q: models::EdgeQuery) -> Result<models::EdgeQuery> {
+        let keys = self.get_edges(q)?.into_iter().map(|edge| edge.key).collect();
+        Ok(models::SpecificEdgeQuery::new(keys).into())
+    }
+}
+
+impl<T: Transaction> Transaction for ExpiringTransaction<T> {
+    fn create_vertex(&self, vertex: &models::Vertex) -> Result<bool> {
+        self.trans.create_vertex(vertex)
+    }
+
+    fn create_vertex_from_type(&self, t: models::Type) -> Result<Uuid> {
+        self.trans.create_vertex_from_type(t)
+    }
+
+    fn get_or_create_vertex(&self, id: Uuid, t: models::Type) -> Result<(models::Vertex, bool)> {
+        self.trans.get_or_create_vertex(id, t)
+    }
+
+    fn get_vertices<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<Vec<models::Vertex>> {
+        let q = self.resolve_vertex_query(q.into())?;
+        self.trans.get_vertices(q)
+    }
+
+    fn delete_vertices<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<()> {
+        let q = self.resolve_vertex_query(q.into())?;
+        self.trans.delete_vertices(q)
+    }
+
+    fn delete_vertices_by_type(&self, t: models::Type) -> Result<u64> {
+        self.trans.delete_vertices_by_type(t)
+    }
+
+    fn get_vertex_count(&self) -> Result<u64> {
+        self.trans.get_vertex_count()
+    }
+
+    fn count_vertices(&self, t: Option<&models::Type>) -> Result<u64> {
+        self.trans.count_vertices(t)
+    }
+
+    fn which_vertices_exist(&self, ids: &[Uuid]) -> Result<Vec<bool>> {
+        self.trans.which_vertices_exist(ids)
+    }
+
+    fn create_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        self.create_edge_with_ttl(key, None)
+    }
+
+    fn get_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<Vec<models::Edge>> {
+        match q.into() {
+            models::EdgeQuery::Specific(specific) => self.unexpired(self.trans.get_edges(specific)?),
+            models::EdgeQuery::Pipe(pipe) => {
+                let inner = self.resolve_vertex_query(*pipe.inner)?;
+                let limit = pipe.limit as usize;
+
+                let pipe = models::PipeEdgeQuery {
+                    inner: Box::new(inner),
+                    ..pipe
+                };
+
+                self.fetch_unexpired(limit, |fetch_limit| {
+                    let mut pipe = pipe.clone();
+                    pipe.limit = cmp::min(fetch_limit, u32::MAX as usize) as u32;
+                    self.trans.get_edges(pipe)
+                })
+            }
+        }
+    }
+
+    fn delete_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<()> {
+        let q = self.resolve_edge_query(q.into())?;
+        self.trans.delete_edges(q)
+    }
+
+    fn get_edge_count(&self, id: Uuid, t: Option<&models::Type>, direction: models::EdgeDirection) -> Result<u64> {
+        let mut q = models::SpecificVertexQuery::single(id).outbound(u32::MAX);
+        q.direction = direction;
+        q.t = t.cloned();
+        Ok(self.get_edges(q)?.len() as u64)
+    }
+
+    fn count_edges(&self, t: Option<&models::Type>) -> Result<u64> {
+        let mut q = models::RangeVertexQuery::new(u32::MAX).outbound(u32::MAX);
+        q.t = t.cloned();
+        Ok(self.get_edges(q)?.len() as u64)
+    }
+
+    fn edges_by_time(
+        &self,
+        after: Option<(DateTime<Utc>, models::EdgeKey)>,
+        limit: usize,
+    ) -> Result<Vec<models::Edge>> {
+        self.fetch_unexpired(limit, |fetch_limit| {
+            self.trans.edges_by_time(after.clone(), fetch_limit)
+        })
+    }
+
+    fn rename_edge_type(&self, from: models::Type, to: models::Type) -> Result<u64> {
+        self.trans.rename_edge_type(from, to)
+    }
+
+    fn get_vertex_properties(&self, q: models::VertexPropertyQuery) -> Result<Vec<models::VertexProperty>> {
+        let q = models::VertexPropertyQuery::new(self.resolve_vertex_query(q.inner)?, q.name);
+        self.trans.get_vertex_properties(q)
+    }
+
+    fn set_vertex_properties(&self, q: models::VertexPropertyQuery, value: &JsonValue) -> Result<()> {
+        let q = models::VertexPropertyQuery::new(self.resolve_vertex_query(q.inner)?, q.name);
+        self.trans.set_vertex_properties(q, value)
+    }
+
+    fn delete_vertex_properties(&self, q: models::VertexPropertyQuery) -> Result<()> {
+        let q = models::VertexPropertyQuery::new(self.resolve_vertex_query(q.inner)?, q.name);
+        self.trans.delete_vertex_properties(q)
+    }
+
+    fn get_edge_properties(&self, q: models::EdgePropertyQuery) -> Result<Vec<models::EdgeProperty>> {
+        let q = models::EdgePropertyQuery::new(self.resolve_edge_query(q.inner)?, q.name);
+        self.trans.get_edge_properties(q)
+    }
+
+    fn set_edge_properties(&self, q: models::EdgePropertyQuery, value: &JsonValue) -> Result<()> {
+        let q = models::EdgePropertyQuery::new(self.resolve_edge_query(q.inner)?, q.name);
+        self.trans.set_edge_properties(q, value)
+    }
+
+    fn delete_edge_properties(&self, q: models::EdgePropertyQuery) -> Result<()> {
+        let q = models::EdgePropertyQuery::new(self.resolve_edge_query(q.inner)?, q.name);
+        self.trans.delete_edge_properties(q)
+    }
+
+    fn get_edge_property_names(&self, key: &models::EdgeKey) -> Result<Vec<String>> {
+        self.trans.get_edge_property_names(key)
+    }
+}
+
+#[cfg(feature = "test-suite")]
+full_test_impl!(ExpiringDatastore::new(crate::memory::MemoryDatastore::default()));
+
+#[cfg(test)]
+mod tests {
+    use super::ExpiringDatastore;
+    use crate::memory::MemoryDatastore;
+    use crate::models::{
+        EdgeDirection, EdgeKey, EdgeQueryExt, SpecificEdgeQuery, SpecificVertexQuery, Type, VertexQueryExt,
+    };
+    use crate::traits::{Datastore, Transaction};
+    use std::thread::sleep;
+    use std::time::Duration;
+    use std::u32;
+
+    #[test]
+    fn should_hide_and_sweep_expired_edges() {
+        let datastore = ExpiringDatastore::new(MemoryDatastore::default());
+        let trans = datastore.transaction().unwrap();
+        let t = Type::new("test_type").unwrap();
+        let outbound_id = trans.create_vertex_from_type(t.clone()).unwrap();
+        let inbound_id = trans.create_vertex_from_type(t.clone()).unwrap();
+        let permanent_id = trans.create_vertex_from_type(t.clone()).unwrap();
+        let key = EdgeKey::new(outbound_id, t.clone(), inbound_id);
+        let permanent_key = EdgeKey::new(outbound_id, t.clone(), permanent_id);
+
+        trans
+            .create_edge_with_ttl(&key, Some(Duration::from_millis(100)))
+            .unwrap();
+        trans.create_edge(&permanent_key).unwrap();
+        assert!(trans.has_edge(&key).unwrap());
+        assert_eq!(trans.count_edges(None).unwrap(), 2);
+
+        sleep(Duration::from_millis(200));
+
+        // The edge is hidden as soon as it expires
+        assert!(!trans.has_edge(&key).unwrap());
+        assert_eq!(trans.count_edges(None).unwrap(), 1);
+        let count = trans
+            .get_edge_count(outbound_id, None, EdgeDirection::Outbound)
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let q = SpecificVertexQuery::single(outbound_id).outbound(1);
+        let edges = trans.get_edges(q).unwrap();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].key, permanent_key);
+
+        let q = SpecificVertexQuery::single(outbound_id)
+            .outbound(u32::MAX)
+            .inbound(u32::MAX);
+        let vertices = trans.get_vertices(q).unwrap();
+        assert_eq!(vertices.len(), 1);
+        assert_eq!(vertices[0].id, permanent_id);
+
+        // ...but is only deleted when swept
+        let inner_trans = datastore.inner().transaction().unwrap();
+        assert!(inner_trans.has_edge(&key).unwrap());
+        assert_eq!(datastore.sweep().unwrap(), 1);
+        assert!(!inner_trans.has_edge(&key).unwrap());
+        assert!(inner_trans.has_edge(&permanent_key).unwrap());
+        assert_eq!(datastore.sweep().unwrap(), 0);
+    }
+
+    #[test]
+    fn should_make_edges_permanent_when_recreated_without_a_ttl() {
+        let datastore = ExpiringDatastore::new(MemoryDatastore::default());
+        let trans = datastore.transaction().unwrap();
+        let t = Type::new("test_type").unwrap();
+        let outbound_id = trans.create_vertex_from_type(t.clone()).unwrap();
+        let inbound_id = trans.create_vertex_from_type(t.clone()).unwrap();
+        let key = EdgeKey::new(outbound_id, t.clone(), inbound_id);
+
+        trans
+            .create_edge_with_ttl(&key, Some(Duration::from_millis(100)))
+            .unwrap();
+        trans.create_edge(&key).unwrap();
+        sleep(Duration::from_millis(200));
+
+        let edges = trans.get_edges(SpecificEdgeQuery::single(key.clone())).unwrap();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(datastore.sweep().unwrap(), 0);
+    }
+}
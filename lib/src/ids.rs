@@ -0,0 +1,116 @@
+//! Strategies for generating the ids of new vertices.
+
+use chrono::offset::Utc;
+use crate::util::{generate_uuid_v1, nanos_since_epoch};
+use rand::{OsRng, Rng};
+use std::cmp;
+use std::fmt::Debug;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Generates ids for vertices created without a caller-specified id, e.g.
+/// via `Transaction::create_vertex_from_type`.
+pub trait IdGenerator: Debug + Send + Sync {
+    /// Generates a new id.
+    fn generate(&self) -> Uuid;
+}
+
+/// Generates UUID v1s. This is the default strategy.
+#[derive(Debug, Default)]
+pub struct UuidV1Generator;
+
+impl IdGenerator for UuidV1Generator {
+    fn generate(&self) -> Uuid {
+        generate_uuid_v1()
+    }
+}
+
+/// Generates random UUID v4s.
+#[derive(Debug, Default)]
+pub struct UuidV4Generator;
+
+impl IdGenerator for UuidV4Generator {
+    fn generate(&self) -> Uuid {
+        let mut bytes = [0u8; 16];
+        OsRng::new().unwrap().fill(&mut bytes);
+        // Set the version and variant bits, as per RFC 4122
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        Uuid::from_slice(&bytes).unwrap()
+    }
+}
+
+/// Generates time-ordered ids, so that new vertices are written to a narrow
+/// key range rather than scattered across the keyspace.
+///
+/// The first 8 bytes are the number of nanoseconds since unix epoch, bumped
+/// as needed so that every id is strictly greater (byte-wise) than the last
+/// one from the same generator. The last 8 bytes are random, but fixed for
+/// the lifetime of the generator. Note that the ids aren't valid RFC 4122
+/// UUIDs, as the version bits would break the ordering.
+#[derive(Debug)]
+pub struct SequentialUuidGenerator {
+    last: Mutex<u64>,
+    node: [u8; 8],
+}
+
+impl SequentialUuidGenerator {
+    /// Creates a new sequential id generator.
+    pub fn new() -> Self {
+        let mut node = [0u8; 8];
+        OsRng::new().unwrap().fill(&mut node);
+
+        Self {
+            last: Mutex::new(0),
+            node,
+        }
+    }
+}
+
+impl Default for SequentialUuidGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdGenerator for SequentialUuidGenerator {
+    fn generate(&self) -> Uuid {
+        let mut last = self.last.lock().unwrap();
+        *last = cmp::max(nanos_since_epoch(&Utc::now()), *last + 1);
+
+        let mut bytes = [0u8; 16];
+
+        for (i, byte) in bytes[..8].iter_mut().enumerate() {
+            *byte = (*last >> (56 - i * 8)) as u8;
+        }
+
+        bytes[8..].copy_from_slice(&self.node);
+        Uuid::from_slice(&bytes).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IdGenerator, SequentialUuidGenerator, UuidV4Generator};
+
+    #[test]
+    fn should_generate_sequential_ids() {
+        let generator = SequentialUuidGenerator::new();
+        let mut last = generator.generate();
+
+        for _ in 0..1000 {
+            let next = generator.generate();
+            assert!(next.as_bytes() > last.as_bytes());
+            last = next;
+        }
+    }
+
+    #[test]
+    fn should_generate_uuid_v4s() {
+        let generator = UuidV4Generator;
+        let first = generator.generate();
+        let second = generator.generate();
+        assert_ne!(first, second);
+        assert_eq!(first.get_version_num(), 4);
+    }
+}
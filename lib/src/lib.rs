@@ -37,18 +37,43 @@ pub mod tests;
 #[macro_use]
 pub mod benches;
 
+#[macro_use]
+mod macros;
+
+mod caching;
+mod diff;
 mod errors;
+mod expiry;
+mod ids;
+mod limits;
 mod memory;
+mod metrics;
 mod models;
+mod scan;
+mod sharding;
+mod stats;
 mod traits;
 pub mod util;
 
+#[cfg(any(test, feature = "test-suite", feature = "bench-suite"))]
+pub mod testkit;
+
+pub use crate::caching::{CachingDatastore, CachingTransaction};
+pub use crate::diff::Diff;
 pub use crate::errors::*;
-pub use crate::memory::{MemoryDatastore, MemoryTransaction};
+pub use crate::expiry::{ExpiringDatastore, ExpiringTransaction, EDGE_EXPIRY_PROPERTY};
+pub use crate::ids::*;
+pub use crate::limits::{LimitedDatastore, LimitedTransaction};
+pub use crate::memory::{MemoryConfig, MemoryDatastore, MemoryTransaction};
+pub use crate::metrics::*;
 pub use crate::models::*;
+pub use crate::sharding::{ShardedDatastore, ShardedTransaction, STUB_VERTEX_TYPE};
+pub use crate::stats::{ColumnStats, DatastoreStats};
 pub use crate::traits::*;
 
 #[cfg(feature = "rocksdb-datastore")]
 mod rdb;
 #[cfg(feature = "rocksdb-datastore")]
-pub use crate::rdb::{RocksdbDatastore, RocksdbTransaction};
+pub use crate::rdb::{
+    import_snapshot, DBCompressionType, Durability, RocksdbConfig, RocksdbDatastore, RocksdbTransaction,
+};
@@ -0,0 +1,298 @@
+//! Limits on the size of query results.
+//!
+//! Wrap any datastore in a `LimitedDatastore` to have queries that would
+//! return too many results fail with `Error::ResultTooLarge`, rather than
+//! having every result loaded into memory. Callers that do want everything
+//! can stream it with `Datastore::vertices_iter` or `Datastore::edges_iter`,
+//! which aren't limited.
+
+use chrono::offset::Utc;
+use chrono::DateTime;
+use crate::errors::{Error, Result};
+use crate::models;
+use crate::stats::DatastoreStats;
+use crate::traits::{Datastore, Transaction};
+use serde_json::value::Value as JsonValue;
+use uuid::Uuid;
+
+/// A datastore that limits how many results a query against an underlying
+/// datastore can return.
+#[derive(Debug)]
+pub struct LimitedDatastore<D: Datastore> {
+    datastore: D,
+    max_results: Option<u32>,
+}
+
+impl<D: Datastore> LimitedDatastore<D> {
+    /// Wraps a datastore with a result limit.
+    ///
+    /// # Arguments
+    /// * `datastore` - The datastore to wrap.
+    /// * `max_results` - The most results a query can return. If `None`,
+    ///   queries are unlimited.
+    pub fn new(datastore: D, max_results: Option<u32>) -> Self {
+        Self { datastore, max_results }
+    }
+
+    /// Gets the underlying datastore.
+    pub fn inner(&self) -> &D {
+        &self.datastore
+    }
+}
+
+impl<D: Datastore> Datastore for LimitedDatastore<D> {
+    type Trans = LimitedTransaction<D::Trans>;
+
+    fn transaction(&self) -> Result<Self::Trans> {
+        Ok(LimitedTransaction {
+            trans: self.datastore.transaction()?,
+            max_results: self.max_results,
+        })
+    }
+
+    fn bulk_insert<I>(&self, items: I) -> Result<()>
+    where
+        I: Iterator<Item = models::BulkInsertItem>,
+    {
+        self.datastore.bulk_insert(items)
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.datastore.clear()
+    }
+
+    fn compact(&self) -> Result<()> {
+        self.datastore.compact()
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.datastore.flush()
+    }
+
+    fn stats(&self) -> Result<DatastoreStats> {
+        self.datastore.stats()
+    }
+
+    fn vertices_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Vertex>> + 'a> {
+        self.datastore.vertices_iter()
+    }
+
+    fn edges_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Edge>> + 'a> {
+        self.datastore.edges_iter()
+    }
+}
+
+/// A transaction that limits how many results its queries can return.
+#[derive(Debug)]
+pub struct LimitedTransaction<T: Transaction> {
+    trans: T,
+    max_results: Option<u32>,
+}
+
+impl<T: Transaction> LimitedTransaction<T> {
+    // Lowers the limits of a query so that it gets at most one result more
+    // than the maximum, which is enough to tell that it went over
+    fn limit_vertex_query(&self, q: models::VertexQuery) -> models::VertexQuery {
+        let max_results = match self.max_results {
+            Some(max_results) => max_results.saturating_add(1),
+            None => return q,
+        };
+
+        match q {
+            models::VertexQuery::Range(mut range) => {
+                range.limit = range.limit.min(max_results);
+                models::VertexQuery::Range(range)
+            }
+            models::VertexQuery::Pipe(mut pipe) => {
+                pipe.limit = pipe.limit.min(max_results);
+                models::VertexQuery::Pipe(pipe)
+            }
+            q => q,
+        }
+    }
+
+    fn limit_edge_query(&self, q: models::EdgeQuery) -> models::EdgeQuery {
+        let max_results = match self.max_results {
+            Some(max_results) => max_results.saturating_add(1),
+            None => return q,
+        };
+
+        match q {
+            models::EdgeQuery::Pipe(mut pipe) => {
+                pipe.limit = pipe.limit.min(max_results);
+                models::EdgeQuery::Pipe(pipe)
+            }
+            q => q,
+        }
+    }
+
+    fn check<R>(&self, results: Vec<R>) -> Result<Vec<R>> {
+        match self.max_results {
+            Some(limit) if results.len() > limit as usize => Err(Error::ResultTooLarge { limit }),
+            _ => Ok(results),
+        }
+    }
+}
+
+impl<T: Transaction> Transaction for LimitedTransaction<T> {
+    fn create_vertex(&self, vertex: &models::Vertex) -> Result<bool> {
+        self.trans.create_vertex(vertex)
+    }
+
+    fn create_vertex_from_type(&self, t: models::Type) -> Result<Uuid> {
+        self.trans.create_vertex_from_type(t)
+    }
+
+    fn get_or_create_vertex(&self, id: Uuid, t: models::Type) -> Result<(models::Vertex, bool)> {
+        self.trans.get_or_create_vertex(id, t)
+    }
+
+    fn get_vertices<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<Vec<models::Vertex>> {
+        let q = self.limit_vertex_query(q.into());
+        self.check(self.trans.get_vertices(q)?)
+    }
+
+    fn delete_vertices<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<()> {
+        self.trans.delete_vertices(q)
+    }
+
+    fn delete_vertices_by_type(&self, t: models::Type) -> Result<u64> {
+        self.trans.delete_vertices_by_type(t)
+    }
+
+    fn get_vertex_count(&self) -> Result<u64> {
+        self.trans.get_vertex_count()
+    }
+
+    fn count_vertices(&self, t: Option<&models::Type>) -> Result<u64> {
+        self.trans.count_vertices(t)
+    }
+
+    fn which_vertices_exist(&self, ids: &[Uuid]) -> Result<Vec<bool>> {
+        self.trans.which_vertices_exist(ids)
+    }
+
+    fn create_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        self.trans.create_edge(key)
+    }
+
+    fn create_edges(&self, keys: &[models::EdgeKey]) -> Result<Vec<bool>> {
+        self.trans.create_edges(keys)
+    }
+
+    fn get_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<Vec<models::Edge>> {
+        let q = self.limit_edge_query(q.into());
+        self.check(self.trans.get_edges(q)?)
+    }
+
+    fn has_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        self.trans.has_edge(key)
+    }
+
+    fn delete_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<()> {
+        self.trans.delete_edges(q)
+    }
+
+    fn get_edge_count(&self, id: Uuid, t: Option<&models::Type>, direction: models::EdgeDirection) -> Result<u64> {
+        self.trans.get_edge_count(id, t, direction)
+    }
+
+    fn count_edges(&self, t: Option<&models::Type>) -> Result<u64> {
+        self.trans.count_edges(t)
+    }
+
+    fn edges_by_time(
+        &self,
+        after: Option<(DateTime<Utc>, models::EdgeKey)>,
+        limit: usize,
+    ) -> Result<Vec<models::Edge>> {
+        let limit = match self.max_results {
+            Some(max_results) => limit.min(max_results as usize + 1),
+            None => limit,
+        };
+
+        self.check(self.trans.edges_by_time(after, limit)?)
+    }
+
+    fn rename_edge_type(&self, from: models::Type, to: models::Type) -> Result<u64> {
+        self.trans.rename_edge_type(from, to)
+    }
+
+    fn get_vertex_properties(&self, q: models::VertexPropertyQuery) -> Result<Vec<models::VertexProperty>> {
+        let q = models::VertexPropertyQuery::new(self.limit_vertex_query(q.inner), q.name);
+        self.check(self.trans.get_vertex_properties(q)?)
+    }
+
+    fn set_vertex_properties(&self, q: models::VertexPropertyQuery, value: &JsonValue) -> Result<()> {
+        self.trans.set_vertex_properties(q, value)
+    }
+
+    fn delete_vertex_properties(&self, q: models::VertexPropertyQuery) -> Result<()> {
+        self.trans.delete_vertex_properties(q)
+    }
+
+    fn get_edge_properties(&self, q: models::EdgePropertyQuery) -> Result<Vec<models::EdgeProperty>> {
+        let q = models::EdgePropertyQuery::new(self.limit_edge_query(q.inner), q.name);
+        self.check(self.trans.get_edge_properties(q)?)
+    }
+
+    fn set_edge_properties(&self, q: models::EdgePropertyQuery, value: &JsonValue) -> Result<()> {
+        self.trans.set_edge_properties(q, value)
+    }
+
+    fn delete_edge_properties(&self, q: models::EdgePropertyQuery) -> Result<()> {
+        self.trans.delete_edge_properties(q)
+    }
+
+    fn get_edge_property_names(&self, key: &models::EdgeKey) -> Result<Vec<String>> {
+        self.trans.get_edge_property_names(key)
+    }
+}
+
+#[cfg(feature = "test-suite")]
+full_test_impl!(LimitedDatastore::new(crate::memory::MemoryDatastore::default(), None));
+
+#[cfg(test)]
+mod tests {
+    use super::LimitedDatastore;
+    use crate::errors::Error;
+    use crate::memory::MemoryDatastore;
+    use crate::models::{EdgeDirection, EdgeKey, RangeVertexQuery, SpecificVertexQuery, Type, VertexQueryExt};
+    use crate::traits::{Datastore, Transaction};
+    use std::u32;
+
+    #[test]
+    fn should_fail_queries_with_too_many_results() {
+        let datastore = LimitedDatastore::new(MemoryDatastore::default(), Some(2));
+        let trans = datastore.transaction().unwrap();
+        let t = Type::new("test_type").unwrap();
+        let ids: Vec<_> = (0..3)
+            .map(|_| trans.create_vertex_from_type(t.clone()).unwrap())
+            .collect();
+
+        for id in &ids[1..] {
+            trans.create_edge(&EdgeKey::new(ids[0], t.clone(), *id)).unwrap();
+        }
+
+        assert_eq!(trans.get_vertices(RangeVertexQuery::new(2)).unwrap().len(), 2);
+        let q = SpecificVertexQuery::new(ids[..2].to_vec());
+        assert_eq!(trans.get_vertices(q).unwrap().len(), 2);
+
+        let q = SpecificVertexQuery::single(ids[0]).outbound(u32::MAX);
+        assert_eq!(trans.get_edges(q).unwrap().len(), 2);
+
+        match trans.get_vertices(RangeVertexQuery::new(u32::MAX)) {
+            Err(Error::ResultTooLarge { limit: 2 }) => (),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        match trans.get_vertices(SpecificVertexQuery::new(ids.clone())) {
+            Err(Error::ResultTooLarge { limit: 2 }) => (),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        // Streaming isn't limited
+        assert_eq!(datastore.vertices_iter().count(), 3);
+        assert_eq!(trans.get_edge_count(ids[0], None, EdgeDirection::Outbound).unwrap(), 2);
+    }
+}
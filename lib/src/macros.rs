@@ -0,0 +1,99 @@
+/// Builds a query for a common traversal pattern, using a terser syntax than
+/// the query builders. Queries are unlimited, i.e. they have a limit of
+/// `u32::MAX`; use the builders directly for anything else.
+///
+/// Supported patterns:
+/// * `query!(vertices)` - All vertices.
+/// * `query!(vertices of type "t")` - All vertices of type `t`.
+/// * `query!(edges from id)` / `query!(edges to id)` - The outbound or
+///   inbound edges of the vertex `id`.
+/// * `query!(edges from id of type "t")` / `query!(edges to id of type "t")`
+///   - As above, but only edges of type `t`.
+///
+/// The vertex id must be a single token, so wrap anything more complex than
+/// a variable in parentheses, e.g. `query!(edges from (vertex.id))`.
+///
+/// # Errors
+/// The `of type` forms return a `ValidationResult`, since type literals
+/// can't be checked at compile time without a procedural macro. They fail
+/// in the same way as `Type::new` when the literal is not a valid type.
+///
+/// # Examples
+/// ```
+/// # #[macro_use] extern crate indradb;
+/// # use indradb::{RangeVertexQuery, Type};
+/// # use std::u32;
+/// # fn main() {
+/// let q = query!(vertices of type "user").unwrap();
+/// assert_eq!(q, RangeVertexQuery::new(u32::MAX).t(Type::new("user").unwrap()));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! query {
+    (vertices) => {
+        $crate::RangeVertexQuery::new(::std::u32::MAX)
+    };
+    (vertices of type $t:literal) => {
+        $crate::Type::new($t).map(|t| $crate::query!(vertices).t(t))
+    };
+    (edges from $id:tt) => {
+        $crate::VertexQueryExt::outbound($crate::SpecificVertexQuery::single($id), ::std::u32::MAX)
+    };
+    (edges to $id:tt) => {
+        $crate::VertexQueryExt::inbound($crate::SpecificVertexQuery::single($id), ::std::u32::MAX)
+    };
+    (edges from $id:tt of type $t:literal) => {
+        $crate::Type::new($t).map(|t| $crate::query!(edges from $id).t(t))
+    };
+    (edges to $id:tt of type $t:literal) => {
+        $crate::Type::new($t).map(|t| $crate::query!(edges to $id).t(t))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::ValidationError;
+    use crate::models::{EdgeDirection, RangeVertexQuery, SpecificVertexQuery, Type, VertexQuery, VertexQueryExt};
+    use std::u32;
+    use uuid::Uuid;
+
+    #[test]
+    fn should_build_vertex_queries() {
+        assert_eq!(query!(vertices), RangeVertexQuery::new(u32::MAX));
+        assert_eq!(
+            query!(vertices of type "user").unwrap(),
+            RangeVertexQuery::new(u32::MAX).t(Type::new("user").unwrap())
+        );
+    }
+
+    #[test]
+    fn should_build_edge_queries() {
+        let id = Uuid::default();
+        let t = Type::new("liked").unwrap();
+
+        let q = query!(edges from id of type "liked").unwrap();
+        assert_eq!(q, SpecificVertexQuery::single(id).outbound(u32::MAX).t(t.clone()));
+        assert_eq!(q.direction, EdgeDirection::Outbound);
+        assert_eq!(*q.inner, VertexQuery::Specific(SpecificVertexQuery::single(id)));
+
+        let q = query!(edges to (Uuid::default()) of type "liked").unwrap();
+        assert_eq!(q, SpecificVertexQuery::single(id).inbound(u32::MAX).t(t));
+
+        let q = query!(edges from id);
+        assert_eq!(q, SpecificVertexQuery::single(id).outbound(u32::MAX));
+    }
+
+    #[test]
+    fn should_fail_on_invalid_type_literals() {
+        match query!(vertices of type "not a valid type") {
+            Err(ValidationError::InvalidType { offending: ' ' }) => (),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        let id = Uuid::default();
+        match query!(edges from id of type "") {
+            Err(ValidationError::InvalidValue) => (),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+}
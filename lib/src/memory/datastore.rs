@@ -2,12 +2,80 @@ use super::super::{Datastore, EdgePropertyQuery, EdgeQuery, Transaction, VertexP
 use chrono::offset::Utc;
 use chrono::DateTime;
 use serde_json::Value as JsonValue;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::mem;
+use std::ops::Bound::{Excluded, Unbounded};
+use std::path::Path;
 use std::sync::{Arc, RwLock};
+use std::u32;
 use uuid::Uuid;
 
-use crate::errors::Result;
+use crate::errors::{Error, Result, ValidationError};
+use crate::ids::{IdGenerator, UuidV1Generator};
 use crate::models;
+use crate::stats::{ColumnStats, DatastoreStats};
+
+// Stores each distinct type once, so that the vertices and edges of a type
+// share a single copy of its name. Interned types are handed out as `Arc`s
+// rather than integer ids, because `Arc` compares, orders and hashes by the
+// type itself. That keeps the edge map sorted by type name, which the
+// range scans below depend on.
+#[derive(Debug, Default)]
+struct TypeInterner {
+    types: HashSet<Arc<models::Type>>,
+}
+
+impl TypeInterner {
+    fn intern(&mut self, t: &models::Type) -> Arc<models::Type> {
+        if let Some(interned) = self.types.get(t) {
+            return Arc::clone(interned);
+        }
+
+        let interned = Arc::new(t.clone());
+        self.types.insert(Arc::clone(&interned));
+        interned
+    }
+
+    fn get(&self, t: &models::Type) -> Option<&Arc<models::Type>> {
+        self.types.get(t)
+    }
+
+    // Drops the types that are no longer referenced by anything other than
+    // the interner itself
+    fn remove_unused(&mut self) {
+        self.types.retain(|t| Arc::strong_count(t) > 1);
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.types.len()
+    }
+}
+
+// An edge key with an interned type. The fields are in the same order as
+// `models::EdgeKey`, so both sort the same way.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+struct InternedEdgeKey {
+    outbound_id: Uuid,
+    t: Arc<models::Type>,
+    inbound_id: Uuid,
+}
+
+impl InternedEdgeKey {
+    fn new(outbound_id: Uuid, t: Arc<models::Type>, inbound_id: Uuid) -> Self {
+        InternedEdgeKey {
+            outbound_id,
+            t,
+            inbound_id,
+        }
+    }
+
+    fn to_key(&self) -> models::EdgeKey {
+        models::EdgeKey::new(self.outbound_id, (*self.t).clone(), self.inbound_id)
+    }
+}
 
 // All of the data is actually stored in this struct, which is stored
 // internally to the datastore itself. This way, we can wrap an rwlock around
@@ -15,28 +83,77 @@ use crate::models;
 // latter approach would risk deadlocking without extreme care.
 #[derive(Debug)]
 struct InternalMemoryDatastore {
-    edge_properties: BTreeMap<(models::EdgeKey, String), JsonValue>,
-    edges: BTreeMap<models::EdgeKey, DateTime<Utc>>,
+    edge_properties: BTreeMap<(InternedEdgeKey, String), JsonValue>,
+    edges: BTreeMap<InternedEdgeKey, DateTime<Utc>>,
     vertex_properties: BTreeMap<(Uuid, String), JsonValue>,
-    vertices: BTreeMap<Uuid, models::Type>,
+    vertices: BTreeMap<Uuid, Arc<models::Type>>,
+    vertex_types: HashMap<Arc<models::Type>, HashSet<Uuid>>,
+    types: TypeInterner,
+    id_generator: Arc<dyn IdGenerator>,
+    dedup_edges: bool,
 }
 
 impl InternalMemoryDatastore {
     fn get_vertex_values_by_query(&self, q: VertexQuery) -> Result<Vec<(Uuid, models::Type)>> {
         match q {
             VertexQuery::Range(range) => {
-                let mut iter: Box<dyn Iterator<Item = (&Uuid, &models::Type)>> = if let Some(start_id) = range.start_id
-                {
+                if range.t.is_some() || range.type_prefix.is_some() {
+                    // Type-scoped queries are served from the type index, so
+                    // that vertices of other types are never visited
+                    let types: Vec<(&models::Type, &HashSet<Uuid>)> = match range.t {
+                        Some(ref t) => self.vertex_types.get(t).map(|ids| (t, ids)).into_iter().collect(),
+                        None => self.vertex_types.iter().map(|(t, ids)| (&**t, ids)).collect(),
+                    };
+
+                    let mut results = Vec::new();
+
+                    for (t, ids) in types {
+                        if let Some(ref type_prefix) = range.type_prefix {
+                            if !t.0.starts_with(&type_prefix[..]) {
+                                continue;
+                            }
+                        }
+
+                        for id in ids {
+                            if !range.start_id.map_or(true, |start_id| *id >= start_id) {
+                                continue;
+                            }
+
+                            if let Some(ref id_range) = range.id_range {
+                                if *id < id_range.start || *id >= id_range.end {
+                                    continue;
+                                }
+                            }
+
+                            results.push((*id, t.clone()));
+                        }
+                    }
+
+                    results.sort();
+                    results.truncate(range.limit as usize);
+                    return Ok(results);
+                }
+
+                let start_id = range
+                    .start_id
+                    .into_iter()
+                    .chain(range.id_range.as_ref().map(|id_range| id_range.start))
+                    .max();
+
+                let mut iter: Box<dyn Iterator<Item = (&Uuid, &Arc<models::Type>)>> = if let Some(start_id) = start_id {
                     Box::new(self.vertices.range(start_id..))
                 } else {
                     Box::new(self.vertices.iter())
                 };
 
-                if let Some(ref t) = range.t {
-                    iter = Box::new(iter.filter(move |(_, v)| v == &t));
+                if let Some(id_range) = range.id_range {
+                    iter = Box::new(iter.take_while(move |(id, _)| **id < id_range.end));
                 }
 
-                Ok(iter.take(range.limit as usize).map(|(k, v)| (*k, v.clone())).collect())
+                Ok(iter
+                    .take(range.limit as usize)
+                    .map(|(k, v)| (*k, (**v).clone()))
+                    .collect())
             }
             VertexQuery::Specific(specific) => {
                 let mut results = Vec::new();
@@ -45,7 +162,7 @@ impl InternalMemoryDatastore {
                     let value = self.vertices.get(&id);
 
                     if let Some(value) = value {
-                        results.push((id, value.clone()));
+                        results.push((id, (**value).clone()));
                     }
                 }
 
@@ -61,7 +178,7 @@ impl InternalMemoryDatastore {
 
                 let mut iter: Box<dyn Iterator<Item = (Uuid, &models::Type)>> = Box::new(
                     iter.map(|id| (id, self.vertices.get(&id)))
-                        .filter_map(|(k, v)| Some((k, v?))),
+                        .filter_map(|(k, v)| Some((k, &**v?))),
                 );
 
                 if let Some(ref t) = pipe.t {
@@ -79,7 +196,7 @@ impl InternalMemoryDatastore {
                 let mut results = Vec::new();
 
                 for key in specific.keys {
-                    let value = self.edges.get(&key);
+                    let value = self.edge_key(&key).and_then(|key| self.edges.get(&key));
 
                     if let Some(update_datetime) = value {
                         results.push((key.clone(), *update_datetime));
@@ -89,6 +206,31 @@ impl InternalMemoryDatastore {
                 Ok(results)
             }
             EdgeQuery::Pipe(pipe) => {
+                // Sorting happens before the limit, so every matching edge
+                // has to be found before any can be returned
+                if let Some(sort) = pipe.sort {
+                    let limit = pipe.limit as usize;
+                    let unsorted = models::PipeEdgeQuery {
+                        limit: u32::MAX,
+                        sort: None,
+                        ..pipe
+                    };
+
+                    let mut results = self.get_edge_values_by_query(unsorted.into())?;
+
+                    results.sort_by(|(first_key, first_datetime), (second_key, second_datetime)| {
+                        sort.order(match sort {
+                            models::SortKey::UpdatedAt { .. } => first_datetime
+                                .cmp(second_datetime)
+                                .then_with(|| first_key.cmp(second_key)),
+                            models::SortKey::Id { .. } => first_key.cmp(second_key),
+                        })
+                    });
+
+                    results.truncate(limit);
+                    return Ok(results);
+                }
+
                 let vertex_values = self.get_vertex_values_by_query(*pipe.inner)?;
                 let mut results = Vec::new();
 
@@ -97,13 +239,13 @@ impl InternalMemoryDatastore {
                 }
 
                 match pipe.direction {
-                    models::EdgeDirection::Outbound => {
+                    models::EdgeDirection::Outbound if !pipe.undirected => {
                         for (id, _) in vertex_values {
                             let lower_bound = match &pipe.t {
-                                Some(t) => models::EdgeKey::new(id, t.clone(), Uuid::default()),
+                                Some(t) => InternedEdgeKey::new(id, Arc::new(t.clone()), Uuid::default()),
                                 None => {
-                                    let empty_type = models::Type::default();
-                                    models::EdgeKey::new(id, empty_type, Uuid::default())
+                                    let empty_type = Arc::new(models::Type::default());
+                                    InternedEdgeKey::new(id, empty_type, Uuid::default())
                                 }
                             };
 
@@ -113,11 +255,23 @@ impl InternalMemoryDatastore {
                                 }
 
                                 if let Some(t) = &pipe.t {
-                                    if &key.t != t {
+                                    if *key.t != *t {
                                         break;
                                     }
                                 }
 
+                                if let Some(type_prefix) = &pipe.type_prefix {
+                                    if !key.t.0.starts_with(&type_prefix[..]) {
+                                        continue;
+                                    }
+                                }
+
+                                if let Some(types) = &pipe.types {
+                                    if !types.contains(&*key.t) {
+                                        continue;
+                                    }
+                                }
+
                                 if let Some(high) = &pipe.high {
                                     if update_datetime > high {
                                         continue;
@@ -130,7 +284,7 @@ impl InternalMemoryDatastore {
                                     }
                                 }
 
-                                results.push((key.clone(), *update_datetime));
+                                results.push((key.to_key(), *update_datetime));
 
                                 if results.len() == pipe.limit as usize {
                                     return Ok(results);
@@ -138,19 +292,36 @@ impl InternalMemoryDatastore {
                             }
                         }
                     }
-                    models::EdgeDirection::Inbound => {
+                    _ => {
                         let mut candidate_ids = HashSet::new();
                         for (id, _) in vertex_values {
                             candidate_ids.insert(id);
                         }
 
+                        // Every edge is visited once, so undirected queries
+                        // can't return an edge twice
                         for (key, update_datetime) in &self.edges {
-                            if !candidate_ids.contains(&key.inbound_id) {
+                            let is_candidate = candidate_ids.contains(&key.inbound_id)
+                                || (pipe.undirected && candidate_ids.contains(&key.outbound_id));
+
+                            if !is_candidate {
                                 continue;
                             }
 
                             if let Some(t) = &pipe.t {
-                                if &key.t != t {
+                                if *key.t != *t {
+                                    continue;
+                                }
+                            }
+
+                            if let Some(type_prefix) = &pipe.type_prefix {
+                                if !key.t.0.starts_with(&type_prefix[..]) {
+                                    continue;
+                                }
+                            }
+
+                            if let Some(types) = &pipe.types {
+                                if !types.contains(&*key.t) {
                                     continue;
                                 }
                             }
@@ -167,7 +338,7 @@ impl InternalMemoryDatastore {
                                 }
                             }
 
-                            results.push((key.clone(), *update_datetime));
+                            results.push((key.to_key(), *update_datetime));
 
                             if results.len() == pipe.limit as usize {
                                 return Ok(results);
@@ -181,9 +352,32 @@ impl InternalMemoryDatastore {
         }
     }
 
+    fn create_vertex(&mut self, id: Uuid, t: models::Type) -> bool {
+        if self.vertices.contains_key(&id) {
+            return false;
+        }
+
+        let t = self.types.intern(&t);
+        self.vertex_types.entry(Arc::clone(&t)).or_default().insert(id);
+        self.vertices.insert(id, t);
+        true
+    }
+
     fn delete_vertices(&mut self, vertices: Vec<Uuid>) {
         for vertex_id in vertices {
-            self.vertices.remove(&vertex_id);
+            if let Some(t) = self.vertices.remove(&vertex_id) {
+                let is_type_empty = match self.vertex_types.get_mut(&t) {
+                    Some(ids) => {
+                        ids.remove(&vertex_id);
+                        ids.is_empty()
+                    }
+                    None => false,
+                };
+
+                if is_type_empty {
+                    self.vertex_types.remove(&t);
+                }
+            }
 
             let mut deletable_vertex_properties: Vec<(Uuid, String)> = Vec::new();
 
@@ -205,63 +399,483 @@ impl InternalMemoryDatastore {
 
             for edge_key in self.edges.keys() {
                 if edge_key.outbound_id == vertex_id || edge_key.inbound_id == vertex_id {
-                    deletable_edges.push(edge_key.clone());
+                    deletable_edges.push(edge_key.to_key());
                 }
             }
 
             self.delete_edges(deletable_edges);
         }
+
+        self.types.remove_unused();
+    }
+
+    fn create_edge(&mut self, key: &models::EdgeKey, dedup: bool) -> bool {
+        if !self.vertices.contains_key(&key.outbound_id) || !self.vertices.contains_key(&key.inbound_id) {
+            return false;
+        }
+
+        let key = self.intern_edge_key(key);
+
+        if dedup && self.edges.contains_key(&key) {
+            return false;
+        }
+
+        self.edges.insert(key, Utc::now());
+        true
     }
 
     fn delete_edges(&mut self, edges: Vec<models::EdgeKey>) {
         for edge_key in edges {
-            self.edges.remove(&edge_key);
+            if let Some(edge_key) = self.edge_key(&edge_key) {
+                self.edges.remove(&edge_key);
 
-            let mut deletable_edge_properties: Vec<(models::EdgeKey, String)> = Vec::new();
+                for property_key in self.get_edge_property_keys(&edge_key) {
+                    self.edge_properties.remove(&property_key);
+                }
+            }
+        }
 
-            for (property_key, _) in self.edge_properties.range((edge_key.clone(), "".to_string())..) {
-                let &(ref property_edge_key, _) = property_key;
+        self.types.remove_unused();
+    }
 
-                if &edge_key != property_edge_key {
-                    break;
-                }
+    fn get_edge_property_keys(&self, edge_key: &InternedEdgeKey) -> Vec<(InternedEdgeKey, String)> {
+        let mut property_keys = Vec::new();
 
-                deletable_edge_properties.push(property_key.clone());
+        for (property_key, _) in self.edge_properties.range((edge_key.clone(), "".to_string())..) {
+            let &(ref property_edge_key, _) = property_key;
+
+            if edge_key != property_edge_key {
+                break;
             }
 
-            for property_key in deletable_edge_properties {
-                self.edge_properties.remove(&property_key);
+            property_keys.push(property_key.clone());
+        }
+
+        property_keys
+    }
+
+    fn rename_edge_type(&mut self, from: &models::Type, to: &models::Type) -> u64 {
+        let renamable_edges: Vec<InternedEdgeKey> = self.edges.keys().filter(|k| *k.t == *from).cloned().collect();
+        let count = renamable_edges.len() as u64;
+
+        for old_key in renamable_edges {
+            let new_key = models::EdgeKey::new(old_key.outbound_id, to.clone(), old_key.inbound_id);
+
+            // Clear out the edge being replaced, if there is one
+            self.delete_edges(vec![new_key.clone()]);
+
+            let new_key = self.intern_edge_key(&new_key);
+            let update_datetime = self.edges.remove(&old_key).unwrap();
+            self.edges.insert(new_key.clone(), update_datetime);
+
+            for property_key in self.get_edge_property_keys(&old_key) {
+                let value = self.edge_properties.remove(&property_key).unwrap();
+                self.edge_properties.insert((new_key.clone(), property_key.1), value);
             }
         }
+
+        // The old keys are all dropped by now, so this forgets `from`
+        self.types.remove_unused();
+        count
     }
+
+    // Gets the interned version of an edge key, without interning its type.
+    // If the type was never interned, no edge can have the key.
+    fn edge_key(&self, key: &models::EdgeKey) -> Option<InternedEdgeKey> {
+        let t = self.types.get(&key.t)?;
+        Some(InternedEdgeKey::new(key.outbound_id, Arc::clone(t), key.inbound_id))
+    }
+
+    fn intern_edge_key(&mut self, key: &models::EdgeKey) -> InternedEdgeKey {
+        let t = self.types.intern(&key.t);
+        InternedEdgeKey::new(key.outbound_id, t, key.inbound_id)
+    }
+}
+
+/// Options for an in-memory datastore. The defaults match the options used
+/// by `MemoryDatastore::default`.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryConfig {
+    /// The strategy for generating the ids of vertices created without one.
+    /// If `None`, UUID v1s will be used.
+    pub id_generator: Option<Arc<dyn IdGenerator>>,
+    /// Whether creating an edge that already exists should be a no-op that
+    /// returns false, rather than updating the edge's datetime. Bulk inserts
+    /// still overwrite existing edges.
+    pub dedup_edges: bool,
 }
 
 /// An in-memory-only datastore.
-#[derive(Debug)]
+///
+/// Cloning the datastore is cheap, and clones share the same underlying
+/// data, so a clone can be handed to each thread that needs access. Reads
+/// and writes go through a single read-write lock: every transaction
+/// operation sees the results of operations completed before it on any
+/// clone, but operations aren't grouped into atomic units across calls.
+#[derive(Clone, Debug)]
 pub struct MemoryDatastore(Arc<RwLock<InternalMemoryDatastore>>);
 
+fn snapshot_field<'a>(value: &'a JsonValue, name: &str) -> Result<&'a JsonValue> {
+    value.get(name).ok_or(Error::InvalidSnapshot)
+}
+
+fn snapshot_array<'a>(value: &'a JsonValue, name: &str) -> Result<&'a Vec<JsonValue>> {
+    snapshot_field(value, name)?.as_array().ok_or(Error::InvalidSnapshot)
+}
+
+fn snapshot_str<'a>(value: &'a JsonValue, name: &str) -> Result<&'a str> {
+    snapshot_field(value, name)?.as_str().ok_or(Error::InvalidSnapshot)
+}
+
+fn snapshot_uuid(value: &JsonValue, name: &str) -> Result<Uuid> {
+    Uuid::parse_str(snapshot_str(value, name)?).map_err(|_| Error::InvalidSnapshot)
+}
+
+// Types were validated when they were first stored, possibly against a
+// custom pattern, so only the invariants every type holds are rechecked
+fn snapshot_type(value: &JsonValue, name: &str) -> Result<models::Type> {
+    let t = unsafe { models::Type::new_unchecked(snapshot_str(value, name)?) };
+    t.validate().map_err(|_| Error::InvalidSnapshot)?;
+    Ok(t)
+}
+
+fn snapshot_datetime(value: &JsonValue, name: &str) -> Result<DateTime<Utc>> {
+    let datetime = DateTime::parse_from_rfc3339(snapshot_str(value, name)?).map_err(|_| Error::InvalidSnapshot)?;
+    Ok(datetime.with_timezone(&Utc))
+}
+
+fn snapshot_edge_key(value: &JsonValue) -> Result<models::EdgeKey> {
+    Ok(models::EdgeKey::new(
+        snapshot_uuid(value, "outbound_id")?,
+        snapshot_type(value, "t")?,
+        snapshot_uuid(value, "inbound_id")?,
+    ))
+}
+
+fn edge_key_snapshot(key: &models::EdgeKey) -> serde_json::Map<String, JsonValue> {
+    let mut map = serde_json::Map::new();
+    map.insert(
+        "outbound_id".to_string(),
+        JsonValue::String(key.outbound_id.to_string()),
+    );
+    map.insert("t".to_string(), JsonValue::String(key.t.0.clone()));
+    map.insert("inbound_id".to_string(), JsonValue::String(key.inbound_id.to_string()));
+    map
+}
+
 impl MemoryDatastore {
     /// Creates a new in-memory datastore.
     pub fn default() -> MemoryDatastore {
+        Self::new_with_options(MemoryConfig::default())
+    }
+
+    /// Creates a new in-memory datastore with custom options.
+    ///
+    /// # Arguments
+    /// * `config` - The options to create the datastore with.
+    pub fn new_with_options(config: MemoryConfig) -> MemoryDatastore {
+        let id_generator = config.id_generator.unwrap_or_else(|| Arc::new(UuidV1Generator));
         Self {
             0: Arc::new(RwLock::new(InternalMemoryDatastore {
                 edge_properties: BTreeMap::new(),
                 edges: BTreeMap::new(),
                 vertex_properties: BTreeMap::new(),
                 vertices: BTreeMap::new(),
+                vertex_types: HashMap::new(),
+                types: TypeInterner::default(),
+                id_generator,
+                dedup_edges: config.dedup_edges,
             })),
         }
     }
+
+    // Gets how many distinct types are interned, for testing that types are
+    // shared
+    #[cfg(test)]
+    pub(crate) fn interned_type_count(&self) -> usize {
+        self.0.read().unwrap().types.len()
+    }
+
+    /// Saves all of the datastore's vertices, edges and properties to a JSON
+    /// file, which can be restored with `load`.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the file to write. It is overwritten if it
+    ///   already exists.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let datastore = self.0.read().unwrap();
+
+        let vertices = datastore
+            .vertices
+            .iter()
+            .map(|(id, t)| serde_json::json!({ "id": id.to_string(), "t": t.0 }))
+            .collect();
+
+        let edges = datastore
+            .edges
+            .iter()
+            .map(|(key, created_datetime)| {
+                let mut map = edge_key_snapshot(&key.to_key());
+                map.insert(
+                    "created_datetime".to_string(),
+                    JsonValue::String(created_datetime.to_rfc3339()),
+                );
+                JsonValue::Object(map)
+            })
+            .collect();
+
+        let vertex_properties = datastore
+            .vertex_properties
+            .iter()
+            .map(|((id, name), value)| serde_json::json!({ "id": id.to_string(), "name": name, "value": value }))
+            .collect();
+
+        let edge_properties = datastore
+            .edge_properties
+            .iter()
+            .map(|((key, name), value)| {
+                let mut map = edge_key_snapshot(&key.to_key());
+                map.insert("name".to_string(), JsonValue::String(name.clone()));
+                map.insert("value".to_string(), value.clone());
+                JsonValue::Object(map)
+            })
+            .collect();
+
+        let snapshot = serde_json::json!({
+            "vertices": JsonValue::Array(vertices),
+            "edges": JsonValue::Array(edges),
+            "vertex_properties": JsonValue::Array(vertex_properties),
+            "edge_properties": JsonValue::Array(edge_properties),
+        });
+
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(writer, &snapshot)?;
+        Ok(())
+    }
+
+    /// Creates a new in-memory datastore from a file written by `save`.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the file to read.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidSnapshot` if the file is valid JSON, but not a
+    /// snapshot.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<MemoryDatastore> {
+        let reader = BufReader::new(File::open(path)?);
+        let snapshot: JsonValue = serde_json::from_reader(reader)?;
+        let datastore = MemoryDatastore::default();
+
+        {
+            let mut internal = datastore.0.write().unwrap();
+
+            for value in snapshot_array(&snapshot, "vertices")? {
+                internal.create_vertex(snapshot_uuid(value, "id")?, snapshot_type(value, "t")?);
+            }
+
+            for value in snapshot_array(&snapshot, "edges")? {
+                let key = internal.intern_edge_key(&snapshot_edge_key(value)?);
+                internal
+                    .edges
+                    .insert(key, snapshot_datetime(value, "created_datetime")?);
+            }
+
+            for value in snapshot_array(&snapshot, "vertex_properties")? {
+                let id = snapshot_uuid(value, "id")?;
+                let name = snapshot_str(value, "name")?.to_string();
+                let value = snapshot_field(value, "value")?.clone();
+                internal.vertex_properties.insert((id, name), value);
+            }
+
+            for value in snapshot_array(&snapshot, "edge_properties")? {
+                let key = internal.intern_edge_key(&snapshot_edge_key(value)?);
+                let name = snapshot_str(value, "name")?.to_string();
+                let value = snapshot_field(value, "value")?.clone();
+                internal.edge_properties.insert((key, name), value);
+            }
+        }
+
+        Ok(datastore)
+    }
 }
 
 impl Datastore for MemoryDatastore {
     type Trans = MemoryTransaction;
 
+    fn bulk_insert<I>(&self, items: I) -> Result<()>
+    where
+        I: Iterator<Item = models::BulkInsertItem>,
+    {
+        let mut datastore = self.0.write().unwrap();
+
+        for item in items {
+            match item {
+                models::BulkInsertItem::Vertex(vertex) => {
+                    datastore.create_vertex(vertex.id, vertex.t);
+                }
+                models::BulkInsertItem::Edge(edge_key) => {
+                    // Bulk inserts overwrite edges even when deduplicating
+                    datastore.create_edge(&edge_key, false);
+                }
+                models::BulkInsertItem::VertexProperty(id, name, value) => {
+                    if datastore.vertices.contains_key(&id) {
+                        datastore.vertex_properties.insert((id, name), value);
+                    }
+                }
+                models::BulkInsertItem::EdgeProperty(edge_key, name, value) => {
+                    if let Some(edge_key) = datastore.edge_key(&edge_key) {
+                        if datastore.edges.contains_key(&edge_key) {
+                            datastore.edge_properties.insert((edge_key, name), value);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        let mut datastore = self.0.write().unwrap();
+        datastore.edge_properties.clear();
+        datastore.edges.clear();
+        datastore.vertex_properties.clear();
+        datastore.vertices.clear();
+        datastore.vertex_types.clear();
+        datastore.types.remove_unused();
+        Ok(())
+    }
+
     fn transaction(&self) -> Result<Self::Trans> {
         Ok(MemoryTransaction {
             datastore: Arc::clone(&self.0),
         })
     }
+
+    // Sizes are estimated from the keys and values themselves, without the
+    // overhead of the maps holding them
+    fn stats(&self) -> Result<DatastoreStats> {
+        let datastore = self.0.read().unwrap();
+        let id_len = mem::size_of::<Uuid>() as u64;
+        let datetime_len = mem::size_of::<DateTime<Utc>>() as u64;
+
+        let column = |name: &str, approximate_entries: usize, approximate_bytes: u64| ColumnStats {
+            name: name.to_string(),
+            approximate_entries: approximate_entries as u64,
+            approximate_bytes,
+            sst_file_count: None,
+            level_bytes: Vec::new(),
+        };
+
+        let vertex_bytes = datastore.vertices.values().map(|t| id_len + t.0.len() as u64).sum();
+
+        let vertex_type_bytes = datastore
+            .vertex_types
+            .iter()
+            .map(|(t, ids)| t.0.len() as u64 + id_len * ids.len() as u64)
+            .sum();
+
+        let edge_bytes = datastore
+            .edges
+            .keys()
+            .map(|key| 2 * id_len + key.t.0.len() as u64 + datetime_len)
+            .sum();
+
+        let vertex_property_bytes = datastore
+            .vertex_properties
+            .iter()
+            .map(|((_, name), value)| id_len + name.len() as u64 + value.to_string().len() as u64)
+            .sum();
+
+        let edge_property_bytes = datastore
+            .edge_properties
+            .iter()
+            .map(|((key, name), value)| {
+                2 * id_len + key.t.0.len() as u64 + name.len() as u64 + value.to_string().len() as u64
+            })
+            .sum();
+
+        let vertex_type_entries = datastore.vertex_types.values().map(|ids| ids.len()).sum();
+
+        Ok(DatastoreStats {
+            vertex_count: datastore.vertices.len() as u64,
+            edge_count: datastore.edges.len() as u64,
+            columns: vec![
+                column("vertices", datastore.vertices.len(), vertex_bytes),
+                column("vertex_types", vertex_type_entries, vertex_type_bytes),
+                column("edges", datastore.edges.len(), edge_bytes),
+                column(
+                    "vertex_properties",
+                    datastore.vertex_properties.len(),
+                    vertex_property_bytes,
+                ),
+                column("edge_properties", datastore.edge_properties.len(), edge_property_bytes),
+            ],
+        })
+    }
+
+    fn vertices_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Vertex>> + 'a> {
+        Box::new(MemoryVertexIter {
+            datastore: Arc::clone(&self.0),
+            after: None,
+        })
+    }
+
+    fn edges_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Edge>> + 'a> {
+        Box::new(MemoryEdgeIter {
+            datastore: Arc::clone(&self.0),
+            after: None,
+        })
+    }
+}
+
+// Iterates over the vertices of a memory datastore. The lock is only held
+// while looking up each vertex, rather than for the life of the iterator,
+// so that the datastore can be written to in the meantime.
+struct MemoryVertexIter {
+    datastore: Arc<RwLock<InternalMemoryDatastore>>,
+    after: Option<Uuid>,
+}
+
+impl Iterator for MemoryVertexIter {
+    type Item = Result<models::Vertex>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let datastore = self.datastore.read().unwrap();
+
+        let next = match self.after {
+            Some(after) => datastore.vertices.range((Excluded(after), Unbounded)).next(),
+            None => datastore.vertices.iter().next(),
+        };
+
+        let vertex = next.map(|(id, t)| models::Vertex::with_id(*id, (**t).clone()))?;
+        self.after = Some(vertex.id);
+        Some(Ok(vertex))
+    }
+}
+
+// Iterates over the edges of a memory datastore. See `MemoryVertexIter`.
+struct MemoryEdgeIter {
+    datastore: Arc<RwLock<InternalMemoryDatastore>>,
+    after: Option<InternedEdgeKey>,
+}
+
+impl Iterator for MemoryEdgeIter {
+    type Item = Result<models::Edge>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let datastore = self.datastore.read().unwrap();
+
+        let next = match self.after {
+            Some(ref after) => datastore.edges.range((Excluded(after), Unbounded)).next(),
+            None => datastore.edges.iter().next(),
+        };
+
+        let (key, created_datetime) = next?;
+        let edge = models::Edge::new(key.to_key(), *created_datetime);
+        self.after = Some(key.clone());
+        Some(Ok(edge))
+    }
 }
 
 /// A transaction for manipulating in-memory-only datastores.
@@ -272,15 +886,33 @@ pub struct MemoryTransaction {
 
 impl Transaction for MemoryTransaction {
     fn create_vertex(&self, vertex: &models::Vertex) -> Result<bool> {
+        vertex.validate()?;
         let mut datastore = self.datastore.write().unwrap();
-        let mut inserted = false;
+        Ok(datastore.create_vertex(vertex.id, vertex.t.clone()))
+    }
 
-        datastore.vertices.entry(vertex.id).or_insert_with(|| {
-            inserted = true;
-            vertex.t.clone()
-        });
+    fn create_vertex_from_type(&self, t: models::Type) -> Result<Uuid> {
+        t.validate()?;
+        let mut datastore = self.datastore.write().unwrap();
+        let id = datastore.id_generator.generate();
 
-        Ok(inserted)
+        if !datastore.create_vertex(id, t) {
+            Err(Error::UuidTaken)
+        } else {
+            Ok(id)
+        }
+    }
+
+    fn get_or_create_vertex(&self, id: Uuid, t: models::Type) -> Result<(models::Vertex, bool)> {
+        t.validate()?;
+        let mut datastore = self.datastore.write().unwrap();
+
+        if let Some(existing_t) = datastore.vertices.get(&id) {
+            return Ok((models::Vertex::with_id(id, (**existing_t).clone()), false));
+        }
+
+        datastore.create_vertex(id, t.clone());
+        Ok((models::Vertex::with_id(id, t), true))
     }
 
     fn get_vertices<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<Vec<models::Vertex>> {
@@ -302,20 +934,53 @@ impl Transaction for MemoryTransaction {
         Ok(())
     }
 
+    fn delete_vertices_by_type(&self, t: models::Type) -> Result<u64> {
+        let mut datastore = self.datastore.write().unwrap();
+        let deletable_vertices: Vec<Uuid> = match datastore.vertex_types.get(&t) {
+            Some(ids) => ids.iter().cloned().collect(),
+            None => return Ok(0),
+        };
+        let count = deletable_vertices.len() as u64;
+        datastore.delete_vertices(deletable_vertices);
+        Ok(count)
+    }
+
     fn get_vertex_count(&self) -> Result<u64> {
         let datastore = self.datastore.read().unwrap();
         Ok(datastore.vertices.len() as u64)
     }
 
+    fn count_vertices(&self, t: Option<&models::Type>) -> Result<u64> {
+        let datastore = self.datastore.read().unwrap();
+
+        let count = match t {
+            Some(t) => datastore.vertex_types.get(t).map_or(0, HashSet::len),
+            None => datastore.vertices.len(),
+        };
+
+        Ok(count as u64)
+    }
+
+    fn which_vertices_exist(&self, ids: &[Uuid]) -> Result<Vec<bool>> {
+        let datastore = self.datastore.read().unwrap();
+        Ok(ids.iter().map(|id| datastore.vertices.contains_key(id)).collect())
+    }
+
     fn create_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        key.validate()?;
         let mut datastore = self.datastore.write().unwrap();
+        let dedup = datastore.dedup_edges;
+        Ok(datastore.create_edge(key, dedup))
+    }
 
-        if !datastore.vertices.contains_key(&key.outbound_id) || !datastore.vertices.contains_key(&key.inbound_id) {
-            return Ok(false);
+    fn create_edges(&self, keys: &[models::EdgeKey]) -> Result<Vec<bool>> {
+        for key in keys {
+            key.validate()?;
         }
 
-        datastore.edges.insert(key.clone(), Utc::now());
-        Ok(true)
+        let mut datastore = self.datastore.write().unwrap();
+        let dedup = datastore.dedup_edges;
+        Ok(keys.iter().map(|key| datastore.create_edge(key, dedup)).collect())
     }
 
     fn get_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<Vec<models::Edge>> {
@@ -330,6 +995,13 @@ impl Transaction for MemoryTransaction {
         Ok(iter.collect())
     }
 
+    fn has_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        let datastore = self.datastore.read().unwrap();
+        Ok(datastore
+            .edge_key(key)
+            .map_or(false, |key| datastore.edges.contains_key(&key)))
+    }
+
     fn delete_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<()> {
         let mut datastore = self.datastore.write().unwrap();
         let deletable_edges: Vec<models::EdgeKey> = datastore
@@ -346,17 +1018,17 @@ impl Transaction for MemoryTransaction {
 
         if direction == models::EdgeDirection::Outbound {
             let lower_bound = match t {
-                Some(t) => models::EdgeKey::new(id, t.clone(), Uuid::default()),
+                Some(t) => InternedEdgeKey::new(id, Arc::new(t.clone()), Uuid::default()),
                 None => {
-                    let empty_type = models::Type::default();
-                    models::EdgeKey::new(id, empty_type, Uuid::default())
+                    let empty_type = Arc::new(models::Type::default());
+                    InternedEdgeKey::new(id, empty_type, Uuid::default())
                 }
             };
             let range = datastore.edges.range(lower_bound..);
 
             let range = range.take_while(|&(k, _)| {
                 if let Some(t) = t {
-                    k.outbound_id == id && &k.t == t
+                    k.outbound_id == id && *k.t == *t
                 } else {
                     k.outbound_id == id
                 }
@@ -366,7 +1038,7 @@ impl Transaction for MemoryTransaction {
         } else {
             let range = datastore.edges.iter().filter(|&(k, _)| {
                 if let Some(t) = t {
-                    k.inbound_id == id && &k.t == t
+                    k.inbound_id == id && *k.t == *t
                 } else {
                     k.inbound_id == id
                 }
@@ -376,6 +1048,28 @@ impl Transaction for MemoryTransaction {
         }
     }
 
+    fn count_edges(&self, t: Option<&models::Type>) -> Result<u64> {
+        let datastore = self.datastore.read().unwrap();
+
+        let count = match t {
+            Some(t) => datastore.edges.keys().filter(|k| *k.t == *t).count(),
+            None => datastore.edges.len(),
+        };
+
+        Ok(count as u64)
+    }
+
+    fn rename_edge_type(&self, from: models::Type, to: models::Type) -> Result<u64> {
+        to.validate()?;
+
+        if from == to {
+            return Err(ValidationError::InvalidValue.into());
+        }
+
+        let mut datastore = self.datastore.write().unwrap();
+        Ok(datastore.rename_edge_type(&from, &to))
+    }
+
     fn get_vertex_properties(&self, q: VertexPropertyQuery) -> Result<Vec<models::VertexProperty>> {
         let mut result = Vec::new();
         let datastore = self.datastore.read().unwrap();
@@ -419,10 +1113,13 @@ impl Transaction for MemoryTransaction {
     fn get_edge_properties(&self, q: EdgePropertyQuery) -> Result<Vec<models::EdgeProperty>> {
         let mut result = Vec::new();
         let datastore = self.datastore.read().unwrap();
+        let name = q.name;
         let edge_values = datastore.get_edge_values_by_query(q.inner)?;
 
         for (key, _) in edge_values {
-            let property_value = datastore.edge_properties.get(&(key.clone(), q.name.clone()));
+            let property_value = datastore
+                .edge_key(&key)
+                .and_then(|interned_key| datastore.edge_properties.get(&(interned_key, name.clone())));
 
             if let Some(property_value) = property_value {
                 result.push(models::EdgeProperty::new(key, property_value.clone()));
@@ -438,6 +1135,7 @@ impl Transaction for MemoryTransaction {
         let edge_values = datastore.get_edge_values_by_query(q.inner)?;
 
         for (key, _) in edge_values {
+            let key = datastore.intern_edge_key(&key);
             datastore.edge_properties.insert((key, q.name.clone()), value.clone());
         }
 
@@ -450,9 +1148,26 @@ impl Transaction for MemoryTransaction {
         let edge_values = datastore.get_edge_values_by_query(q.inner)?;
 
         for (key, _) in edge_values {
-            datastore.edge_properties.remove(&(key, q.name.clone()));
+            if let Some(key) = datastore.edge_key(&key) {
+                datastore.edge_properties.remove(&(key, q.name.clone()));
+            }
         }
 
         Ok(())
     }
+
+    fn get_edge_property_names(&self, key: &models::EdgeKey) -> Result<Vec<String>> {
+        let datastore = self.datastore.read().unwrap();
+
+        let names = match datastore.edge_key(key) {
+            Some(key) => datastore
+                .get_edge_property_keys(&key)
+                .into_iter()
+                .map(|(_, name)| name)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Ok(names)
+    }
 }
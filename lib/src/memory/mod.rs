@@ -1,7 +1,8 @@
 //! The in-memory-only datastore implementation. This is the simplest and
 //! generally fastest implementation, but it has these drawbacks:
 //!
-//! * Data is not persisted.
+//! * Data is not persisted, unless it is explicitly saved to a snapshot
+//!   file via `MemoryDatastore::save`.
 //! * Transaction changes cannot be rolled back on error.
 //! * Locking is coarse-grained; only one thread can write to the datastore at
 //!   a time. Consequently, this may actually perform worse on highly
@@ -9,7 +10,10 @@
 
 mod datastore;
 
-pub use self::datastore::{MemoryDatastore, MemoryTransaction};
+#[cfg(test)]
+mod tests;
+
+pub use self::datastore::{MemoryConfig, MemoryDatastore, MemoryTransaction};
 
 #[cfg(feature = "bench-suite")]
 full_bench_impl!(MemoryDatastore::default());
@@ -0,0 +1,335 @@
+use super::{MemoryConfig, MemoryDatastore};
+use crate::errors::Error;
+use crate::ids::{IdGenerator, SequentialUuidGenerator};
+use crate::models::{
+    Edge, EdgeKey, EdgeQueryExt, SpecificEdgeQuery, SpecificVertexQuery, Type, Vertex, VertexQueryExt,
+};
+use crate::traits::{Datastore, Transaction};
+use crate::util::generate_temporary_path;
+use regex::Regex;
+use serde_json::Value as JsonValue;
+use std::fs;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[test]
+fn should_share_state_between_clones() {
+    let datastore = MemoryDatastore::default();
+    let cloned = datastore.clone();
+    let t = Type::new("test_vertex_type").unwrap();
+
+    let cloned_trans = cloned.transaction().unwrap();
+    let id = cloned_trans.create_vertex_from_type(t.clone()).unwrap();
+
+    let trans = datastore.transaction().unwrap();
+    let vertices = trans.get_vertices(SpecificVertexQuery::single(id)).unwrap();
+    assert_eq!(vertices.len(), 1);
+    assert_eq!(vertices[0].t, t);
+}
+
+#[test]
+fn should_use_custom_id_generator() {
+    let config = MemoryConfig {
+        id_generator: Some(Arc::new(SequentialUuidGenerator::new())),
+        ..MemoryConfig::default()
+    };
+    let datastore = MemoryDatastore::new_with_options(config);
+    let trans = datastore.transaction().unwrap();
+    let t = Type::new("test_vertex_type").unwrap();
+    let first_id = trans.create_vertex_from_type(t.clone()).unwrap();
+    let second_id = trans.create_vertex_from_type(t).unwrap();
+    assert!(second_id > first_id);
+}
+
+#[test]
+fn should_combine_options() {
+    let config = MemoryConfig {
+        id_generator: Some(Arc::new(SequentialUuidGenerator::new())),
+        dedup_edges: true,
+    };
+    let datastore = MemoryDatastore::new_with_options(config);
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = Type::new("test_vertex_type").unwrap();
+    let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let inbound_id = trans.create_vertex_from_type(vertex_t).unwrap();
+    assert!(inbound_id > outbound_id);
+
+    let key = EdgeKey::new(outbound_id, Type::new("test_edge_type").unwrap(), inbound_id);
+    assert!(trans.create_edge(&key).unwrap());
+    assert!(!trans.create_edge(&key).unwrap());
+}
+
+#[test]
+fn should_dedup_edges() {
+    let config = MemoryConfig {
+        dedup_edges: true,
+        ..MemoryConfig::default()
+    };
+    let datastore = MemoryDatastore::new_with_options(config);
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = Type::new("test_vertex_type").unwrap();
+    let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let inbound_id = trans.create_vertex_from_type(vertex_t).unwrap();
+    let key = EdgeKey::new(outbound_id, Type::new("test_edge_type").unwrap(), inbound_id);
+
+    assert!(trans.create_edge(&key).unwrap());
+    let original = trans.get_edges(SpecificEdgeQuery::single(key.clone())).unwrap();
+
+    assert!(!trans.create_edge(&key).unwrap());
+    let results = trans.create_edges(&[key.clone(), key.clone()]).unwrap();
+    assert_eq!(results, vec![false, false]);
+
+    let edges = trans.get_edges(SpecificEdgeQuery::single(key)).unwrap();
+    assert_eq!(edges.len(), 1);
+    assert!(edges[0].eq_exact(&original[0]));
+}
+
+#[test]
+fn should_save_and_load() {
+    let path = generate_temporary_path();
+    let datastore = MemoryDatastore::default();
+    let vertex_t = Type::new("test_vertex_type").unwrap();
+    let edge_t = Type::new("test_edge_type").unwrap();
+
+    let (outbound_id, inbound_id) = {
+        let trans = datastore.transaction().unwrap();
+        let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        let inbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        let key = EdgeKey::new(outbound_id, edge_t.clone(), inbound_id);
+        trans.create_edge(&key).unwrap();
+        let q = SpecificVertexQuery::single(outbound_id).property("foo");
+        trans.set_vertex_properties(q, &JsonValue::Bool(true)).unwrap();
+        let q = SpecificEdgeQuery::single(key).property("bar");
+        trans.set_edge_properties(q, &JsonValue::from(42)).unwrap();
+        (outbound_id, inbound_id)
+    };
+
+    datastore.save(&path).unwrap();
+    let loaded = MemoryDatastore::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let trans = datastore.transaction().unwrap();
+    let loaded_trans = loaded.transaction().unwrap();
+    assert_eq!(loaded_trans.get_vertex_count().unwrap(), 3);
+    assert_eq!(loaded_trans.count_edges(None).unwrap(), 1);
+
+    let key = EdgeKey::new(outbound_id, edge_t, inbound_id);
+    let edges = trans.get_edges(SpecificEdgeQuery::single(key.clone())).unwrap();
+    let loaded_edges = loaded_trans.get_edges(SpecificEdgeQuery::single(key.clone())).unwrap();
+    assert_eq!(loaded_edges.len(), 1);
+    assert_eq!(loaded_edges[0].key, key);
+    assert_eq!(loaded_edges[0].created_datetime, edges[0].created_datetime);
+
+    let vertices = loaded_trans
+        .get_vertices(SpecificVertexQuery::single(inbound_id))
+        .unwrap();
+    assert_eq!(vertices.len(), 1);
+    assert_eq!(vertices[0].t, vertex_t);
+
+    let q = SpecificVertexQuery::single(outbound_id).property("foo");
+    let vertex_properties = loaded_trans.get_vertex_properties(q).unwrap();
+    assert_eq!(vertex_properties.len(), 1);
+    assert_eq!(vertex_properties[0].value, JsonValue::Bool(true));
+
+    let q = SpecificEdgeQuery::single(key).property("bar");
+    let edge_properties = loaded_trans.get_edge_properties(q).unwrap();
+    assert_eq!(edge_properties.len(), 1);
+    assert_eq!(edge_properties[0].value, JsonValue::from(42));
+}
+
+#[test]
+fn should_save_and_load_types_with_custom_patterns() {
+    let path = generate_temporary_path();
+    let datastore = MemoryDatastore::default();
+    let pattern = Regex::new("^[a-z.]+$").unwrap();
+    let t = Type::new_with_pattern("test.dotted.type", &pattern).unwrap();
+
+    let id = {
+        let trans = datastore.transaction().unwrap();
+        let id = trans.create_vertex_from_type(t.clone()).unwrap();
+        trans.create_edge(&EdgeKey::new(id, t.clone(), id)).unwrap();
+        id
+    };
+
+    datastore.save(&path).unwrap();
+    let loaded = MemoryDatastore::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let trans = loaded.transaction().unwrap();
+    let vertices = trans.get_vertices(SpecificVertexQuery::single(id)).unwrap();
+    assert_eq!(vertices.len(), 1);
+    assert_eq!(vertices[0].t, t);
+    assert!(trans.has_edge(&EdgeKey::new(id, t.clone(), id)).unwrap());
+}
+
+#[test]
+fn should_page_through_edges_with_the_same_datetime() {
+    let path = generate_temporary_path();
+    let id_generator = SequentialUuidGenerator::new();
+    let outbound_id = id_generator.generate();
+    let inbound_ids: Vec<Uuid> = (0..6).map(|_| id_generator.generate()).collect();
+
+    // Edges created at the same time can only be set up through a snapshot
+    let vertices: Vec<JsonValue> = inbound_ids
+        .iter()
+        .chain(Some(&outbound_id))
+        .map(|id| serde_json::json!({ "id": id.to_string(), "t": "test_vertex_type" }))
+        .collect();
+    let edges: Vec<JsonValue> = inbound_ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let created_datetime = if i < 5 {
+                "2019-01-01T00:00:00Z"
+            } else {
+                "2019-01-02T00:00:00Z"
+            };
+
+            serde_json::json!({
+                "outbound_id": outbound_id.to_string(),
+                "t": "test_edge_type",
+                "inbound_id": id.to_string(),
+                "created_datetime": created_datetime,
+            })
+        })
+        .collect();
+    let snapshot = serde_json::json!({
+        "vertices": vertices,
+        "edges": edges,
+        "vertex_properties": [],
+        "edge_properties": [],
+    });
+    fs::write(&path, snapshot.to_string()).unwrap();
+    let datastore = MemoryDatastore::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let trans = datastore.transaction().unwrap();
+    let all = trans.edges_by_time(None, 10).unwrap();
+    assert_eq!(all.len(), 6);
+
+    // Pages end partway through the edges with the same datetime
+    let mut paged = Vec::new();
+    let mut after = None;
+
+    loop {
+        let page = trans.edges_by_time(after, 2).unwrap();
+
+        match page.last() {
+            Some(last) => after = Some((last.created_datetime, last.key.clone())),
+            None => break,
+        }
+
+        paged.extend(page);
+    }
+
+    let keys = |edges: Vec<Edge>| edges.into_iter().map(|edge| edge.key).collect::<Vec<_>>();
+    assert_eq!(keys(paged), keys(all));
+}
+
+#[test]
+fn should_not_load_an_invalid_snapshot() {
+    let path = generate_temporary_path();
+    fs::write(&path, "{\"vertices\": [{\"id\": \"foo\", \"t\": \"bar\"}]}").unwrap();
+    let result = MemoryDatastore::load(&path);
+    fs::remove_file(&path).unwrap();
+
+    match result {
+        Err(Error::InvalidSnapshot) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn should_not_create_items_with_forged_types() {
+    let datastore = MemoryDatastore::default();
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = Type::new("test_vertex_type").unwrap();
+    let forged_t = Type("a".repeat(256));
+
+    match trans.create_vertex(&Vertex::new(forged_t.clone())) {
+        Err(Error::Validation { .. }) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let inbound_id = trans.create_vertex_from_type(vertex_t).unwrap();
+    let key = EdgeKey::new(outbound_id, forged_t, inbound_id);
+
+    match trans.create_edge(&key) {
+        Err(Error::Validation { .. }) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    match trans.create_edges(&[key]) {
+        Err(Error::Validation { .. }) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    assert_eq!(trans.get_vertex_count().unwrap(), 2);
+    assert_eq!(trans.count_edges(None).unwrap(), 0);
+}
+
+#[test]
+fn should_not_rename_edge_type_to_itself() {
+    let datastore = MemoryDatastore::default();
+    let trans = datastore.transaction().unwrap();
+    let t = Type::new("test_edge_type").unwrap();
+
+    match trans.rename_edge_type(t.clone(), t) {
+        Err(Error::Validation { .. }) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn should_get_edge_property_names() {
+    let datastore = MemoryDatastore::default();
+    let trans = datastore.transaction().unwrap();
+    let t = Type::new("test_type").unwrap();
+    let outbound_id = trans.create_vertex_from_type(t.clone()).unwrap();
+    let inbound_id = trans.create_vertex_from_type(t.clone()).unwrap();
+    let key = EdgeKey::new(outbound_id, t, inbound_id);
+    assert_eq!(trans.get_edge_property_names(&key).unwrap(), Vec::<String>::new());
+
+    trans.create_edge(&key).unwrap();
+
+    for name in &["foo", "bar"] {
+        let q = SpecificEdgeQuery::single(key.clone()).property(*name);
+        trans.set_edge_properties(q, &JsonValue::Bool(true)).unwrap();
+    }
+
+    assert_eq!(trans.get_edge_property_names(&key).unwrap(), vec!["bar", "foo"]);
+}
+
+#[test]
+fn should_intern_types() {
+    let datastore = MemoryDatastore::default();
+    let trans = datastore.transaction().unwrap();
+    let t = Type::new("test_type").unwrap();
+    let ids: Vec<_> = (0..10)
+        .map(|_| trans.create_vertex_from_type(t.clone()).unwrap())
+        .collect();
+
+    let keys: Vec<_> = ids
+        .iter()
+        .flat_map(|outbound_id| ids.iter().map(move |inbound_id| (*outbound_id, *inbound_id)))
+        .map(|(outbound_id, inbound_id)| EdgeKey::new(outbound_id, t.clone(), inbound_id))
+        .collect();
+    trans.create_edges(&keys).unwrap();
+    assert_eq!(trans.count_edges(Some(&t)).unwrap(), 100);
+    assert_eq!(datastore.interned_type_count(), 1);
+
+    // Types are forgotten once nothing has them
+    let renamed_t = Type::new("renamed_test_type").unwrap();
+    trans.rename_edge_type(t.clone(), renamed_t.clone()).unwrap();
+    assert_eq!(datastore.interned_type_count(), 2);
+    let renamed_keys = keys
+        .into_iter()
+        .map(|key| EdgeKey::new(key.outbound_id, renamed_t.clone(), key.inbound_id))
+        .collect();
+    trans.delete_edges(SpecificEdgeQuery::new(renamed_keys)).unwrap();
+    assert_eq!(datastore.interned_type_count(), 1);
+    datastore.clear().unwrap();
+    assert_eq!(datastore.interned_type_count(), 0);
+}
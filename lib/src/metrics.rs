@@ -0,0 +1,369 @@
+//! Hooks for recording metrics on datastore operations.
+//!
+//! Wrap any datastore in a `MetricsDatastore` to have each operation
+//! reported to a `MetricsSink`, along with how long it took.
+
+use chrono::offset::Utc;
+use chrono::DateTime;
+use crate::errors::Result;
+use crate::models;
+use crate::stats::DatastoreStats;
+use crate::traits::{Datastore, Transaction};
+use serde_json::value::Value as JsonValue;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// The kinds of operations reported to a `MetricsSink`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OpKind {
+    BulkInsert,
+    VertexCreate,
+    VertexQuery,
+    VertexDelete,
+    EdgeCreate,
+    EdgeQuery,
+    EdgeDelete,
+    EdgeUpdate,
+    Count,
+    PropertyQuery,
+    PropertySet,
+    PropertyDelete,
+}
+
+const OP_KIND_COUNT: usize = 12;
+
+/// Receives metrics on datastore operations.
+pub trait MetricsSink {
+    /// Records that an operation was run.
+    ///
+    /// # Arguments
+    /// * `op` - The kind of operation.
+    /// * `dur` - How long the operation took.
+    fn record_op(&self, op: OpKind, dur: Duration);
+
+    /// Records that an operation returned an error. This is called in
+    /// addition to `record_op`.
+    ///
+    /// # Arguments
+    /// * `op` - The kind of operation.
+    fn record_error(&self, _op: OpKind) {}
+}
+
+/// A metrics sink that discards everything.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopMetricsSink;
+
+impl MetricsSink for NoopMetricsSink {
+    fn record_op(&self, _op: OpKind, _dur: Duration) {}
+}
+
+/// A metrics sink that keeps a running count of operations and errors.
+#[derive(Debug, Default)]
+pub struct CountingMetricsSink {
+    ops: [AtomicUsize; OP_KIND_COUNT],
+    errors: [AtomicUsize; OP_KIND_COUNT],
+}
+
+impl CountingMetricsSink {
+    /// Gets the number of times an operation was run.
+    ///
+    /// # Arguments
+    /// * `op` - The kind of operation.
+    pub fn op_count(&self, op: OpKind) -> usize {
+        self.ops[op as usize].load(Ordering::SeqCst)
+    }
+
+    /// Gets the number of times an operation returned an error.
+    ///
+    /// # Arguments
+    /// * `op` - The kind of operation.
+    pub fn error_count(&self, op: OpKind) -> usize {
+        self.errors[op as usize].load(Ordering::SeqCst)
+    }
+}
+
+impl MetricsSink for CountingMetricsSink {
+    fn record_op(&self, op: OpKind, _dur: Duration) {
+        self.ops[op as usize].fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn record_error(&self, op: OpKind) {
+        self.errors[op as usize].fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// A datastore that reports metrics on all of the operations run against
+/// an underlying datastore.
+#[derive(Debug)]
+pub struct MetricsDatastore<D: Datastore, M: MetricsSink> {
+    datastore: D,
+    sink: Arc<M>,
+}
+
+impl<D: Datastore> MetricsDatastore<D, NoopMetricsSink> {
+    /// Wraps a datastore without recording any metrics.
+    ///
+    /// # Arguments
+    /// * `datastore` - The datastore to wrap.
+    pub fn new(datastore: D) -> Self {
+        Self::with_sink(datastore, NoopMetricsSink)
+    }
+}
+
+impl<D: Datastore, M: MetricsSink> MetricsDatastore<D, M> {
+    /// Wraps a datastore, recording metrics to a sink.
+    ///
+    /// # Arguments
+    /// * `datastore` - The datastore to wrap.
+    /// * `sink` - Where metrics are reported.
+    pub fn with_sink(datastore: D, sink: M) -> Self {
+        Self {
+            datastore,
+            sink: Arc::new(sink),
+        }
+    }
+
+    /// Gets the sink that metrics are reported to.
+    pub fn sink(&self) -> &M {
+        &self.sink
+    }
+
+    fn record<R, F: FnOnce() -> Result<R>>(&self, op: OpKind, f: F) -> Result<R> {
+        let start = Instant::now();
+        let result = f();
+        self.sink.record_op(op, start.elapsed());
+
+        if result.is_err() {
+            self.sink.record_error(op);
+        }
+
+        result
+    }
+}
+
+impl<D: Datastore, M: MetricsSink> Datastore for MetricsDatastore<D, M> {
+    type Trans = MetricsTransaction<D::Trans, M>;
+
+    fn transaction(&self) -> Result<Self::Trans> {
+        Ok(MetricsTransaction {
+            trans: self.datastore.transaction()?,
+            sink: Arc::clone(&self.sink),
+        })
+    }
+
+    fn bulk_insert<I>(&self, items: I) -> Result<()>
+    where
+        I: Iterator<Item = models::BulkInsertItem>,
+    {
+        self.record(OpKind::BulkInsert, || self.datastore.bulk_insert(items))
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.datastore.clear()
+    }
+
+    fn compact(&self) -> Result<()> {
+        self.datastore.compact()
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.datastore.flush()
+    }
+
+    fn stats(&self) -> Result<DatastoreStats> {
+        self.datastore.stats()
+    }
+
+    // Full scans aren't recorded, since their work happens lazily as the
+    // iterators are consumed
+    fn vertices_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Vertex>> + 'a> {
+        self.datastore.vertices_iter()
+    }
+
+    fn edges_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Edge>> + 'a> {
+        self.datastore.edges_iter()
+    }
+}
+
+/// A transaction that reports metrics to the sink of the datastore it was
+/// created from.
+#[derive(Debug)]
+pub struct MetricsTransaction<T: Transaction, M: MetricsSink> {
+    trans: T,
+    sink: Arc<M>,
+}
+
+impl<T: Transaction, M: MetricsSink> MetricsTransaction<T, M> {
+    fn record<R, F: FnOnce() -> Result<R>>(&self, op: OpKind, f: F) -> Result<R> {
+        let start = Instant::now();
+        let result = f();
+        self.sink.record_op(op, start.elapsed());
+
+        if result.is_err() {
+            self.sink.record_error(op);
+        }
+
+        result
+    }
+}
+
+impl<T: Transaction, M: MetricsSink> Transaction for MetricsTransaction<T, M> {
+    fn create_vertex(&self, vertex: &models::Vertex) -> Result<bool> {
+        self.record(OpKind::VertexCreate, || self.trans.create_vertex(vertex))
+    }
+
+    fn create_vertex_from_type(&self, t: models::Type) -> Result<Uuid> {
+        self.record(OpKind::VertexCreate, || self.trans.create_vertex_from_type(t))
+    }
+
+    fn get_or_create_vertex(&self, id: Uuid, t: models::Type) -> Result<(models::Vertex, bool)> {
+        self.record(OpKind::VertexCreate, || self.trans.get_or_create_vertex(id, t))
+    }
+
+    fn get_vertices<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<Vec<models::Vertex>> {
+        self.record(OpKind::VertexQuery, || self.trans.get_vertices(q))
+    }
+
+    fn delete_vertices<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<()> {
+        self.record(OpKind::VertexDelete, || self.trans.delete_vertices(q))
+    }
+
+    fn delete_vertices_by_type(&self, t: models::Type) -> Result<u64> {
+        self.record(OpKind::VertexDelete, || self.trans.delete_vertices_by_type(t))
+    }
+
+    fn get_vertex_count(&self) -> Result<u64> {
+        self.record(OpKind::Count, || self.trans.get_vertex_count())
+    }
+
+    fn count_vertices(&self, t: Option<&models::Type>) -> Result<u64> {
+        self.record(OpKind::Count, || self.trans.count_vertices(t))
+    }
+
+    fn which_vertices_exist(&self, ids: &[Uuid]) -> Result<Vec<bool>> {
+        self.record(OpKind::VertexQuery, || self.trans.which_vertices_exist(ids))
+    }
+
+    fn create_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        self.record(OpKind::EdgeCreate, || self.trans.create_edge(key))
+    }
+
+    fn create_edges(&self, keys: &[models::EdgeKey]) -> Result<Vec<bool>> {
+        self.record(OpKind::EdgeCreate, || self.trans.create_edges(keys))
+    }
+
+    fn get_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<Vec<models::Edge>> {
+        self.record(OpKind::EdgeQuery, || self.trans.get_edges(q))
+    }
+
+    fn has_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        self.record(OpKind::EdgeQuery, || self.trans.has_edge(key))
+    }
+
+    fn delete_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<()> {
+        self.record(OpKind::EdgeDelete, || self.trans.delete_edges(q))
+    }
+
+    fn get_edge_count(&self, id: Uuid, t: Option<&models::Type>, direction: models::EdgeDirection) -> Result<u64> {
+        self.record(OpKind::Count, || self.trans.get_edge_count(id, t, direction))
+    }
+
+    fn count_edges(&self, t: Option<&models::Type>) -> Result<u64> {
+        self.record(OpKind::Count, || self.trans.count_edges(t))
+    }
+
+    fn edges_by_time(
+        &self,
+        after: Option<(DateTime<Utc>, models::EdgeKey)>,
+        limit: usize,
+    ) -> Result<Vec<models::Edge>> {
+        self.record(OpKind::EdgeQuery, || self.trans.edges_by_time(after, limit))
+    }
+
+    fn rename_edge_type(&self, from: models::Type, to: models::Type) -> Result<u64> {
+        self.record(OpKind::EdgeUpdate, || self.trans.rename_edge_type(from, to))
+    }
+
+    fn get_vertex_properties(&self, q: models::VertexPropertyQuery) -> Result<Vec<models::VertexProperty>> {
+        self.record(OpKind::PropertyQuery, || self.trans.get_vertex_properties(q))
+    }
+
+    fn set_vertex_properties(&self, q: models::VertexPropertyQuery, value: &JsonValue) -> Result<()> {
+        self.record(OpKind::PropertySet, || self.trans.set_vertex_properties(q, value))
+    }
+
+    fn delete_vertex_properties(&self, q: models::VertexPropertyQuery) -> Result<()> {
+        self.record(OpKind::PropertyDelete, || self.trans.delete_vertex_properties(q))
+    }
+
+    fn get_edge_properties(&self, q: models::EdgePropertyQuery) -> Result<Vec<models::EdgeProperty>> {
+        self.record(OpKind::PropertyQuery, || self.trans.get_edge_properties(q))
+    }
+
+    fn set_edge_properties(&self, q: models::EdgePropertyQuery, value: &JsonValue) -> Result<()> {
+        self.record(OpKind::PropertySet, || self.trans.set_edge_properties(q, value))
+    }
+
+    fn delete_edge_properties(&self, q: models::EdgePropertyQuery) -> Result<()> {
+        self.record(OpKind::PropertyDelete, || self.trans.delete_edge_properties(q))
+    }
+
+    fn get_edge_property_names(&self, key: &models::EdgeKey) -> Result<Vec<String>> {
+        self.record(OpKind::PropertyQuery, || self.trans.get_edge_property_names(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CountingMetricsSink, MetricsDatastore, OpKind};
+    use crate::memory::MemoryDatastore;
+    use crate::models::{SpecificVertexQuery, Type};
+    use crate::traits::{Datastore, Transaction};
+
+    #[test]
+    fn should_count_ops() {
+        let datastore = MetricsDatastore::with_sink(MemoryDatastore::default(), CountingMetricsSink::default());
+        let trans = datastore.transaction().unwrap();
+        let t = Type::new("test_vertex_type").unwrap();
+
+        for _ in 0..5 {
+            trans.create_vertex_from_type(t.clone()).unwrap();
+        }
+
+        let sink = datastore.sink();
+        assert_eq!(sink.op_count(OpKind::VertexCreate), 5);
+        assert_eq!(sink.error_count(OpKind::VertexCreate), 0);
+        assert_eq!(sink.op_count(OpKind::EdgeCreate), 0);
+
+        let id = trans.create_vertex_from_type(t).unwrap();
+        trans.get_vertices(SpecificVertexQuery::single(id)).unwrap();
+        assert_eq!(sink.op_count(OpKind::VertexCreate), 6);
+        assert_eq!(sink.op_count(OpKind::VertexQuery), 1);
+    }
+
+    #[test]
+    fn should_clear_the_underlying_datastore() {
+        let datastore = MetricsDatastore::with_sink(MemoryDatastore::default(), CountingMetricsSink::default());
+        let trans = datastore.transaction().unwrap();
+        trans
+            .create_vertex_from_type(Type::new("test_vertex_type").unwrap())
+            .unwrap();
+
+        // The underlying datastore's own clear is used, rather than deleting
+        // through the wrapper's transactions
+        datastore.clear().unwrap();
+        assert_eq!(trans.get_vertex_count().unwrap(), 0);
+        assert_eq!(datastore.sink().op_count(OpKind::VertexDelete), 0);
+    }
+
+    #[test]
+    fn should_run_with_the_default_sink() {
+        let datastore = MetricsDatastore::new(MemoryDatastore::default());
+        let trans = datastore.transaction().unwrap();
+        let t = Type::new("test_vertex_type").unwrap();
+        trans.create_vertex_from_type(t).unwrap();
+        assert_eq!(trans.get_vertex_count().unwrap(), 1);
+    }
+}
@@ -1,6 +1,9 @@
 use super::types::Type;
 use chrono::offset::Utc;
 use chrono::DateTime;
+use crate::errors::{ValidationError, ValidationResult};
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use uuid::Uuid;
 
 /// Represents a uniquely identifiable key to an edge.
@@ -31,6 +34,78 @@ impl EdgeKey {
             inbound_id,
         }
     }
+
+    /// Re-checks the edge's type. See `Type::validate`.
+    pub fn validate(&self) -> ValidationResult<()> {
+        self.t.validate()
+    }
+}
+
+impl fmt::Display for EdgeKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -[{}]-> {}", self.outbound_id, self.t, self.inbound_id)
+    }
+}
+
+/// The name of the edge property that edge labels are stored in.
+pub const EDGE_LABEL_PROPERTY: &str = "indradb-edge-label";
+
+/// The maximum length of an edge label built with `EdgeLabel::new`, in
+/// bytes.
+pub const DEFAULT_MAX_EDGE_LABEL_LENGTH: usize = 255;
+
+/// A free-form, human-readable label for an edge, e.g. "liked on mobile".
+/// Unlike the edge's type, it isn't part of the edge's key. See
+/// `Transaction::set_edge_label`.
+///
+/// Labels can only be constructed through `EdgeLabel::new` or
+/// `EdgeLabel::new_with_max_length`, so every label has had its length
+/// checked.
+#[derive(Eq, PartialEq, Clone, Debug, Hash)]
+pub struct EdgeLabel(pub(crate) String);
+
+impl EdgeLabel {
+    /// Constructs a new label.
+    ///
+    /// # Arguments
+    /// * `s` - The label, which must be at most
+    ///   `DEFAULT_MAX_EDGE_LABEL_LENGTH` bytes long.
+    ///
+    /// # Errors
+    /// Returns `ValidationError::ValueTooLong` if the label is too long.
+    pub fn new<S: Into<String>>(s: S) -> ValidationResult<Self> {
+        Self::new_with_max_length(s, DEFAULT_MAX_EDGE_LABEL_LENGTH)
+    }
+
+    /// Constructs a new label, with a caller-supplied maximum length.
+    ///
+    /// # Arguments
+    /// * `s` - The label.
+    /// * `max_length` - The most bytes the label can have.
+    ///
+    /// # Errors
+    /// Returns `ValidationError::ValueTooLong` if the label is longer than
+    /// `max_length`.
+    pub fn new_with_max_length<S: Into<String>>(s: S, max_length: usize) -> ValidationResult<Self> {
+        let s = s.into();
+
+        if s.len() > max_length {
+            Err(ValidationError::ValueTooLong)
+        } else {
+            Ok(EdgeLabel(s))
+        }
+    }
+
+    /// Gets a reference to the underlying string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for EdgeLabel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
 }
 
 /// An edge.
@@ -64,13 +139,67 @@ impl Edge {
     pub fn new(key: EdgeKey, created_datetime: DateTime<Utc>) -> Edge {
         Edge { key, created_datetime }
     }
+
+    /// Returns a copy of the edge going the other direction, i.e. with the
+    /// outbound and inbound vertices swapped. The type and datetime are
+    /// kept as-is.
+    pub fn reversed(&self) -> Edge {
+        let key = EdgeKey::new(self.key.inbound_id, self.key.t.clone(), self.key.outbound_id);
+        Edge::new(key, self.created_datetime)
+    }
+
+    /// Checks whether two edges are fully equal, including their datetimes.
+    /// As opposed to `==`, which only compares keys, i.e. whether the edges
+    /// are the same edge.
+    ///
+    /// # Arguments
+    /// * `other` - The edge to compare against.
+    pub fn eq_exact(&self, other: &Edge) -> bool {
+        self.key == other.key && self.created_datetime == other.created_datetime
+    }
+
+    /// Checks whether two edges have the same type.
+    ///
+    /// # Arguments
+    /// * `other` - The edge to compare against.
+    pub fn same_type(&self, other: &Edge) -> bool {
+        self.key.t == other.key.t
+    }
+
+    /// Re-checks the edge's key. See `EdgeKey::validate`.
+    pub fn validate(&self) -> ValidationResult<()> {
+        self.key.validate()
+    }
+}
+
+impl PartialEq for Edge {
+    fn eq(&self, other: &Edge) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for Edge {}
+
+impl Hash for Edge {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+impl fmt::Display for Edge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.key.fmt(f)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Edge, EdgeKey};
-    use chrono::Utc;
+    use super::{Edge, EdgeKey, EdgeLabel, DEFAULT_MAX_EDGE_LABEL_LENGTH};
+    use chrono::{Duration, Utc};
+    use crate::errors::ValidationError;
     use crate::models::Type;
+    use std::collections::HashSet;
+    use std::str::FromStr;
     use uuid::Uuid;
 
     #[test]
@@ -84,4 +213,85 @@ mod tests {
         assert!(edge.created_datetime >= start_datetime);
         assert!(edge.created_datetime <= end_datetime);
     }
+
+    #[test]
+    fn should_display_edge() {
+        let outbound_id = Uuid::from_str("16151dea-a538-4bf1-9559-851e256cf139").unwrap();
+        let inbound_id = Uuid::from_str("2cb0e5a6-7ad6-4a25-9ca6-2b1a2ad2f1c8").unwrap();
+        let edge = Edge::new_with_current_datetime(EdgeKey::new(outbound_id, Type::new("liked").unwrap(), inbound_id));
+        assert_eq!(
+            edge.to_string(),
+            "16151dea-a538-4bf1-9559-851e256cf139 -[liked]-> 2cb0e5a6-7ad6-4a25-9ca6-2b1a2ad2f1c8"
+        );
+    }
+
+    #[test]
+    fn should_reverse_edge() {
+        let outbound_id = Uuid::from_str("16151dea-a538-4bf1-9559-851e256cf139").unwrap();
+        let inbound_id = Uuid::from_str("2cb0e5a6-7ad6-4a25-9ca6-2b1a2ad2f1c8").unwrap();
+        let t = Type::new("liked").unwrap();
+        let edge = Edge::new(EdgeKey::new(outbound_id, t.clone(), inbound_id), Utc::now());
+
+        let reversed = edge.reversed();
+        assert_eq!(reversed.key, EdgeKey::new(inbound_id, t, outbound_id));
+        assert_eq!(reversed.created_datetime, edge.created_datetime);
+        assert_eq!(reversed.reversed(), edge);
+    }
+
+    #[test]
+    fn should_compare_edges_exactly() {
+        let key = EdgeKey::new(Uuid::default(), Type::new("liked").unwrap(), Uuid::default());
+        let first = Edge::new(key.clone(), Utc::now());
+        let second = Edge::new(key, first.created_datetime - Duration::days(1));
+
+        assert_eq!(first, second);
+        assert!(!first.eq_exact(&second));
+        assert!(first.eq_exact(&first.clone()));
+    }
+
+    #[test]
+    fn should_compare_edge_types() {
+        let other_id = Uuid::from_str("16151dea-a538-4bf1-9559-851e256cf139").unwrap();
+        let liked = Type::new("liked").unwrap();
+        let first = Edge::new_with_current_datetime(EdgeKey::new(Uuid::default(), liked.clone(), Uuid::default()));
+        let second = Edge::new_with_current_datetime(EdgeKey::new(other_id, liked, Uuid::default()));
+        let reviewed = Edge::new_with_current_datetime(EdgeKey::new(
+            Uuid::default(),
+            Type::new("reviewed").unwrap(),
+            Uuid::default(),
+        ));
+
+        assert!(first.same_type(&second));
+        assert!(!first.same_type(&reviewed));
+    }
+
+    #[test]
+    fn should_limit_edge_label_length() {
+        assert_eq!(EdgeLabel::new("liked on mobile").unwrap().as_str(), "liked on mobile");
+        assert!(EdgeLabel::new("a".repeat(DEFAULT_MAX_EDGE_LABEL_LENGTH)).is_ok());
+
+        match EdgeLabel::new("a".repeat(DEFAULT_MAX_EDGE_LABEL_LENGTH + 1)) {
+            Err(ValidationError::ValueTooLong) => (),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        assert!(EdgeLabel::new_with_max_length("liked", 5).is_ok());
+
+        match EdgeLabel::new_with_max_length("liked on mobile", 5) {
+            Err(ValidationError::ValueTooLong) => (),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_hash_edges_by_key() {
+        let key = EdgeKey::new(Uuid::default(), Type::new("liked").unwrap(), Uuid::default());
+        let first = Edge::new(key.clone(), Utc::now());
+        let second = Edge::new(key, Utc::now() - Duration::days(1));
+
+        let mut edges = HashSet::new();
+        edges.insert(first);
+        edges.insert(second);
+        assert_eq!(edges.len(), 1);
+    }
 }
@@ -6,7 +6,7 @@ mod types;
 mod vertices;
 
 pub use self::bulk_insert::BulkInsertItem;
-pub use self::edges::{Edge, EdgeKey};
+pub use self::edges::{Edge, EdgeKey, EdgeLabel, DEFAULT_MAX_EDGE_LABEL_LENGTH, EDGE_LABEL_PROPERTY};
 pub use self::properties::{EdgeProperty, VertexProperty};
 pub use self::queries::*;
 pub use self::types::Type;
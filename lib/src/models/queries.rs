@@ -3,6 +3,8 @@ use super::types::Type;
 use chrono::offset::Utc;
 use chrono::DateTime;
 use crate::errors;
+use std::cmp::Ordering;
+use std::ops::Range;
 use std::str::FromStr;
 use uuid::Uuid;
 
@@ -41,6 +43,40 @@ impl From<EdgeDirection> for String {
     }
 }
 
+/// Specifies how the results of a query should be sorted.
+///
+/// Sorting happens before the query's limit is applied, so a sorted query
+/// has to find every matching result before it can return any of them.
+#[derive(Eq, PartialEq, Clone, Debug, Hash, Copy)]
+pub enum SortKey {
+    /// Sorts by update datetime. Ties are broken by key.
+    UpdatedAt { ascending: bool },
+
+    /// Sorts by key, i.e. by outbound ID, then type, then inbound ID.
+    Id { ascending: bool },
+}
+
+impl SortKey {
+    /// Whether results are sorted from lowest to highest.
+    pub fn ascending(&self) -> bool {
+        match *self {
+            SortKey::UpdatedAt { ascending } | SortKey::Id { ascending } => ascending,
+        }
+    }
+
+    /// Orients an ascending comparison between two results.
+    ///
+    /// # Arguments
+    /// * `ordering` - How the results compare in ascending order.
+    pub fn order(&self, ordering: Ordering) -> Ordering {
+        if self.ascending() {
+            ordering
+        } else {
+            ordering.reverse()
+        }
+    }
+}
+
 /// A query for vertices.
 ///
 /// Generally you shouldn't need to instantiate a `VertexQuery` directly, but
@@ -109,6 +145,14 @@ pub struct RangeVertexQuery {
 
     /// Sets the lowest vertex ID to return.
     pub start_id: Option<Uuid>,
+
+    /// Filters the vertices returned to those whose type starts with this
+    /// prefix.
+    pub type_prefix: Option<String>,
+
+    /// Filters the vertices returned to those whose ID is in this range.
+    /// The range includes its start, but not its end.
+    pub id_range: Option<Range<Uuid>>,
 }
 
 impl VertexQueryExt for RangeVertexQuery {}
@@ -123,6 +167,8 @@ impl RangeVertexQuery {
             limit,
             t: None,
             start_id: None,
+            type_prefix: None,
+            id_range: None,
         }
     }
 
@@ -135,6 +181,8 @@ impl RangeVertexQuery {
             limit: self.limit,
             t: Some(t),
             start_id: self.start_id,
+            type_prefix: self.type_prefix,
+            id_range: self.id_range,
         }
     }
 
@@ -147,6 +195,40 @@ impl RangeVertexQuery {
             limit: self.limit,
             t: self.t,
             start_id: Some(start_id),
+            type_prefix: self.type_prefix,
+            id_range: self.id_range,
+        }
+    }
+
+    /// Filter the vertices returned to those whose type starts with a
+    /// prefix. The prefix doesn't need to be a valid type itself.
+    ///
+    /// # Arguments
+    /// * `prefix` - Sets the type prefix filter.
+    pub fn with_type_prefix(self, prefix: &str) -> Self {
+        Self {
+            limit: self.limit,
+            t: self.t,
+            start_id: self.start_id,
+            type_prefix: Some(prefix.to_string()),
+            id_range: self.id_range,
+        }
+    }
+
+    /// Filter the vertices returned to those whose ID is in the range
+    /// `[start, end)`, by byte order. This splits the ID space into
+    /// disjoint slices, e.g. so that several workers can each scan one.
+    ///
+    /// # Arguments
+    /// * `start` - The lowest vertex ID to return.
+    /// * `end` - The vertex ID to stop before.
+    pub fn with_id_range(self, start: Uuid, end: Uuid) -> Self {
+        Self {
+            limit: self.limit,
+            t: self.t,
+            start_id: self.start_id,
+            type_prefix: self.type_prefix,
+            id_range: Some(start..end),
         }
     }
 }
@@ -356,6 +438,20 @@ pub struct PipeEdgeQuery {
 
     /// Specifies the oldest update datetime for returned edges.
     pub low: Option<DateTime<Utc>>,
+
+    /// Filters the edges returned to those whose type starts with this
+    /// prefix.
+    pub type_prefix: Option<String>,
+
+    /// Filters the edges returned to those whose type is one of these.
+    pub types: Option<Vec<Type>>,
+
+    /// Whether to also get the edges going the other direction.
+    pub undirected: bool,
+
+    /// How to sort the edges before the limit is applied. If `None`, edges
+    /// are returned in whatever order the datastore finds them.
+    pub sort: Option<SortKey>,
 }
 
 impl EdgeQueryExt for PipeEdgeQuery {}
@@ -376,6 +472,10 @@ impl PipeEdgeQuery {
             t: None,
             high: None,
             low: None,
+            type_prefix: None,
+            types: None,
+            undirected: false,
+            sort: None,
         }
     }
 
@@ -391,6 +491,10 @@ impl PipeEdgeQuery {
             t: Some(t),
             high: self.high,
             low: self.low,
+            type_prefix: self.type_prefix,
+            types: self.types,
+            undirected: self.undirected,
+            sort: self.sort,
         }
     }
 
@@ -406,6 +510,10 @@ impl PipeEdgeQuery {
             t: self.t,
             high: Some(high),
             low: self.low,
+            type_prefix: self.type_prefix,
+            types: self.types,
+            undirected: self.undirected,
+            sort: self.sort,
         }
     }
 
@@ -421,6 +529,90 @@ impl PipeEdgeQuery {
             t: self.t,
             high: self.high,
             low: Some(low),
+            type_prefix: self.type_prefix,
+            types: self.types,
+            undirected: self.undirected,
+            sort: self.sort,
+        }
+    }
+
+    /// Filter the edges returned to those whose type starts with a prefix.
+    /// The prefix doesn't need to be a valid type itself.
+    ///
+    /// # Arguments
+    /// * `prefix` - Sets the type prefix filter.
+    pub fn with_type_prefix(self, prefix: &str) -> Self {
+        Self {
+            inner: self.inner,
+            direction: self.direction,
+            limit: self.limit,
+            t: self.t,
+            high: self.high,
+            low: self.low,
+            type_prefix: Some(prefix.to_string()),
+            types: self.types,
+            undirected: self.undirected,
+            sort: self.sort,
+        }
+    }
+
+    /// Filter the edges returned to those whose type is one of a set of
+    /// types. An empty set matches no edges.
+    ///
+    /// # Arguments
+    /// * `types` - Sets the types filter.
+    pub fn with_type_in(self, types: Vec<Type>) -> Self {
+        Self {
+            inner: self.inner,
+            direction: self.direction,
+            limit: self.limit,
+            t: self.t,
+            high: self.high,
+            low: self.low,
+            type_prefix: self.type_prefix,
+            types: Some(types),
+            undirected: self.undirected,
+            sort: self.sort,
+        }
+    }
+
+    /// Gets the edges going in both directions, i.e. both the outbound and
+    /// inbound edges of the vertices. Edges are returned as they're stored,
+    /// and an edge is only returned once, even if both of its ends are in
+    /// the inner query. Use `Edge::reversed` to orient the edges.
+    pub fn undirected(self) -> Self {
+        Self {
+            inner: self.inner,
+            direction: self.direction,
+            limit: self.limit,
+            t: self.t,
+            high: self.high,
+            low: self.low,
+            type_prefix: self.type_prefix,
+            types: self.types,
+            undirected: true,
+            sort: self.sort,
+        }
+    }
+
+    /// Sorts the edges returned. The sort is applied before the limit, so
+    /// e.g. the newest edges can be gotten regardless of how they're
+    /// stored.
+    ///
+    /// # Arguments
+    /// * `sort` - How to sort the edges.
+    pub fn sort_by(self, sort: SortKey) -> Self {
+        Self {
+            inner: self.inner,
+            direction: self.direction,
+            limit: self.limit,
+            t: self.t,
+            high: self.high,
+            low: self.low,
+            type_prefix: self.type_prefix,
+            types: self.types,
+            undirected: self.undirected,
+            sort: Some(sort),
         }
     }
 }
@@ -451,7 +643,8 @@ impl EdgePropertyQuery {
 
 #[cfg(test)]
 mod tests {
-    use super::EdgeDirection;
+    use super::{EdgeDirection, SortKey};
+    use std::cmp::Ordering;
     use std::str::FromStr;
 
     #[test]
@@ -468,4 +661,13 @@ mod tests {
         let s: String = EdgeDirection::Inbound.into();
         assert_eq!(s, "inbound".to_string());
     }
+
+    #[test]
+    fn should_orient_sort_orderings() {
+        let ascending = SortKey::Id { ascending: true };
+        let descending = SortKey::UpdatedAt { ascending: false };
+        assert_eq!(ascending.order(Ordering::Less), Ordering::Less);
+        assert_eq!(descending.order(Ordering::Less), Ordering::Greater);
+        assert_eq!(descending.order(Ordering::Equal), Ordering::Equal);
+    }
 }
@@ -1,15 +1,20 @@
 use core::str::FromStr;
 use crate::errors::{ValidationError, ValidationResult};
 use regex::Regex;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Deref;
 
 lazy_static! {
     static ref TYPE_VALIDATOR: Regex = Regex::new("^[a-zA-Z0-9-_]+$").unwrap();
+    static ref TYPE_INVALID_CHAR: Regex = Regex::new("[^a-zA-Z0-9-_]").unwrap();
 }
 
 /// An edge or vertex type.
 ///
-/// Types must be less than 256 characters long, and can only contain letters,
-/// numbers, dashes and underscores.
+/// Types must be less than 256 bytes long, and can only contain letters,
+/// numbers, dashes and underscores. The limit is in bytes rather than
+/// characters because datastores encode the type length in a single byte.
 #[derive(Eq, PartialEq, Clone, Debug, Hash, Ord, PartialOrd)]
 pub struct Type(pub String);
 
@@ -18,26 +23,93 @@ impl Type {
     ///
     /// # Arguments
     ///
-    /// * `t` - The type, which must be less than 256 characters long.
+    /// * `t` - The type, which must be less than 256 bytes long.
     ///
     /// # Errors
-    /// Returns a `ValidationError` if the type is longer than 255 characters,
-    /// or has invalid characters.
+    /// Returns a `ValidationError` if the type is longer than 255 bytes,
+    /// or has invalid characters. If there is an invalid character, the
+    /// first one is reported via `ValidationError::InvalidType`.
     pub fn new<S: Into<String>>(s: S) -> ValidationResult<Self> {
         let s = s.into();
 
         if s.len() > 255 {
             Err(ValidationError::ValueTooLong)
         } else if !TYPE_VALIDATOR.is_match(&s[..]) {
+            match TYPE_INVALID_CHAR.find(&s[..]) {
+                Some(m) => Err(ValidationError::InvalidType {
+                    offending: s[m.start()..].chars().next().unwrap(),
+                }),
+                // The only way to not match without an invalid character is
+                // with an empty string
+                None => Err(ValidationError::InvalidValue),
+            }
+        } else {
+            Ok(Type(s))
+        }
+    }
+
+    /// Constructs a new type, validating it against a caller-supplied
+    /// pattern instead of the default character whitelist. This is useful
+    /// for namespaced types, e.g. `com.example.User`.
+    ///
+    /// # Arguments
+    ///
+    /// * `t` - The type, which must be less than 256 bytes long.
+    /// * `pattern` - The pattern the type must match.
+    ///
+    /// # Errors
+    /// Returns a `ValidationError` if the type is longer than 255 bytes,
+    /// or does not match `pattern`.
+    pub fn new_with_pattern<S: Into<String>>(s: S, pattern: &Regex) -> ValidationResult<Self> {
+        let s = s.into();
+
+        if s.len() > 255 {
+            Err(ValidationError::ValueTooLong)
+        } else if !pattern.is_match(&s[..]) {
             Err(ValidationError::InvalidValue)
         } else {
             Ok(Type(s))
         }
     }
 
+    /// Checks whether a string would make a valid type, without
+    /// constructing one.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - The candidate type.
+    pub fn is_valid(s: &str) -> bool {
+        s.len() <= 255 && TYPE_VALIDATOR.is_match(s)
+    }
+
+    /// Re-checks the invariants that every type must hold, however it was
+    /// constructed: that it's not empty, and that it's less than 256 bytes
+    /// long. Since the inner string is public, a type can be built without
+    /// going through a constructor, so datastores call this before storing
+    /// one. The character whitelist isn't checked, because types built with
+    /// `new_with_pattern` may legitimately fall outside of it.
+    ///
+    /// # Errors
+    /// Returns `ValidationError::InvalidValue` if the type is empty, or
+    /// `ValidationError::ValueTooLong` if it's longer than 255 bytes.
+    pub fn validate(&self) -> ValidationResult<()> {
+        if self.0.is_empty() {
+            Err(ValidationError::InvalidValue)
+        } else if self.0.len() > 255 {
+            Err(ValidationError::ValueTooLong)
+        } else {
+            Ok(())
+        }
+    }
+
     pub unsafe fn new_unchecked<S: Into<String>>(s: S) -> Self {
         Type(s.into())
     }
+
+    /// Gets a reference to the underlying string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 }
 
 impl Default for Type {
@@ -46,6 +118,20 @@ impl Default for Type {
     }
 }
 
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Deref for Type {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
 impl FromStr for Type {
     type Err = ValidationError;
 
@@ -54,9 +140,28 @@ impl FromStr for Type {
     }
 }
 
+impl TryFrom<String> for Type {
+    type Error = ValidationError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::new(s)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Type {
+    type Error = ValidationError;
+
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        Self::new(s)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Type;
+    use crate::errors::ValidationError;
+    use regex::Regex;
+    use std::convert::TryInto;
     use std::str::FromStr;
     use crate::util::generate_random_secret;
 
@@ -66,8 +171,112 @@ mod tests {
         assert!(Type::new("$").is_err());
     }
 
+    #[test]
+    fn should_report_offending_type_character() {
+        match Type::new("") {
+            Err(ValidationError::InvalidValue) => (),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        match Type::new("foo bar") {
+            Err(ValidationError::InvalidType { offending }) => assert_eq!(offending, ' '),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        match Type::new("foo\u{1F600}bar") {
+            Err(ValidationError::InvalidType { offending }) => assert_eq!(offending, '\u{1F600}'),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_validate_type_with_pattern() {
+        let pattern = Regex::new("^[a-zA-Z0-9-_.]+$").unwrap();
+        assert_eq!(
+            Type::new_with_pattern("com.example.User", &pattern).unwrap().0,
+            "com.example.User"
+        );
+        assert!(Type::new_with_pattern("com example", &pattern).is_err());
+        assert!(Type::new_with_pattern(generate_random_secret(256), &pattern).is_err());
+        assert!(Type::new("com.example.User").is_err());
+    }
+
+    #[test]
+    fn should_limit_type_length_in_bytes() {
+        assert!(Type::new("a".repeat(255)).is_ok());
+
+        match Type::new("a".repeat(256)) {
+            Err(ValidationError::ValueTooLong) => (),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        // 128 two-byte characters are within a character limit, but not the
+        // byte limit
+        let pattern = Regex::new("^.+$").unwrap();
+        assert!(Type::new_with_pattern(format!("{}a", "\u{e9}".repeat(127)), &pattern).is_ok());
+
+        match Type::new_with_pattern("\u{e9}".repeat(128), &pattern) {
+            Err(ValidationError::ValueTooLong) => (),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_check_type_validity() {
+        assert!(Type::is_valid("foo"));
+        assert!(Type::is_valid("foo-bar_baz"));
+        assert!(Type::is_valid(&"a".repeat(255)));
+        assert!(!Type::is_valid(""));
+        assert!(!Type::is_valid("$"));
+        assert!(!Type::is_valid("foo bar"));
+        assert!(!Type::is_valid(&"a".repeat(256)));
+    }
+
+    #[test]
+    fn should_validate_constructed_types() {
+        assert!(Type::new("foo").unwrap().validate().is_ok());
+        let pattern = Regex::new("^[a-zA-Z0-9-_.]+$").unwrap();
+        assert!(Type::new_with_pattern("com.example.User", &pattern)
+            .unwrap()
+            .validate()
+            .is_ok());
+
+        match Type("a".repeat(256)).validate() {
+            Err(ValidationError::ValueTooLong) => (),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        match Type::default().validate() {
+            Err(ValidationError::InvalidValue) => (),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
     #[test]
     fn should_convert_str_to_type() {
         assert_eq!(Type::from_str("foo").unwrap(), Type::new("foo").unwrap());
     }
+
+    #[test]
+    fn should_get_type_as_str() {
+        let t = Type::new("foo").unwrap();
+        assert_eq!(t.as_str(), "foo");
+        assert_eq!(t.len(), 3);
+    }
+
+    #[test]
+    fn should_display_type() {
+        assert_eq!(Type::new("foo").unwrap().to_string(), "foo");
+    }
+
+    #[test]
+    fn should_try_convert_to_type() {
+        let t: Type = "foo".to_string().try_into().unwrap();
+        assert_eq!(t, Type::new("foo").unwrap());
+        let t: Type = "foo".try_into().unwrap();
+        assert_eq!(t, Type::new("foo").unwrap());
+
+        let result: Result<Type, _> = "$".try_into();
+        assert!(result.is_err());
+    }
 }
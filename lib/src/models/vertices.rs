@@ -1,5 +1,7 @@
 use super::types::Type;
+use crate::errors::ValidationResult;
 use crate::util::generate_uuid_v1;
+use std::fmt;
 use uuid::Uuid;
 
 /// A vertex.
@@ -37,6 +39,25 @@ impl Vertex {
     pub fn with_id(id: Uuid, t: Type) -> Self {
         Vertex { id, t }
     }
+
+    /// Checks whether two vertices have the same type.
+    ///
+    /// # Arguments
+    /// * `other` - The vertex to compare against.
+    pub fn same_type(&self, other: &Vertex) -> bool {
+        self.t == other.t
+    }
+
+    /// Re-checks the vertex's type. See `Type::validate`.
+    pub fn validate(&self) -> ValidationResult<()> {
+        self.t.validate()
+    }
+}
+
+impl fmt::Display for Vertex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.id, self.t)
+    }
 }
 
 impl PartialEq for Vertex {
@@ -46,3 +67,29 @@ impl PartialEq for Vertex {
 }
 
 impl Eq for Vertex {}
+
+#[cfg(test)]
+mod tests {
+    use super::Vertex;
+    use crate::models::Type;
+    use std::str::FromStr;
+    use uuid::Uuid;
+
+    #[test]
+    fn should_display_vertex() {
+        let id = Uuid::from_str("16151dea-a538-4bf1-9559-851e256cf139").unwrap();
+        let vertex = Vertex::with_id(id, Type::new("user").unwrap());
+        assert_eq!(vertex.to_string(), "16151dea-a538-4bf1-9559-851e256cf139:user");
+    }
+
+    #[test]
+    fn should_compare_vertex_types() {
+        let user = Vertex::new(Type::new("user").unwrap());
+        let other_user = Vertex::new(Type::new("user").unwrap());
+        let movie = Vertex::new(Type::new("movie").unwrap());
+
+        assert!(user.same_type(&other_user));
+        assert!(user.same_type(&user));
+        assert!(!user.same_type(&movie));
+    }
+}
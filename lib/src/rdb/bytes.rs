@@ -1,5 +1,5 @@
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use chrono::offset::Utc;
+use chrono::offset::{TimeZone, Utc};
 use chrono::{DateTime, NaiveDateTime};
 use chrono::{Duration, Timelike};
 use crate::models;
@@ -25,6 +25,8 @@ pub enum Component<'a> {
     UnsizedString(&'a str),
     Type(&'a models::Type),
     DateTime(DateTime<Utc>),
+    // Unlike `DateTime`, sorts from oldest to newest
+    Timestamp(DateTime<Utc>),
 }
 
 impl<'a> Component<'a> {
@@ -33,7 +35,7 @@ impl<'a> Component<'a> {
             Component::Uuid(_) => 16,
             Component::UnsizedString(s) => s.len(),
             Component::Type(t) => t.0.len() + 1,
-            Component::DateTime(_) => 8,
+            Component::DateTime(_) | Component::Timestamp(_) => 8,
         }
     }
 
@@ -53,12 +55,27 @@ impl<'a> Component<'a> {
                 let time_to_end = nanos_since_epoch(&MAX_DATETIME) - nanos_since_epoch(&datetime);
                 cursor.write_u64::<BigEndian>(time_to_end)?;
             }
+            Component::Timestamp(datetime) => {
+                cursor.write_u64::<BigEndian>(nanos_since_epoch(&datetime))?;
+            }
         };
 
         Ok(())
     }
 }
 
+/// A type borrowed from the bytes of a key, rather than copied out of them.
+/// Scans compare against this, so that only the items they keep need an
+/// owned `Type`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TypeRef<'a>(pub &'a str);
+
+impl<'a> PartialEq<models::Type> for TypeRef<'a> {
+    fn eq(&self, other: &models::Type) -> bool {
+        self.0 == other.0
+    }
+}
+
 pub fn build(components: &[Component]) -> Vec<u8> {
     let len = components.iter().fold(0, |len, component| len + component.len());
     let mut cursor: Cursor<Vec<u8>> = Cursor::new(Vec::with_capacity(len));
@@ -94,6 +111,14 @@ pub fn read_type<T: AsRef<[u8]>>(cursor: &mut Cursor<T>) -> models::Type {
     }
 }
 
+pub fn read_type_ref<'a>(cursor: &mut Cursor<&'a [u8]>) -> TypeRef<'a> {
+    let bytes: &'a [u8] = cursor.get_ref();
+    let start = cursor.position() as usize + 1;
+    let end = start + bytes[start - 1] as usize;
+    cursor.set_position(end as u64);
+    unsafe { TypeRef(str::from_utf8_unchecked(&bytes[start..end])) }
+}
+
 pub fn read_unsized_string<T: AsRef<[u8]>>(cursor: &mut Cursor<T>) -> String {
     let mut buf = String::new();
     cursor.read_to_string(&mut buf).unwrap();
@@ -105,3 +130,8 @@ pub fn read_datetime<T: AsRef<[u8]>>(cursor: &mut Cursor<T>) -> DateTime<Utc> {
     assert!(time_to_end <= i64::MAX as u64);
     *MAX_DATETIME - Duration::nanoseconds(time_to_end as i64)
 }
+
+pub fn read_timestamp<T: AsRef<[u8]>>(cursor: &mut Cursor<T>) -> DateTime<Utc> {
+    let nanos = cursor.read_u64::<BigEndian>().unwrap();
+    Utc.timestamp((nanos / 1_000_000_000) as i64, (nanos % 1_000_000_000) as u32)
+}
@@ -2,28 +2,100 @@ use super::super::{
     Datastore, EdgeDirection, EdgePropertyQuery, EdgeQuery, Transaction, VertexPropertyQuery, VertexQuery,
 };
 use super::managers::*;
+use super::snapshot::{self, Record, RecordReader};
 use chrono::offset::Utc;
-use crate::errors::Result;
+use chrono::DateTime;
+use crate::errors::{Error, Result, ValidationError};
+use crate::ids::{IdGenerator, UuidV1Generator};
 use crate::models;
-use rocksdb::{DBCompactionStyle, Options, WriteBatch, WriteOptions, DB};
+use crate::stats::{ColumnStats, DatastoreStats};
+use rocksdb::{
+    BlockBasedOptions, ColumnFamily, DBCompactionStyle, DBCompressionType, IteratorMode, Options, WriteBatch,
+    WriteOptions, DB,
+};
 use serde_json::Value as JsonValue;
+use std::cmp;
+use std::collections::{HashMap, HashSet};
 use std::i32;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::iter;
 use std::sync::Arc;
+use std::u32;
 use std::u64;
 use std::usize;
 use crate::util::next_uuid;
 use uuid::Uuid;
 
-const CF_NAMES: [&str; 6] = [
+const CF_NAMES: [&str; 8] = [
     "vertices:v1",
+    "vertex_types:v1",
     "edges:v1",
     "edge_ranges:v1",
     "reversed_edge_ranges:v1",
+    "edge_times:v1",
     "vertex_properties:v1",
     "edge_properties:v1",
 ];
 
-fn get_options(max_open_files: Option<i32>, bulk_load_optimized: bool) -> Options {
+/// Tuning options for a rocksdb datastore. The defaults match the options
+/// used by `RocksdbDatastore::new`.
+#[derive(Clone, Debug, Default)]
+pub struct RocksdbConfig {
+    /// The maximum number of open files to have. If `None`, the default will
+    /// be used.
+    pub max_open_files: Option<i32>,
+    /// The size of the block cache, in megabytes. If `None`, the default will
+    /// be used.
+    pub block_cache_mb: Option<usize>,
+    /// The compression to use. If `None`, the default will be used.
+    pub compression: Option<DBCompressionType>,
+    /// Whether to configure the database to optimize for bulk loading, based
+    /// off of suggestions from the RocksDB FAQ.
+    pub bulk_load_optimized: bool,
+    /// The strategy for generating the ids of vertices created without one.
+    /// If `None`, UUID v1s will be used.
+    pub id_generator: Option<Arc<dyn IdGenerator>>,
+    /// Whether creating an edge that already exists should be a no-op that
+    /// returns false, rather than updating the edge's datetime. Bulk inserts
+    /// still overwrite existing edges.
+    pub dedup_edges: bool,
+    /// How durable writes are. Bulk inserts always skip the write-ahead log,
+    /// regardless of this.
+    pub durability: Durability,
+}
+
+/// How durable a rocksdb datastore's writes are, trading off against write
+/// throughput.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Durability {
+    /// Writes go to the write-ahead log, which is synced to disk before the
+    /// write returns. Writes survive a machine crash.
+    Sync,
+    /// Writes go to the write-ahead log, but it isn't synced. Writes survive
+    /// a process crash, but the latest ones may be lost if the machine
+    /// crashes. This is the default.
+    Async,
+    /// Writes skip the write-ahead log, so they may be lost if the process
+    /// crashes before they're flushed. This is the fastest option, and is
+    /// meant for loads that can be redone.
+    NoWal,
+}
+
+impl Default for Durability {
+    fn default() -> Self {
+        Durability::Async
+    }
+}
+
+fn write(db: &DB, batch: WriteBatch, durability: Durability) -> Result<()> {
+    let mut opts = WriteOptions::default();
+    opts.set_sync(durability == Durability::Sync);
+    opts.disable_wal(durability == Durability::NoWal);
+    db.write_opt(batch, &opts)?;
+    Ok(())
+}
+
+fn get_options(config: &RocksdbConfig) -> Options {
     // Current tuning based off of the total ordered example, flash
     // storage example on
     // https://github.com/facebook/rocksdb/wiki/RocksDB-Tuning-Guide
@@ -42,11 +114,21 @@ fn get_options(max_open_files: Option<i32>, bulk_load_optimized: bool) -> Option
     opts.set_max_bytes_for_level_multiplier(8.0);
     opts.set_max_background_compactions(4);
 
-    if let Some(max_open_files) = max_open_files {
+    if let Some(max_open_files) = config.max_open_files {
         opts.set_max_open_files(max_open_files);
     }
 
-    if bulk_load_optimized {
+    if let Some(block_cache_mb) = config.block_cache_mb {
+        let mut block_opts = BlockBasedOptions::default();
+        block_opts.set_lru_cache(block_cache_mb * 1_048_576);
+        opts.set_block_based_table_factory(&block_opts);
+    }
+
+    if let Some(compression) = config.compression {
+        opts.set_compression_type(compression);
+    }
+
+    if config.bulk_load_optimized {
         // Via https://github.com/facebook/rocksdb/wiki/RocksDB-FAQ
         opts.set_allow_concurrent_memtable_write(false);
         // opts.set_memtable_factory(MemtableFactory::Vector); // disabled as this seems to stall writes
@@ -71,10 +153,96 @@ where
     })
 }
 
+// Writes a vertex as part of a batch that may write the same vertex more
+// than once, keeping track of the type each vertex was last written with.
+fn create_vertex_in_batch(
+    vertex_manager: &VertexManager,
+    batch: &mut WriteBatch,
+    batch_vertex_types: &mut HashMap<Uuid, models::Type>,
+    vertex: &models::Vertex,
+) -> Result<()> {
+    let old_t = match batch_vertex_types.get(&vertex.id) {
+        Some(t) => Some(t.clone()),
+        None => vertex_manager.get(vertex.id)?,
+    };
+
+    vertex_manager.create_replacing(batch, vertex, old_t.as_ref())?;
+    batch_vertex_types.insert(vertex.id, vertex.t.clone());
+    Ok(())
+}
+
+// Populates the vertex type index for databases created before it existed.
+fn backfill_vertex_types(db: &Arc<DB>) -> Result<()> {
+    let vertex_manager = VertexManager::new(db.clone());
+    let vertex_type_manager = VertexTypeManager::new(db.clone());
+    let mut batch = WriteBatch::default();
+
+    for item in vertex_manager.iterate_for_range(Uuid::default())? {
+        let (id, t) = item?;
+        vertex_type_manager.create(&mut batch, &t, id)?;
+    }
+
+    db.write(batch)?;
+    Ok(())
+}
+
+// Populates the edge time index for databases created before it existed.
+fn backfill_edge_times(db: &Arc<DB>) -> Result<()> {
+    let edge_manager = EdgeManager::new(db.clone());
+    let edge_time_manager = EdgeTimeManager::new(db.clone());
+    let mut batch = WriteBatch::default();
+
+    for item in edge_manager.iterate_for_all()? {
+        let (key, update_datetime) = item?;
+        edge_time_manager.set(&mut batch, key.outbound_id, &key.t, update_datetime, key.inbound_id)?;
+    }
+
+    db.write(batch)?;
+    Ok(())
+}
+
+// Gets a numeric rocksdb property for a column family, treating a missing
+// property as zero.
+fn int_property(db: &DB, cf: ColumnFamily, name: &str) -> Result<u64> {
+    match db.property_value_cf(cf, name)? {
+        Some(value) => Ok(value.trim().parse().unwrap_or(0)),
+        None => Ok(0),
+    }
+}
+
+// Parses the output of the `rocksdb.levelstats` property into the number of
+// SST files and their size in bytes for each level. The output is a table
+// that looks like:
+//
+// Level Files Size(MB)
+// --------------------
+//   0        1        0
+//   1        0        0
+//
+// Sizes are only reported to the nearest megabyte.
+fn parse_level_stats(s: &str) -> Vec<(u64, u64)> {
+    s.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            if fields.len() != 3 || fields[0].parse::<u64>().is_err() {
+                return None;
+            }
+
+            let files = fields[1].parse::<u64>().ok()?;
+            let megabytes = fields[2].parse::<f64>().ok()?;
+            Some((files, (megabytes * 1024.0 * 1024.0) as u64))
+        })
+        .collect()
+}
+
 /// A datastore that is backed by rocksdb.
 #[derive(Debug)]
 pub struct RocksdbDatastore {
     db: Arc<DB>,
+    id_generator: Arc<dyn IdGenerator>,
+    dedup_edges: bool,
+    durability: Durability,
 }
 
 impl RocksdbDatastore {
@@ -88,22 +256,70 @@ impl RocksdbDatastore {
     ///   optimize for bulk loading, based off of suggestions from the RocksDB
     ///   FAQ.
     pub fn new(path: &str, max_open_files: Option<i32>, bulk_load_optimized: bool) -> Result<RocksdbDatastore> {
-        let opts = get_options(max_open_files, bulk_load_optimized);
+        let config = RocksdbConfig {
+            max_open_files,
+            bulk_load_optimized,
+            ..RocksdbConfig::default()
+        };
+
+        Self::new_with_options(path, config)
+    }
+
+    /// Creates a new rocksdb datastore with custom tuning options.
+    ///
+    /// # Arguments
+    /// * `path` - The file path to the rocksdb database.
+    /// * `config` - The options to open the database with.
+    pub fn new_with_options(path: &str, config: RocksdbConfig) -> Result<RocksdbDatastore> {
+        let opts = get_options(&config);
+
+        let mut needs_vertex_types_backfill = false;
+        let mut needs_edge_times_backfill = false;
 
         let db = match DB::open_cf(&opts, path, &CF_NAMES) {
             Ok(db) => db,
             Err(_) => {
-                let mut db = DB::open(&opts, path)?;
+                // Either the database doesn't exist yet, or it was created by
+                // an older version and is missing some column families.
+                let existing_cf_names = DB::list_cf(&opts, path).unwrap_or_default();
+                let existing_cf_names: Vec<&str> = existing_cf_names
+                    .iter()
+                    .map(|name| &name[..])
+                    .filter(|name| *name != "default")
+                    .collect();
+                let mut db = DB::open_cf(&opts, path, &existing_cf_names)?;
 
                 for cf_name in &CF_NAMES {
-                    db.create_cf(cf_name, &opts)?;
+                    if !existing_cf_names.contains(cf_name) {
+                        db.create_cf(cf_name, &opts)?;
+                    }
                 }
 
+                needs_vertex_types_backfill =
+                    existing_cf_names.contains(&"vertices:v1") && !existing_cf_names.contains(&"vertex_types:v1");
+                needs_edge_times_backfill =
+                    existing_cf_names.contains(&"edges:v1") && !existing_cf_names.contains(&"edge_times:v1");
                 db
             }
         };
 
-        Ok(RocksdbDatastore { db: Arc::new(db) })
+        let db = Arc::new(db);
+
+        if needs_vertex_types_backfill {
+            backfill_vertex_types(&db)?;
+        }
+
+        if needs_edge_times_backfill {
+            backfill_edge_times(&db)?;
+        }
+
+        let id_generator = config.id_generator.unwrap_or_else(|| Arc::new(UuidV1Generator));
+        Ok(RocksdbDatastore {
+            db,
+            id_generator,
+            dedup_edges: config.dedup_edges,
+            durability: config.durability,
+        })
     }
 
     /// Runs a repair operation on the rocksdb database.
@@ -113,10 +329,122 @@ impl RocksdbDatastore {
     /// * `max_open_files` - The maximum number of open files to have. If
     ///   `None`, the default will be used.
     pub fn repair(path: &str, max_open_files: Option<i32>) -> Result<()> {
-        let opts = get_options(max_open_files, false);
-        DB::repair(opts, path)?;
+        let config = RocksdbConfig {
+            max_open_files,
+            ..RocksdbConfig::default()
+        };
+
+        DB::repair(get_options(&config), path)?;
         Ok(())
     }
+
+    /// Writes all of the datastore's vertices, edges and properties as a
+    /// logical snapshot, which can be restored with `import_snapshot`, or
+    /// loaded into another kind of datastore with the free
+    /// `import_snapshot` function.
+    ///
+    /// The export reads the database as it goes, so writes made during it
+    /// may or may not be included.
+    ///
+    /// # Arguments
+    /// * `writer` - Where to write the snapshot.
+    pub fn export_snapshot<W: Write>(&self, writer: W) -> Result<()> {
+        let vertex_manager = VertexManager::new(self.db.clone());
+        let edge_manager = EdgeManager::new(self.db.clone());
+        let vertex_property_manager = VertexPropertyManager::new(self.db.clone());
+        let edge_property_manager = EdgePropertyManager::new(self.db.clone());
+        let mut writer = BufWriter::new(writer);
+
+        snapshot::write_header(&mut writer)?;
+
+        for item in vertex_manager.iterate_for_range(Uuid::default())? {
+            let (id, t) = item?;
+            snapshot::write_record(&mut writer, &Record::Vertex(models::Vertex::with_id(id, t)))?;
+
+            for item in vertex_property_manager.iterate_for_owner(id)? {
+                let ((_, name), value) = item?;
+                snapshot::write_record(&mut writer, &Record::VertexProperty(id, name, value))?;
+            }
+        }
+
+        for item in edge_manager.iterate_for_all()? {
+            let (key, created_datetime) = item?;
+            let edge = models::Edge::new(key.clone(), created_datetime);
+            snapshot::write_record(&mut writer, &Record::Edge(edge))?;
+
+            for item in edge_property_manager.iterate_for_owner(key.outbound_id, &key.t, key.inbound_id)? {
+                let ((_, _, _, name), value) = item?;
+                snapshot::write_record(&mut writer, &Record::EdgeProperty(key.clone(), name, value))?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Imports a snapshot written by `export_snapshot`. Unlike the free
+    /// `import_snapshot` function, edges keep their datetimes from the
+    /// snapshot. Records are merged into whatever the datastore already
+    /// has, so this is normally used on a freshly created datastore.
+    ///
+    /// # Arguments
+    /// * `reader` - Where to read the snapshot from.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidSnapshot` if the snapshot is malformed or
+    /// truncated. Records read before the problem was found are kept.
+    pub fn import_snapshot<R: Read>(&self, reader: R) -> Result<()> {
+        let vertex_manager = VertexManager::new(self.db.clone());
+        let edge_manager = EdgeManager::new(self.db.clone());
+        let vertex_property_manager = VertexPropertyManager::new(self.db.clone());
+        let edge_property_manager = EdgePropertyManager::new(self.db.clone());
+        let mut records = RecordReader::new(BufReader::new(reader))?;
+
+        loop {
+            let mut batch = WriteBatch::default();
+            let mut batch_vertex_types: HashMap<Uuid, models::Type> = HashMap::new();
+            let mut count = 0;
+
+            for record in records.by_ref().take(snapshot::IMPORT_BATCH_SIZE) {
+                match record? {
+                    Record::Vertex(vertex) => {
+                        create_vertex_in_batch(&vertex_manager, &mut batch, &mut batch_vertex_types, &vertex)?;
+                    }
+                    Record::Edge(edge) => {
+                        let key = edge.key;
+                        edge_manager.set(
+                            &mut batch,
+                            key.outbound_id,
+                            &key.t,
+                            key.inbound_id,
+                            edge.created_datetime,
+                        )?;
+                    }
+                    Record::VertexProperty(id, name, value) => {
+                        vertex_property_manager.set(&mut batch, id, &name, &value)?;
+                    }
+                    Record::EdgeProperty(key, name, value) => {
+                        edge_property_manager.set(
+                            &mut batch,
+                            key.outbound_id,
+                            &key.t,
+                            key.inbound_id,
+                            &name,
+                            &value,
+                        )?;
+                    }
+                }
+
+                count += 1;
+            }
+
+            if count == 0 {
+                return Ok(());
+            }
+
+            write(&self.db, batch, self.durability)?;
+        }
+    }
 }
 
 impl Datastore for RocksdbDatastore {
@@ -133,11 +461,12 @@ impl Datastore for RocksdbDatastore {
         let vertex_property_manager = VertexPropertyManager::new(self.db.clone());
         let edge_property_manager = EdgePropertyManager::new(self.db.clone());
         let mut batch = WriteBatch::default();
+        let mut batch_vertex_types: HashMap<Uuid, models::Type> = HashMap::new();
 
         for item in items {
             match item {
                 models::BulkInsertItem::Vertex(ref vertex) => {
-                    vertex_manager.create(&mut batch, vertex)?;
+                    create_vertex_in_batch(&vertex_manager, &mut batch, &mut batch_vertex_types, vertex)?;
                 }
                 models::BulkInsertItem::Edge(ref key) => {
                     edge_manager.set(&mut batch, key.outbound_id, &key.t, key.inbound_id, Utc::now())?;
@@ -161,8 +490,110 @@ impl Datastore for RocksdbDatastore {
         Ok(())
     }
 
+    // We override the default `clear` implementation so that the column
+    // families are emptied directly, rather than through the vertex
+    // deletion logic. The column families can't be dropped instead, since
+    // that requires exclusive access to the database, which transactions
+    // share.
+    fn clear(&self) -> Result<()> {
+        let mut batch = WriteBatch::default();
+
+        for cf_name in &CF_NAMES {
+            let cf = self.db.cf_handle(cf_name).unwrap();
+
+            for (key, _) in self.db.iterator_cf(cf, IteratorMode::Start)? {
+                batch.delete_cf(cf, &key)?;
+            }
+        }
+
+        write(&self.db, batch, self.durability)?;
+        Ok(())
+    }
+
+    // Deletions leave tombstones behind until they're compacted away, which
+    // slows down the range scans that queries are built on
+    fn compact(&self) -> Result<()> {
+        self.db.compact_range(None::<&[u8]>, None::<&[u8]>);
+
+        for cf_name in &CF_NAMES {
+            let cf = self.db.cf_handle(cf_name).unwrap();
+            self.db.compact_range_cf(cf, None::<&[u8]>, None::<&[u8]>);
+        }
+
+        Ok(())
+    }
+
+    // Writes made without durability may only be in the memtables, so
+    // they're flushed to SST files
+    fn flush(&self) -> Result<()> {
+        for cf_name in &CF_NAMES {
+            let cf = self.db.cf_handle(cf_name).unwrap();
+            self.db.flush_cf(cf)?;
+        }
+
+        Ok(())
+    }
+
+    fn stats(&self) -> Result<DatastoreStats> {
+        let trans = self.transaction()?;
+        let mut columns = Vec::with_capacity(CF_NAMES.len());
+
+        for cf_name in &CF_NAMES {
+            let cf = self.db.cf_handle(cf_name).unwrap();
+            let level_stats = self.db.property_value_cf(cf, "rocksdb.levelstats")?;
+            let levels = parse_level_stats(&level_stats.unwrap_or_default());
+
+            // Data that hasn't been flushed yet is only in the memtables
+            let approximate_bytes = int_property(&self.db, cf, "rocksdb.estimate-live-data-size")?
+                + int_property(&self.db, cf, "rocksdb.cur-size-all-mem-tables")?;
+
+            columns.push(ColumnStats {
+                name: cf_name.to_string(),
+                approximate_entries: int_property(&self.db, cf, "rocksdb.estimate-num-keys")?,
+                approximate_bytes,
+                sst_file_count: Some(levels.iter().map(|(files, _)| files).sum()),
+                level_bytes: levels.into_iter().map(|(_, bytes)| bytes).collect(),
+            });
+        }
+
+        Ok(DatastoreStats {
+            vertex_count: trans.get_vertex_count()?,
+            edge_count: trans.count_edges(None)?,
+            columns,
+        })
+    }
+
     fn transaction(&self) -> Result<Self::Trans> {
-        RocksdbTransaction::new(self.db.clone())
+        RocksdbTransaction::new(
+            self.db.clone(),
+            self.id_generator.clone(),
+            self.dedup_edges,
+            self.durability,
+        )
+    }
+
+    fn vertices_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Vertex>> + 'a> {
+        let vertex_manager = VertexManager::new(self.db.clone());
+
+        match vertex_manager.iterate_for_range(Uuid::default()) {
+            Ok(iterator) => Box::new(iterator.map(|item| {
+                let (id, t) = item?;
+                Ok(models::Vertex::with_id(id, t))
+            })),
+            Err(err) => Box::new(iter::once(Err(err))),
+        }
+    }
+
+    fn edges_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Edge>> + 'a> {
+        let edge_manager = EdgeManager::new(self.db.clone());
+
+        match edge_manager.iterate_for_all() {
+            Ok(iterator) => Box::new(iterator.map(|item| {
+                let (key, update_datetime) = item?;
+                Ok(models::Edge::new(key, update_datetime))
+            })),
+            Err(err) => Box::new(iter::once(Err(err))),
+        }
     }
 }
 
@@ -170,11 +601,19 @@ impl Datastore for RocksdbDatastore {
 #[derive(Debug)]
 pub struct RocksdbTransaction {
     db: Arc<DB>,
+    id_generator: Arc<dyn IdGenerator>,
+    dedup_edges: bool,
+    durability: Durability,
 }
 
 impl RocksdbTransaction {
-    fn new(db: Arc<DB>) -> Result<Self> {
-        Ok(RocksdbTransaction { db })
+    fn new(db: Arc<DB>, id_generator: Arc<dyn IdGenerator>, dedup_edges: bool, durability: Durability) -> Result<Self> {
+        Ok(RocksdbTransaction {
+            db,
+            id_generator,
+            dedup_edges,
+            durability,
+        })
     }
 
     fn vertex_query_to_iterator(&self, q: VertexQuery) -> Result<Box<dyn Iterator<Item = Result<VertexItem>>>> {
@@ -182,7 +621,7 @@ impl RocksdbTransaction {
             VertexQuery::Range(q) => {
                 let vertex_manager = VertexManager::new(self.db.clone());
 
-                let next_uuid = match q.start_id {
+                let mut next_uuid = match q.start_id {
                     Some(start_id) => {
                         match next_uuid(start_id) {
                             Ok(next_uuid) => next_uuid,
@@ -197,15 +636,34 @@ impl RocksdbTransaction {
                     None => Uuid::default(),
                 };
 
-                let mut iter: Box<dyn Iterator<Item = Result<VertexItem>>> =
-                    Box::new(vertex_manager.iterate_for_range(next_uuid)?);
+                if let Some(ref id_range) = q.id_range {
+                    next_uuid = cmp::max(next_uuid, id_range.start);
+                }
 
-                if let Some(ref t) = q.t {
-                    iter = Box::new(iter.filter(move |item| match item {
-                        Ok((_, v)) => v == t,
+                let iter: Box<dyn Iterator<Item = Result<VertexItem>>> = match (q.t, q.type_prefix) {
+                    (Some(t), Some(type_prefix)) => {
+                        if !t.0.starts_with(&type_prefix[..]) {
+                            return Ok(Box::new(vec![].into_iter()));
+                        }
+
+                        Box::new(vertex_manager.iterate_for_type(&t, next_uuid)?)
+                    }
+                    (Some(t), None) => Box::new(vertex_manager.iterate_for_type(&t, next_uuid)?),
+                    (None, Some(type_prefix)) => {
+                        Box::new(vertex_manager.iterate_for_type_prefix(&type_prefix, next_uuid)?)
+                    }
+                    (None, None) => Box::new(vertex_manager.iterate_for_range(next_uuid)?),
+                };
+
+                // Every iterator above is ordered by vertex ID, so the scan
+                // can stop at the end of the range
+                let iter: Box<dyn Iterator<Item = Result<VertexItem>>> = match q.id_range {
+                    Some(id_range) => Box::new(iter.take_while(move |item| match item {
+                        Ok((id, _)) => *id < id_range.end,
                         Err(_) => true,
-                    }));
-                }
+                    })),
+                    None => iter,
+                };
 
                 let results: Vec<Result<VertexItem>> = iter.take(q.limit as usize).collect();
                 Ok(Box::new(results.into_iter()))
@@ -272,11 +730,43 @@ impl RocksdbTransaction {
                 Ok(Box::new(iterator))
             }
             EdgeQuery::Pipe(q) => {
+                // Edges are scanned in key order, so sorting any other way
+                // means buffering every matching edge before the limit can
+                // be applied
+                if let Some(sort) = q.sort {
+                    let limit = q.limit as usize;
+                    let unsorted = models::PipeEdgeQuery {
+                        limit: u32::MAX,
+                        sort: None,
+                        ..q
+                    };
+
+                    let mut edges = self
+                        .edge_query_to_iterator(unsorted.into())?
+                        .collect::<Result<Vec<EdgeRangeItem>>>()?;
+
+                    edges.sort_by(|first, second| {
+                        let first_key = (first.0, &first.1, first.3);
+                        let second_key = (second.0, &second.1, second.3);
+
+                        sort.order(match sort {
+                            models::SortKey::UpdatedAt { .. } => {
+                                first.2.cmp(&second.2).then_with(|| first_key.cmp(&second_key))
+                            }
+                            models::SortKey::Id { .. } => first_key.cmp(&second_key),
+                        })
+                    });
+
+                    edges.truncate(limit);
+                    return Ok(Box::new(edges.into_iter().map(Ok)));
+                }
+
                 let vertex_iterator = self.vertex_query_to_iterator(*q.inner)?;
 
-                let edge_range_manager = match q.direction {
-                    EdgeDirection::Outbound => EdgeRangeManager::new(self.db.clone()),
-                    EdgeDirection::Inbound => EdgeRangeManager::new_reversed(self.db.clone()),
+                let directions = if q.undirected {
+                    vec![EdgeDirection::Outbound, EdgeDirection::Inbound]
+                } else {
+                    vec![q.direction]
                 };
 
                 // Ideally we'd use iterators all the way down, but things
@@ -286,44 +776,67 @@ impl RocksdbTransaction {
                 // just resort to building a vector.
                 let mut edges: Vec<Result<EdgeRangeItem>> = Vec::new();
 
-                for item in vertex_iterator {
+                // An undirected query finds an edge from both of its ends if
+                // they're both in the inner query, so keep track of the edges
+                // that have already been returned.
+                let mut seen_keys = HashSet::new();
+
+                'vertices: for item in vertex_iterator {
                     let (id, _) = item?;
-                    let edge_iterator = edge_range_manager.iterate_for_range(id, q.t.as_ref(), q.high)?;
-
-                    for item in edge_iterator {
-                        match item {
-                            Ok((
-                                edge_range_first_id,
-                                edge_range_t,
-                                edge_range_update_datetime,
-                                edge_range_second_id,
-                            )) => {
-                                if let Some(low) = q.low {
-                                    if edge_range_update_datetime < low {
-                                        break;
+
+                    for direction in &directions {
+                        let edge_range_manager = match direction {
+                            EdgeDirection::Outbound => EdgeRangeManager::new(self.db.clone()),
+                            EdgeDirection::Inbound => EdgeRangeManager::new_reversed(self.db.clone()),
+                        };
+
+                        let edge_iterator = edge_range_manager.iterate_for_range(id, q.t.as_ref(), q.high)?;
+
+                        for item in edge_iterator {
+                            match item {
+                                Ok((
+                                    edge_range_first_id,
+                                    edge_range_t,
+                                    edge_range_update_datetime,
+                                    edge_range_second_id,
+                                )) => {
+                                    if let Some(ref type_prefix) = q.type_prefix {
+                                        if !edge_range_t.0.starts_with(&type_prefix[..]) {
+                                            continue;
+                                        }
                                     }
-                                }
 
-                                edges.push(match q.direction {
-                                    EdgeDirection::Outbound => Ok((
-                                        edge_range_first_id,
-                                        edge_range_t,
-                                        edge_range_update_datetime,
-                                        edge_range_second_id,
-                                    )),
-                                    EdgeDirection::Inbound => Ok((
-                                        edge_range_second_id,
-                                        edge_range_t,
-                                        edge_range_update_datetime,
-                                        edge_range_first_id,
-                                    )),
-                                })
+                                    if let Some(ref types) = q.types {
+                                        if !types.contains(&edge_range_t) {
+                                            continue;
+                                        }
+                                    }
+
+                                    if let Some(low) = q.low {
+                                        if edge_range_update_datetime < low {
+                                            break;
+                                        }
+                                    }
+
+                                    let (outbound_id, inbound_id) = match direction {
+                                        EdgeDirection::Outbound => (edge_range_first_id, edge_range_second_id),
+                                        EdgeDirection::Inbound => (edge_range_second_id, edge_range_first_id),
+                                    };
+
+                                    if q.undirected
+                                        && !seen_keys.insert((outbound_id, edge_range_t.clone(), inbound_id))
+                                    {
+                                        continue;
+                                    }
+
+                                    edges.push(Ok((outbound_id, edge_range_t, edge_range_update_datetime, inbound_id)));
+                                }
+                                Err(_) => edges.push(item),
                             }
-                            Err(_) => edges.push(item),
-                        }
 
-                        if edges.len() == q.limit as usize {
-                            break;
+                            if edges.len() == q.limit as usize {
+                                break 'vertices;
+                            }
                         }
                     }
                 }
@@ -336,18 +849,29 @@ impl RocksdbTransaction {
 
 impl Transaction for RocksdbTransaction {
     fn create_vertex(&self, vertex: &models::Vertex) -> Result<bool> {
+        vertex.validate()?;
         let vertex_manager = VertexManager::new(self.db.clone());
 
         if vertex_manager.exists(vertex.id)? {
             Ok(false)
         } else {
             let mut batch = WriteBatch::default();
-            vertex_manager.create(&mut batch, vertex)?;
-            self.db.write(batch)?;
+            vertex_manager.create_replacing(&mut batch, vertex, None)?;
+            write(&self.db, batch, self.durability)?;
             Ok(true)
         }
     }
 
+    fn create_vertex_from_type(&self, t: models::Type) -> Result<Uuid> {
+        let v = models::Vertex::with_id(self.id_generator.generate(), t);
+
+        if !self.create_vertex(&v)? {
+            Err(Error::UuidTaken)
+        } else {
+            Ok(v.id)
+        }
+    }
+
     fn get_vertices<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<Vec<models::Vertex>> {
         let iterator = self.vertex_query_to_iterator(q.into())?;
 
@@ -370,30 +894,111 @@ impl Transaction for RocksdbTransaction {
             vertex_manager.delete(&mut batch, id)?;
         }
 
-        self.db.write(batch)?;
+        write(&self.db, batch, self.durability)?;
         Ok(())
     }
 
+    fn delete_vertices_by_type(&self, t: models::Type) -> Result<u64> {
+        let vertex_manager = VertexManager::new(self.db.clone());
+        let mut batch = WriteBatch::default();
+        let mut count = 0;
+
+        for item in vertex_manager.iterate_for_type(&t, Uuid::default())? {
+            let (id, _) = item?;
+            vertex_manager.delete(&mut batch, id)?;
+            count += 1;
+        }
+
+        write(&self.db, batch, self.durability)?;
+        Ok(count)
+    }
+
     fn get_vertex_count(&self) -> Result<u64> {
         let vertex_manager = VertexManager::new(self.db.clone());
         let iterator = vertex_manager.iterate_for_range(Uuid::default())?;
         Ok(iterator.count() as u64)
     }
 
+    fn count_vertices(&self, t: Option<&models::Type>) -> Result<u64> {
+        let vertex_manager = VertexManager::new(self.db.clone());
+
+        let iter: Box<dyn Iterator<Item = Result<VertexItem>>> = match t {
+            Some(t) => Box::new(vertex_manager.iterate_for_type(t, Uuid::default())?),
+            None => Box::new(vertex_manager.iterate_for_range(Uuid::default())?),
+        };
+
+        let mut count = 0;
+
+        for item in iter {
+            item?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    // The version of the rocksdb crate in use doesn't expose `multi_get`, so
+    // this does a point lookup per vertex. That still avoids fetching the
+    // vertices' types, and a round trip per vertex for remote callers.
+    fn which_vertices_exist(&self, ids: &[Uuid]) -> Result<Vec<bool>> {
+        let vertex_manager = VertexManager::new(self.db.clone());
+        ids.iter().map(|id| vertex_manager.exists(*id)).collect()
+    }
+
     fn create_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        key.validate()?;
         let vertex_manager = VertexManager::new(self.db.clone());
 
         if !vertex_manager.exists(key.outbound_id)? || !vertex_manager.exists(key.inbound_id)? {
             Ok(false)
         } else {
             let edge_manager = EdgeManager::new(self.db.clone());
+
+            if self.dedup_edges && edge_manager.get(key.outbound_id, &key.t, key.inbound_id)?.is_some() {
+                return Ok(false);
+            }
+
             let mut batch = WriteBatch::default();
             edge_manager.set(&mut batch, key.outbound_id, &key.t, key.inbound_id, Utc::now())?;
-            self.db.write(batch)?;
+            write(&self.db, batch, self.durability)?;
             Ok(true)
         }
     }
 
+    fn create_edges(&self, keys: &[models::EdgeKey]) -> Result<Vec<bool>> {
+        for key in keys {
+            key.validate()?;
+        }
+
+        let vertex_manager = VertexManager::new(self.db.clone());
+        let edge_manager = EdgeManager::new(self.db.clone());
+        let mut batch = WriteBatch::default();
+        let mut results = Vec::with_capacity(keys.len());
+
+        // `EdgeManager::set` only sees edges that have already been written,
+        // so a key that's repeated in `keys` must only be set once, otherwise
+        // a stale edge range entry would be left behind.
+        let mut set_keys = HashSet::new();
+
+        for key in keys {
+            if !vertex_manager.exists(key.outbound_id)? || !vertex_manager.exists(key.inbound_id)? {
+                results.push(false);
+            } else if !set_keys.insert(key) {
+                // With deduplication, only the first of a repeated key counts
+                // as created
+                results.push(!self.dedup_edges);
+            } else if self.dedup_edges && edge_manager.get(key.outbound_id, &key.t, key.inbound_id)?.is_some() {
+                results.push(false);
+            } else {
+                edge_manager.set(&mut batch, key.outbound_id, &key.t, key.inbound_id, Utc::now())?;
+                results.push(true);
+            }
+        }
+
+        write(&self.db, batch, self.durability)?;
+        Ok(results)
+    }
+
     fn get_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<Vec<models::Edge>> {
         let iterator = self.edge_query_to_iterator(q.into())?;
 
@@ -407,6 +1012,11 @@ impl Transaction for RocksdbTransaction {
         mapped.collect()
     }
 
+    fn has_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        let edge_manager = EdgeManager::new(self.db.clone());
+        Ok(edge_manager.get(key.outbound_id, &key.t, key.inbound_id)?.is_some())
+    }
+
     fn delete_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<()> {
         let edge_manager = EdgeManager::new(self.db.clone());
         let vertex_manager = VertexManager::new(self.db.clone());
@@ -421,7 +1031,7 @@ impl Transaction for RocksdbTransaction {
             };
         }
 
-        self.db.write(batch)?;
+        write(&self.db, batch, self.durability)?;
         Ok(())
     }
 
@@ -436,6 +1046,109 @@ impl Transaction for RocksdbTransaction {
         Ok(count as u64)
     }
 
+    fn count_edges(&self, t: Option<&models::Type>) -> Result<u64> {
+        let edge_manager = EdgeManager::new(self.db.clone());
+
+        if let Some(t) = t {
+            return edge_manager.count_for_type(t);
+        }
+
+        let mut count = 0;
+
+        for item in edge_manager.iterate_for_all()? {
+            item?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    fn edges_by_time(
+        &self,
+        after: Option<(DateTime<Utc>, models::EdgeKey)>,
+        limit: usize,
+    ) -> Result<Vec<models::Edge>> {
+        let edge_time_manager = EdgeTimeManager::new(self.db.clone());
+        let mut edges: Vec<models::Edge> = Vec::new();
+        let from = after.as_ref().map(|(datetime, _)| *datetime);
+
+        for item in edge_time_manager.iterate_for_range(from)? {
+            let (outbound_id, t, update_datetime, inbound_id) = item?;
+
+            // The index orders edges with the same datetime by their encoded
+            // keys, which doesn't match how `EdgeKey`s are ordered, so keep
+            // reading past the limit until the last datetime is complete,
+            // and sort afterwards
+            let last_update_datetime = edges.last().map(|edge| edge.created_datetime);
+
+            if edges.len() >= limit && last_update_datetime != Some(update_datetime) {
+                break;
+            }
+
+            let key = models::EdgeKey::new(outbound_id, t, inbound_id);
+
+            // Edges at the cursor's datetime may come before or after its
+            // key
+            if let Some((ref after_datetime, ref after_key)) = after {
+                if (update_datetime, &key) <= (*after_datetime, after_key) {
+                    continue;
+                }
+            }
+
+            edges.push(models::Edge::new(key, update_datetime));
+        }
+
+        edges.sort_by_key(|edge| (edge.created_datetime, edge.key.clone()));
+        edges.truncate(limit);
+        Ok(edges)
+    }
+
+    fn rename_edge_type(&self, from: models::Type, to: models::Type) -> Result<u64> {
+        to.validate()?;
+
+        if from == to {
+            return Err(ValidationError::InvalidValue.into());
+        }
+
+        let edge_manager = EdgeManager::new(self.db.clone());
+        let edge_property_manager = EdgePropertyManager::new(self.db.clone());
+        let mut batch = WriteBatch::default();
+        let mut count = 0;
+
+        for item in edge_manager.iterate_for_type(&from)? {
+            let (key, update_datetime) = item?;
+
+            // The batch isn't written until every edge has been handled, so
+            // these reads all see the edges as they were before the rename
+            let replaced_update_datetime = edge_manager.get(key.outbound_id, &to, key.inbound_id)?;
+            let properties: Vec<EdgePropertyItem> = edge_property_manager
+                .iterate_for_owner(key.outbound_id, &from, key.inbound_id)?
+                .collect::<Result<_>>()?;
+
+            if let Some(replaced_update_datetime) = replaced_update_datetime {
+                edge_manager.delete(
+                    &mut batch,
+                    key.outbound_id,
+                    &to,
+                    key.inbound_id,
+                    replaced_update_datetime,
+                )?;
+            }
+
+            edge_manager.delete(&mut batch, key.outbound_id, &from, key.inbound_id, update_datetime)?;
+            edge_manager.set(&mut batch, key.outbound_id, &to, key.inbound_id, update_datetime)?;
+
+            for ((_, _, _, name), value) in properties {
+                edge_property_manager.set(&mut batch, key.outbound_id, &to, key.inbound_id, &name, &value)?;
+            }
+
+            count += 1;
+        }
+
+        write(&self.db, batch, self.durability)?;
+        Ok(count)
+    }
+
     fn get_vertex_properties(&self, q: VertexPropertyQuery) -> Result<Vec<models::VertexProperty>> {
         let manager = VertexPropertyManager::new(self.db.clone());
         let mut properties = Vec::new();
@@ -461,7 +1174,7 @@ impl Transaction for RocksdbTransaction {
             manager.set(&mut batch, id, &q.name, value)?;
         }
 
-        self.db.write(batch)?;
+        write(&self.db, batch, self.durability)?;
         Ok(())
     }
 
@@ -474,7 +1187,7 @@ impl Transaction for RocksdbTransaction {
             manager.delete(&mut batch, id, &q.name)?;
         }
 
-        self.db.write(batch)?;
+        write(&self.db, batch, self.durability)?;
         Ok(())
     }
 
@@ -504,7 +1217,7 @@ impl Transaction for RocksdbTransaction {
             manager.set(&mut batch, outbound_id, &t, inbound_id, &q.name, value)?;
         }
 
-        self.db.write(batch)?;
+        write(&self.db, batch, self.durability)?;
         Ok(())
     }
 
@@ -517,7 +1230,13 @@ impl Transaction for RocksdbTransaction {
             manager.delete(&mut batch, outbound_id, &t, inbound_id, &q.name)?;
         }
 
-        self.db.write(batch)?;
+        write(&self.db, batch, self.durability)?;
         Ok(())
     }
+
+    fn get_edge_property_names(&self, key: &models::EdgeKey) -> Result<Vec<String>> {
+        let manager = EdgePropertyManager::new(self.db.clone());
+        let iterator = manager.iterate_for_owner(key.outbound_id, &key.t, key.inbound_id)?;
+        iterator.map(|item| Ok((item?.0).3)).collect()
+    }
 }
@@ -6,6 +6,7 @@ use crate::models;
 use rocksdb::{ColumnFamily, DBIterator, Direction, IteratorMode, WriteBatch, DB};
 use serde_json;
 use serde_json::Value as JsonValue;
+use std::cmp;
 use std::io::Cursor;
 use std::ops::Deref;
 use std::sync::Arc;
@@ -14,6 +15,7 @@ use uuid::Uuid;
 
 pub type OwnedPropertyItem = ((Uuid, String), JsonValue);
 pub type VertexItem = (Uuid, models::Type);
+pub type EdgeItem = (models::EdgeKey, DateTime<Utc>);
 pub type EdgeRangeItem = (Uuid, models::Type, DateTime<Utc>, Uuid);
 pub type EdgePropertyItem = ((Uuid, models::Type, Uuid, String), JsonValue);
 
@@ -79,13 +81,73 @@ impl VertexManager {
         self.iterate(iter)
     }
 
+    pub fn iterate_for_type(&self, t: &models::Type, id: Uuid) -> Result<impl Iterator<Item = Result<VertexItem>>> {
+        let vertex_type_manager = VertexTypeManager::new(self.db.clone());
+        let t = t.clone();
+        let iterator = vertex_type_manager
+            .iterate_for_type(&t, id)?
+            .map(move |item| -> Result<VertexItem> { Ok((item?, t.clone())) });
+        Ok(iterator)
+    }
+
+    pub fn iterate_for_type_prefix(
+        &self,
+        type_prefix: &str,
+        id: Uuid,
+    ) -> Result<impl Iterator<Item = Result<VertexItem>>> {
+        let vertex_type_manager = VertexTypeManager::new(self.db.clone());
+        let mut items: Vec<VertexItem> = Vec::new();
+
+        for item in vertex_type_manager.iterate_for_type_prefix(type_prefix)? {
+            let (t, vertex_id) = item?;
+
+            if vertex_id >= id {
+                items.push((vertex_id, t));
+            }
+        }
+
+        items.sort();
+        Ok(items.into_iter().map(Ok))
+    }
+
     pub fn create(&self, batch: &mut WriteBatch, vertex: &models::Vertex) -> Result<()> {
+        let old_t = self.get(vertex.id)?;
+        self.create_replacing(batch, vertex, old_t.as_ref())
+    }
+
+    // Writes a vertex that previously had the type `old_t`, if any. Writes
+    // earlier in the same batch aren't visible to `get`, so callers that
+    // write a vertex more than once per batch pass in the type they wrote
+    // last.
+    pub fn create_replacing(
+        &self,
+        mut batch: &mut WriteBatch,
+        vertex: &models::Vertex,
+        old_t: Option<&models::Type>,
+    ) -> Result<()> {
         let key = self.key(vertex.id);
         batch.put_cf(self.cf, &key, &build(&[Component::Type(&vertex.t)]))?;
+
+        let vertex_type_manager = VertexTypeManager::new(self.db.clone());
+
+        // Otherwise the entry for the vertex's old type would be left
+        // behind in the index when its type changes
+        if let Some(old_t) = old_t {
+            if *old_t != vertex.t {
+                vertex_type_manager.delete(&mut batch, old_t, vertex.id)?;
+            }
+        }
+
+        vertex_type_manager.create(&mut batch, &vertex.t, vertex.id)?;
         Ok(())
     }
 
     pub fn delete(&self, mut batch: &mut WriteBatch, id: Uuid) -> Result<()> {
+        if let Some(t) = self.get(id)? {
+            let vertex_type_manager = VertexTypeManager::new(self.db.clone());
+            vertex_type_manager.delete(&mut batch, &t, id)?;
+        }
+
         batch.delete_cf(self.cf, &self.key(id))?;
 
         let vertex_property_manager = VertexPropertyManager::new(self.db.clone());
@@ -135,6 +197,74 @@ impl VertexManager {
     }
 }
 
+pub struct VertexTypeManager {
+    pub db: Arc<DB>,
+    pub cf: ColumnFamily,
+}
+
+impl VertexTypeManager {
+    pub fn new(db: Arc<DB>) -> Self {
+        VertexTypeManager {
+            cf: db.cf_handle("vertex_types:v1").unwrap(),
+            db,
+        }
+    }
+
+    fn key(&self, t: &models::Type, id: Uuid) -> Vec<u8> {
+        build(&[Component::Type(t), Component::Uuid(id)])
+    }
+
+    pub fn iterate_for_type(&self, t: &models::Type, id: Uuid) -> Result<impl Iterator<Item = Result<Uuid>>> {
+        let prefix = build(&[Component::Type(t)]);
+        let low_key = self.key(t, id);
+        let iterator = self
+            .db
+            .iterator_cf(self.cf, IteratorMode::From(&low_key, Direction::Forward))?;
+
+        Ok(take_while_prefixed(iterator, prefix).map(|item| -> Result<Uuid> {
+            let (k, _) = item;
+            let mut cursor = Cursor::new(k);
+            read_type(&mut cursor);
+            Ok(read_uuid(&mut cursor))
+        }))
+    }
+
+    pub fn iterate_for_type_prefix(
+        &self,
+        type_prefix: &str,
+    ) -> Result<impl Iterator<Item = Result<(models::Type, Uuid)>>> {
+        // Types are length-prefixed in keys, so the matching entries are
+        // split across one key range for each possible type length.
+        let mut items = Vec::new();
+
+        for len in cmp::max(type_prefix.len(), 1)..=u8::MAX as usize {
+            let mut prefix = vec![len as u8];
+            prefix.extend_from_slice(type_prefix.as_bytes());
+            let iterator = self
+                .db
+                .iterator_cf(self.cf, IteratorMode::From(&prefix, Direction::Forward))?;
+
+            for (k, _) in take_while_prefixed(iterator, prefix) {
+                let mut cursor = Cursor::new(k);
+                let t = read_type(&mut cursor);
+                items.push(Ok((t, read_uuid(&mut cursor))));
+            }
+        }
+
+        Ok(items.into_iter())
+    }
+
+    pub fn create(&self, batch: &mut WriteBatch, t: &models::Type, id: Uuid) -> Result<()> {
+        batch.put_cf(self.cf, &self.key(t, id), &[])?;
+        Ok(())
+    }
+
+    pub fn delete(&self, batch: &mut WriteBatch, t: &models::Type, id: Uuid) -> Result<()> {
+        batch.delete_cf(self.cf, &self.key(t, id))?;
+        Ok(())
+    }
+}
+
 pub struct EdgeManager {
     pub db: Arc<DB>,
     pub cf: ColumnFamily,
@@ -166,6 +296,51 @@ impl EdgeManager {
         }
     }
 
+    pub fn iterate_for_all(&self) -> Result<impl Iterator<Item = Result<EdgeItem>>> {
+        let iterator = self.db.iterator_cf(self.cf, IteratorMode::Start)?;
+
+        Ok(iterator.map(|item| -> Result<EdgeItem> {
+            let (k, v) = item;
+            let mut cursor = Cursor::new(k);
+            let outbound_id = read_uuid(&mut cursor);
+            let t = read_type(&mut cursor);
+            let inbound_id = read_uuid(&mut cursor);
+            let mut cursor = Cursor::new(v);
+            let update_datetime = read_datetime(&mut cursor);
+            Ok((models::EdgeKey::new(outbound_id, t, inbound_id), update_datetime))
+        }))
+    }
+
+    // Types are compared in place, so edges of other types are skipped
+    // without copying their types out of their keys
+    fn matches_type(k: &[u8], t: &models::Type) -> bool {
+        let mut cursor = Cursor::new(k);
+        read_uuid(&mut cursor);
+        read_type_ref(&mut cursor) == *t
+    }
+
+    pub fn iterate_for_type<'a>(&self, t: &'a models::Type) -> Result<impl Iterator<Item = Result<EdgeItem>> + 'a> {
+        let iterator = self.db.iterator_cf(self.cf, IteratorMode::Start)?;
+
+        Ok(iterator
+            .filter(move |(k, _)| Self::matches_type(k, t))
+            .map(move |(k, v)| -> Result<EdgeItem> {
+                let mut cursor = Cursor::new(&k[..]);
+                let outbound_id = read_uuid(&mut cursor);
+                read_type_ref(&mut cursor);
+                let inbound_id = read_uuid(&mut cursor);
+                let mut cursor = Cursor::new(v);
+                let update_datetime = read_datetime(&mut cursor);
+                let key = models::EdgeKey::new(outbound_id, t.clone(), inbound_id);
+                Ok((key, update_datetime))
+            }))
+    }
+
+    pub fn count_for_type(&self, t: &models::Type) -> Result<u64> {
+        let iterator = self.db.iterator_cf(self.cf, IteratorMode::Start)?;
+        Ok(iterator.filter(|(k, _)| Self::matches_type(k, t)).count() as u64)
+    }
+
     pub fn set(
         &self,
         mut batch: &mut WriteBatch,
@@ -177,15 +352,19 @@ impl EdgeManager {
         let edge_range_manager = EdgeRangeManager::new(self.db.clone());
         let reversed_edge_range_manager = EdgeRangeManager::new_reversed(self.db.clone());
 
+        let edge_time_manager = EdgeTimeManager::new(self.db.clone());
+
         if let Some(update_datetime) = self.get(outbound_id, t, inbound_id)? {
             edge_range_manager.delete(&mut batch, outbound_id, t, update_datetime, inbound_id)?;
             reversed_edge_range_manager.delete(&mut batch, inbound_id, t, update_datetime, outbound_id)?;
+            edge_time_manager.delete(&mut batch, outbound_id, t, update_datetime, inbound_id)?;
         }
 
         let key = self.key(outbound_id, t, inbound_id);
         batch.put_cf(self.cf, &key, &build(&[Component::DateTime(new_update_datetime)]))?;
         edge_range_manager.set(&mut batch, outbound_id, t, new_update_datetime, inbound_id)?;
         reversed_edge_range_manager.set(&mut batch, inbound_id, t, new_update_datetime, outbound_id)?;
+        edge_time_manager.set(&mut batch, outbound_id, t, new_update_datetime, inbound_id)?;
         Ok(())
     }
 
@@ -205,6 +384,9 @@ impl EdgeManager {
         let reversed_edge_range_manager = EdgeRangeManager::new_reversed(self.db.clone());
         reversed_edge_range_manager.delete(&mut batch, inbound_id, t, update_datetime, outbound_id)?;
 
+        let edge_time_manager = EdgeTimeManager::new(self.db.clone());
+        edge_time_manager.delete(&mut batch, outbound_id, t, update_datetime, inbound_id)?;
+
         let edge_property_manager = EdgePropertyManager::new(self.db.clone());
         for item in edge_property_manager.iterate_for_owner(outbound_id, t, inbound_id)? {
             let ((edge_property_outbound_id, edge_property_t, edge_property_inbound_id, edge_property_name), _) = item?;
@@ -341,6 +523,79 @@ impl EdgeRangeManager {
     }
 }
 
+pub struct EdgeTimeManager {
+    pub db: Arc<DB>,
+    pub cf: ColumnFamily,
+}
+
+impl EdgeTimeManager {
+    pub fn new(db: Arc<DB>) -> Self {
+        EdgeTimeManager {
+            cf: db.cf_handle("edge_times:v1").unwrap(),
+            db,
+        }
+    }
+
+    fn key(&self, outbound_id: Uuid, t: &models::Type, update_datetime: DateTime<Utc>, inbound_id: Uuid) -> Vec<u8> {
+        build(&[
+            Component::Timestamp(update_datetime),
+            Component::Uuid(outbound_id),
+            Component::Type(t),
+            Component::Uuid(inbound_id),
+        ])
+    }
+
+    // Iterates from the edges at `from`, inclusive
+    pub fn iterate_for_range(
+        &self,
+        from: Option<DateTime<Utc>>,
+    ) -> Result<impl Iterator<Item = Result<EdgeRangeItem>>> {
+        let iterator = match from {
+            Some(from) => {
+                let low_key = build(&[Component::Timestamp(from)]);
+                self.db
+                    .iterator_cf(self.cf, IteratorMode::From(&low_key, Direction::Forward))?
+            }
+            None => self.db.iterator_cf(self.cf, IteratorMode::Start)?,
+        };
+
+        Ok(iterator.map(|item| -> Result<EdgeRangeItem> {
+            let (k, _) = item;
+            let mut cursor = Cursor::new(k);
+            let update_datetime = read_timestamp(&mut cursor);
+            let outbound_id = read_uuid(&mut cursor);
+            let t = read_type(&mut cursor);
+            let inbound_id = read_uuid(&mut cursor);
+            Ok((outbound_id, t, update_datetime, inbound_id))
+        }))
+    }
+
+    pub fn set(
+        &self,
+        batch: &mut WriteBatch,
+        outbound_id: Uuid,
+        t: &models::Type,
+        update_datetime: DateTime<Utc>,
+        inbound_id: Uuid,
+    ) -> Result<()> {
+        let key = self.key(outbound_id, t, update_datetime, inbound_id);
+        batch.put_cf(self.cf, &key, &[])?;
+        Ok(())
+    }
+
+    pub fn delete(
+        &self,
+        batch: &mut WriteBatch,
+        outbound_id: Uuid,
+        t: &models::Type,
+        update_datetime: DateTime<Utc>,
+        inbound_id: Uuid,
+    ) -> Result<()> {
+        batch.delete_cf(self.cf, &self.key(outbound_id, t, update_datetime, inbound_id))?;
+        Ok(())
+    }
+}
+
 pub struct VertexPropertyManager {
     pub db: Arc<DB>,
     pub cf: ColumnFamily,
@@ -3,11 +3,14 @@
 mod bytes;
 mod datastore;
 mod managers;
+mod snapshot;
 
 #[cfg(feature = "test-suite")]
 mod tests;
 
-pub use self::datastore::{RocksdbDatastore, RocksdbTransaction};
+pub use self::datastore::{Durability, RocksdbConfig, RocksdbDatastore, RocksdbTransaction};
+pub use self::snapshot::import_snapshot;
+pub use rocksdb::DBCompressionType;
 
 mod normal_config {
     #[cfg(feature = "bench-suite")]
@@ -40,3 +43,54 @@ mod bulk_load_optimized_config {
         RocksdbDatastore::new(&generate_temporary_path(), Some(1), true).unwrap()
     });
 }
+
+// Compares the cost of each durability setting. Only the transactional
+// write benchmarks are run, since the setting doesn't affect reads or bulk
+// inserts.
+mod sync_durability_config {
+    #[cfg(feature = "bench-suite")]
+    define_bench!(bench_create_vertices_individually, {
+        use super::{Durability, RocksdbConfig, RocksdbDatastore};
+        use crate::util::generate_temporary_path;
+        let config = RocksdbConfig {
+            durability: Durability::Sync,
+            ..RocksdbConfig::default()
+        };
+        RocksdbDatastore::new_with_options(&generate_temporary_path(), config).unwrap()
+    });
+
+    #[cfg(feature = "bench-suite")]
+    define_bench!(bench_create_edges_individually, {
+        use super::{Durability, RocksdbConfig, RocksdbDatastore};
+        use crate::util::generate_temporary_path;
+        let config = RocksdbConfig {
+            durability: Durability::Sync,
+            ..RocksdbConfig::default()
+        };
+        RocksdbDatastore::new_with_options(&generate_temporary_path(), config).unwrap()
+    });
+}
+
+mod no_wal_durability_config {
+    #[cfg(feature = "bench-suite")]
+    define_bench!(bench_create_vertices_individually, {
+        use super::{Durability, RocksdbConfig, RocksdbDatastore};
+        use crate::util::generate_temporary_path;
+        let config = RocksdbConfig {
+            durability: Durability::NoWal,
+            ..RocksdbConfig::default()
+        };
+        RocksdbDatastore::new_with_options(&generate_temporary_path(), config).unwrap()
+    });
+
+    #[cfg(feature = "bench-suite")]
+    define_bench!(bench_create_edges_individually, {
+        use super::{Durability, RocksdbConfig, RocksdbDatastore};
+        use crate::util::generate_temporary_path;
+        let config = RocksdbConfig {
+            durability: Durability::NoWal,
+            ..RocksdbConfig::default()
+        };
+        RocksdbDatastore::new_with_options(&generate_temporary_path(), config).unwrap()
+    });
+}
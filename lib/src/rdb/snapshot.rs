@@ -0,0 +1,259 @@
+//! A portable, logical snapshot format for the rocksdb datastore.
+//!
+//! A snapshot starts with a magic number and a format version, followed by a
+//! series of records. Each record is a one byte kind, a big-endian `u32`
+//! payload length, and then the payload itself. Later versions of the
+//! format may only add record kinds, so readers accept snapshots with a
+//! newer version, and use the length prefix to skip over records they
+//! don't recognize.
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use chrono::offset::{TimeZone, Utc};
+use chrono::DateTime;
+use crate::errors::{Error, Result};
+use crate::models;
+use crate::traits::Datastore;
+use serde_json::Value as JsonValue;
+use std::io::{Cursor, Error as IoError, ErrorKind as IoErrorKind, Read, Write};
+use std::u32;
+use uuid::Uuid;
+
+const MAGIC: &[u8; 4] = b"IDBS";
+const VERSION: u8 = 1;
+
+// How many records to import per write
+pub const IMPORT_BATCH_SIZE: usize = 10_000;
+
+const VERTEX_RECORD: u8 = 0;
+const EDGE_RECORD: u8 = 1;
+const VERTEX_PROPERTY_RECORD: u8 = 2;
+const EDGE_PROPERTY_RECORD: u8 = 3;
+
+pub enum Record {
+    Vertex(models::Vertex),
+    Edge(models::Edge),
+    VertexProperty(Uuid, String, JsonValue),
+    EdgeProperty(models::EdgeKey, String, JsonValue),
+}
+
+pub fn write_header<W: Write>(writer: &mut W) -> Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_u8(VERSION)?;
+    Ok(())
+}
+
+pub fn write_record<W: Write>(writer: &mut W, record: &Record) -> Result<()> {
+    let mut payload = Vec::new();
+
+    let kind = match *record {
+        Record::Vertex(ref vertex) => {
+            write_uuid(&mut payload, vertex.id)?;
+            write_string(&mut payload, &vertex.t.0)?;
+            VERTEX_RECORD
+        }
+        Record::Edge(ref edge) => {
+            write_edge_key(&mut payload, &edge.key)?;
+            payload.write_i64::<BigEndian>(edge.created_datetime.timestamp())?;
+            payload.write_u32::<BigEndian>(edge.created_datetime.timestamp_subsec_nanos())?;
+            EDGE_RECORD
+        }
+        Record::VertexProperty(id, ref name, ref value) => {
+            write_uuid(&mut payload, id)?;
+            write_string(&mut payload, name)?;
+            write_string(&mut payload, &serde_json::to_string(value)?)?;
+            VERTEX_PROPERTY_RECORD
+        }
+        Record::EdgeProperty(ref key, ref name, ref value) => {
+            write_edge_key(&mut payload, key)?;
+            write_string(&mut payload, name)?;
+            write_string(&mut payload, &serde_json::to_string(value)?)?;
+            EDGE_PROPERTY_RECORD
+        }
+    };
+
+    writer.write_u8(kind)?;
+    writer.write_u32::<BigEndian>(payload.len() as u32)?;
+    writer.write_all(&payload)?;
+    Ok(())
+}
+
+fn write_uuid(payload: &mut Vec<u8>, id: Uuid) -> Result<()> {
+    payload.write_all(id.as_bytes())?;
+    Ok(())
+}
+
+fn write_string(payload: &mut Vec<u8>, s: &str) -> Result<()> {
+    debug_assert!(s.len() <= u32::MAX as usize);
+    payload.write_u32::<BigEndian>(s.len() as u32)?;
+    payload.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+fn write_edge_key(payload: &mut Vec<u8>, key: &models::EdgeKey) -> Result<()> {
+    write_uuid(payload, key.outbound_id)?;
+    write_string(payload, &key.t.0)?;
+    write_uuid(payload, key.inbound_id)
+}
+
+/// Reads the records out of a snapshot, after checking its header.
+pub struct RecordReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic).map_err(|_| Error::InvalidSnapshot)?;
+        let version = reader.read_u8().map_err(|_| Error::InvalidSnapshot)?;
+
+        if &magic != MAGIC || version < VERSION {
+            return Err(Error::InvalidSnapshot);
+        }
+
+        Ok(RecordReader { reader })
+    }
+
+    fn next_record(&mut self) -> Result<Option<Record>> {
+        loop {
+            let kind = match self.reader.read_u8() {
+                Ok(kind) => kind,
+                // The snapshot can only end between records
+                Err(ref err) if err.kind() == IoErrorKind::UnexpectedEof => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+
+            // The payload is read through `take` rather than into a buffer
+            // of the declared length, so a corrupt length can't make us
+            // allocate more than the snapshot actually holds
+            let len = self.reader.read_u32::<BigEndian>().map_err(truncated)?;
+            let mut payload = Vec::new();
+            self.reader.by_ref().take(u64::from(len)).read_to_end(&mut payload)?;
+
+            if payload.len() != len as usize {
+                return Err(Error::InvalidSnapshot);
+            }
+
+            let mut cursor = Cursor::new(payload);
+
+            let record = match kind {
+                VERTEX_RECORD => {
+                    let id = read_uuid(&mut cursor)?;
+                    let t = read_type(&mut cursor)?;
+                    Record::Vertex(models::Vertex::with_id(id, t))
+                }
+                EDGE_RECORD => {
+                    let key = read_edge_key(&mut cursor)?;
+                    let secs = cursor.read_i64::<BigEndian>().map_err(truncated)?;
+                    let nanos = cursor.read_u32::<BigEndian>().map_err(truncated)?;
+                    let created_datetime: DateTime<Utc> =
+                        Utc.timestamp_opt(secs, nanos).single().ok_or(Error::InvalidSnapshot)?;
+                    Record::Edge(models::Edge::new(key, created_datetime))
+                }
+                VERTEX_PROPERTY_RECORD => {
+                    let id = read_uuid(&mut cursor)?;
+                    let name = read_string(&mut cursor)?;
+                    let value = read_json(&mut cursor)?;
+                    Record::VertexProperty(id, name, value)
+                }
+                EDGE_PROPERTY_RECORD => {
+                    let key = read_edge_key(&mut cursor)?;
+                    let name = read_string(&mut cursor)?;
+                    let value = read_json(&mut cursor)?;
+                    Record::EdgeProperty(key, name, value)
+                }
+                // Skip over records from a later version of the format
+                _ => continue,
+            };
+
+            return Ok(Some(record));
+        }
+    }
+}
+
+impl<R: Read> Iterator for RecordReader<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+fn truncated(_: IoError) -> Error {
+    Error::InvalidSnapshot
+}
+
+fn read_uuid(cursor: &mut Cursor<Vec<u8>>) -> Result<Uuid> {
+    let mut buf = [0u8; 16];
+    cursor.read_exact(&mut buf).map_err(truncated)?;
+    Uuid::from_slice(&buf).map_err(|_| Error::InvalidSnapshot)
+}
+
+fn read_string(cursor: &mut Cursor<Vec<u8>>) -> Result<String> {
+    let len = cursor.read_u32::<BigEndian>().map_err(truncated)?;
+    let remaining = cursor.get_ref().len() as u64 - cursor.position();
+
+    if u64::from(len) > remaining {
+        return Err(Error::InvalidSnapshot);
+    }
+
+    let mut buf = vec![0u8; len as usize];
+    cursor.read_exact(&mut buf).map_err(truncated)?;
+    String::from_utf8(buf).map_err(|_| Error::InvalidSnapshot)
+}
+
+// Types were validated when they were first stored, possibly against a
+// custom pattern, so only the invariants every type holds are rechecked
+fn read_type(cursor: &mut Cursor<Vec<u8>>) -> Result<models::Type> {
+    let t = unsafe { models::Type::new_unchecked(read_string(cursor)?) };
+    t.validate().map_err(|_| Error::InvalidSnapshot)?;
+    Ok(t)
+}
+
+fn read_json(cursor: &mut Cursor<Vec<u8>>) -> Result<JsonValue> {
+    Ok(serde_json::from_str(&read_string(cursor)?)?)
+}
+
+fn read_edge_key(cursor: &mut Cursor<Vec<u8>>) -> Result<models::EdgeKey> {
+    let outbound_id = read_uuid(cursor)?;
+    let t = read_type(cursor)?;
+    let inbound_id = read_uuid(cursor)?;
+    Ok(models::EdgeKey::new(outbound_id, t, inbound_id))
+}
+
+/// Imports a snapshot written by `RocksdbDatastore::export_snapshot` into
+/// any datastore. Records are merged into whatever the datastore already
+/// has.
+///
+/// This goes through `Datastore::bulk_insert`, so edges get the current
+/// datetime rather than the one in the snapshot. Use
+/// `RocksdbDatastore::import_snapshot` to keep the original datetimes.
+///
+/// # Arguments
+/// * `datastore` - The datastore to import into.
+/// * `reader` - Where to read the snapshot from.
+///
+/// # Errors
+/// Returns `Error::InvalidSnapshot` if the snapshot is malformed or
+/// truncated. Records read before the problem was found are kept.
+pub fn import_snapshot<D: Datastore, R: Read>(datastore: &D, reader: R) -> Result<()> {
+    let mut records = RecordReader::new(reader)?;
+
+    loop {
+        let mut items = Vec::with_capacity(IMPORT_BATCH_SIZE);
+
+        for record in records.by_ref().take(IMPORT_BATCH_SIZE) {
+            items.push(match record? {
+                Record::Vertex(vertex) => models::BulkInsertItem::Vertex(vertex),
+                Record::Edge(edge) => models::BulkInsertItem::Edge(edge.key),
+                Record::VertexProperty(id, name, value) => models::BulkInsertItem::VertexProperty(id, name, value),
+                Record::EdgeProperty(key, name, value) => models::BulkInsertItem::EdgeProperty(key, name, value),
+            });
+        }
+
+        if items.is_empty() {
+            return Ok(());
+        }
+
+        datastore.bulk_insert(items.into_iter())?;
+    }
+}
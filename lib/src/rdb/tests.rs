@@ -11,3 +11,477 @@ fn should_repair() {
     // Now try to repair
     RocksdbDatastore::repair(&path, Some(1)).unwrap();
 }
+
+#[test]
+fn should_create_with_options() {
+    use super::{DBCompressionType, Durability, RocksdbConfig, RocksdbDatastore};
+    use crate::ids::SequentialUuidGenerator;
+    use crate::models::{SpecificVertexQuery, Type};
+    use crate::traits::{Datastore, Transaction};
+    use crate::util::generate_temporary_path;
+    use std::sync::Arc;
+
+    let config = RocksdbConfig {
+        max_open_files: Some(1),
+        block_cache_mb: Some(8),
+        compression: Some(DBCompressionType::None),
+        bulk_load_optimized: true,
+        id_generator: Some(Arc::new(SequentialUuidGenerator::new())),
+        dedup_edges: false,
+        durability: Durability::Sync,
+    };
+
+    let datastore = RocksdbDatastore::new_with_options(&generate_temporary_path(), config).unwrap();
+    let trans = datastore.transaction().unwrap();
+    let t = Type::new("test_vertex_type").unwrap();
+    let id = trans.create_vertex_from_type(t.clone()).unwrap();
+    let vertices = trans.get_vertices(SpecificVertexQuery::single(id)).unwrap();
+    assert_eq!(vertices.len(), 1);
+    assert_eq!(vertices[0].t, t);
+
+    let next_id = trans.create_vertex_from_type(t).unwrap();
+    assert!(next_id > id);
+}
+
+#[test]
+fn should_update_the_vertex_type_index_when_bulk_inserts_change_types() {
+    use super::RocksdbDatastore;
+    use crate::models::{BulkInsertItem, RangeVertexQuery, Type, Vertex};
+    use crate::traits::{Datastore, Transaction};
+    use crate::util::generate_temporary_path;
+    use std::u32;
+
+    let datastore = RocksdbDatastore::new(&generate_temporary_path(), Some(1), false).unwrap();
+    let old_t = Type::new("test_old_vertex_type").unwrap();
+    let middle_t = Type::new("test_middle_vertex_type").unwrap();
+    let new_t = Type::new("test_new_vertex_type").unwrap();
+    let vertex = Vertex::new(old_t.clone());
+    datastore
+        .bulk_insert(vec![BulkInsertItem::Vertex(vertex.clone())].into_iter())
+        .unwrap();
+
+    // The vertex's type changes twice within the same batch
+    let items = vec![
+        BulkInsertItem::Vertex(Vertex::with_id(vertex.id, middle_t.clone())),
+        BulkInsertItem::Vertex(Vertex::with_id(vertex.id, new_t.clone())),
+    ];
+    datastore.bulk_insert(items.into_iter()).unwrap();
+
+    let trans = datastore.transaction().unwrap();
+
+    for t in &[old_t, middle_t] {
+        let range = trans
+            .get_vertices(RangeVertexQuery::new(u32::MAX).t(t.clone()))
+            .unwrap();
+        assert_eq!(range.len(), 0);
+        assert_eq!(trans.count_vertices(Some(t)).unwrap(), 0);
+    }
+
+    let range = trans
+        .get_vertices(RangeVertexQuery::new(u32::MAX).t(new_t.clone()))
+        .unwrap();
+    assert_eq!(range.len(), 1);
+    assert_eq!(range[0].id, vertex.id);
+}
+
+#[test]
+fn should_backfill_the_vertex_type_index() {
+    use super::RocksdbDatastore;
+    use crate::models::{RangeVertexQuery, Type};
+    use crate::traits::{Datastore, Transaction};
+    use crate::util::generate_temporary_path;
+    use rocksdb::{Options, DB};
+    use std::u32;
+
+    let path = generate_temporary_path();
+    let t = Type::new("test_vertex_type").unwrap();
+
+    {
+        let datastore = RocksdbDatastore::new(&path, Some(1), false).unwrap();
+        let trans = datastore.transaction().unwrap();
+        trans.create_vertex_from_type(t.clone()).unwrap();
+        trans.create_vertex_from_type(t.clone()).unwrap();
+    }
+
+    // Simulate a database created before the index existed
+    {
+        let cf_names = DB::list_cf(&Options::default(), &path).unwrap();
+        let cf_names: Vec<&str> = cf_names.iter().map(|name| &name[..]).collect();
+        let mut db = DB::open_cf(&Options::default(), &path, &cf_names).unwrap();
+        db.drop_cf("vertex_types:v1").unwrap();
+    }
+
+    let datastore = RocksdbDatastore::new(&path, Some(1), false).unwrap();
+    let trans = datastore.transaction().unwrap();
+    let range = trans
+        .get_vertices(RangeVertexQuery::new(u32::MAX).t(t.clone()))
+        .unwrap();
+    assert_eq!(range.len(), 2);
+    assert_eq!(trans.count_vertices(Some(&t)).unwrap(), 2);
+}
+
+#[test]
+fn should_backfill_the_edge_time_index() {
+    use super::RocksdbDatastore;
+    use crate::models::{EdgeKey, Type};
+    use crate::traits::{Datastore, Transaction};
+    use crate::util::generate_temporary_path;
+    use rocksdb::{Options, DB};
+
+    let path = generate_temporary_path();
+    let vertex_t = Type::new("test_vertex_type").unwrap();
+    let edge_t = Type::new("test_edge_type").unwrap();
+
+    let key = {
+        let datastore = RocksdbDatastore::new(&path, Some(1), false).unwrap();
+        let trans = datastore.transaction().unwrap();
+        let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        let inbound_id = trans.create_vertex_from_type(vertex_t).unwrap();
+        let key = EdgeKey::new(outbound_id, edge_t, inbound_id);
+        trans.create_edge(&key).unwrap();
+        key
+    };
+
+    // Simulate a database created before the index existed
+    {
+        let cf_names = DB::list_cf(&Options::default(), &path).unwrap();
+        let cf_names: Vec<&str> = cf_names.iter().map(|name| &name[..]).collect();
+        let mut db = DB::open_cf(&Options::default(), &path, &cf_names).unwrap();
+        db.drop_cf("edge_times:v1").unwrap();
+    }
+
+    let datastore = RocksdbDatastore::new(&path, Some(1), false).unwrap();
+    let trans = datastore.transaction().unwrap();
+    let edges = trans.edges_by_time(None, 10).unwrap();
+    assert_eq!(edges.len(), 1);
+    assert_eq!(edges[0].key, key);
+}
+
+#[test]
+fn should_not_create_items_with_forged_types() {
+    use super::RocksdbDatastore;
+    use crate::errors::Error;
+    use crate::models::{EdgeKey, Type, Vertex};
+    use crate::traits::{Datastore, Transaction};
+    use crate::util::generate_temporary_path;
+
+    let datastore = RocksdbDatastore::new(&generate_temporary_path(), Some(1), false).unwrap();
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = Type::new("test_vertex_type").unwrap();
+
+    // A type this long would otherwise have its length byte truncated when
+    // it's encoded into a key
+    let forged_t = Type("a".repeat(256));
+
+    match trans.create_vertex(&Vertex::new(forged_t.clone())) {
+        Err(Error::Validation { .. }) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let inbound_id = trans.create_vertex_from_type(vertex_t).unwrap();
+    let key = EdgeKey::new(outbound_id, forged_t, inbound_id);
+
+    match trans.create_edge(&key) {
+        Err(Error::Validation { .. }) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    match trans.create_edges(&[key]) {
+        Err(Error::Validation { .. }) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    assert_eq!(trans.get_vertex_count().unwrap(), 2);
+    assert_eq!(trans.count_edges(None).unwrap(), 0);
+}
+
+#[test]
+fn should_round_trip_a_snapshot() {
+    use super::{import_snapshot, RocksdbDatastore};
+    use crate::errors::Error;
+    use crate::memory::MemoryDatastore;
+    use crate::models::{EdgeKey, SpecificEdgeQuery, Type};
+    use crate::traits::{Datastore, Transaction};
+    use crate::util::generate_temporary_path;
+    use std::io::Cursor;
+
+    let datastore = RocksdbDatastore::new(&generate_temporary_path(), Some(1), false).unwrap();
+    let vertex_t = Type::new("test_vertex_type").unwrap();
+    let edge_t = Type::new("test_edge_type").unwrap();
+
+    let key = {
+        let trans = datastore.transaction().unwrap();
+        let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        let inbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        trans.create_vertex_from_type(vertex_t).unwrap();
+        let key = EdgeKey::new(outbound_id, edge_t, inbound_id);
+        trans.create_edge(&key).unwrap();
+        key
+    };
+
+    let mut snapshot = Vec::new();
+    datastore.export_snapshot(&mut snapshot).unwrap();
+
+    let memory_datastore = MemoryDatastore::default();
+    import_snapshot(&memory_datastore, Cursor::new(&snapshot)).unwrap();
+    let trans = memory_datastore.transaction().unwrap();
+    assert_eq!(trans.get_vertex_count().unwrap(), 3);
+    assert_eq!(trans.count_edges(None).unwrap(), 1);
+    assert!(trans.has_edge(&key).unwrap());
+
+    // Importing into rocksdb keeps the edges' datetimes
+    let rocksdb_datastore = RocksdbDatastore::new(&generate_temporary_path(), Some(1), false).unwrap();
+    rocksdb_datastore.import_snapshot(Cursor::new(&snapshot)).unwrap();
+    let trans = rocksdb_datastore.transaction().unwrap();
+    assert_eq!(trans.get_vertex_count().unwrap(), 3);
+    let imported_edges = trans.get_edges(SpecificEdgeQuery::single(key.clone())).unwrap();
+    let original_edges = datastore
+        .transaction()
+        .unwrap()
+        .get_edges(SpecificEdgeQuery::single(key))
+        .unwrap();
+    assert_eq!(imported_edges.len(), 1);
+    assert!(imported_edges[0].eq_exact(&original_edges[0]));
+
+    let truncated = &snapshot[..snapshot.len() - 1];
+
+    match import_snapshot(&MemoryDatastore::default(), Cursor::new(truncated)) {
+        Err(Error::InvalidSnapshot) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn should_import_snapshots_with_types_made_with_custom_patterns() {
+    use super::{import_snapshot, RocksdbDatastore};
+    use crate::memory::MemoryDatastore;
+    use crate::models::{SpecificVertexQuery, Type};
+    use crate::traits::{Datastore, Transaction};
+    use crate::util::generate_temporary_path;
+    use regex::Regex;
+    use std::io::Cursor;
+
+    let datastore = RocksdbDatastore::new(&generate_temporary_path(), Some(1), false).unwrap();
+    let pattern = Regex::new("^[a-z.]+$").unwrap();
+    let t = Type::new_with_pattern("test.dotted.type", &pattern).unwrap();
+    let trans = datastore.transaction().unwrap();
+    let id = trans.create_vertex_from_type(t.clone()).unwrap();
+
+    let mut snapshot = Vec::new();
+    datastore.export_snapshot(&mut snapshot).unwrap();
+
+    let memory_datastore = MemoryDatastore::default();
+    import_snapshot(&memory_datastore, Cursor::new(&snapshot)).unwrap();
+    let trans = memory_datastore.transaction().unwrap();
+    let vertices = trans.get_vertices(SpecificVertexQuery::single(id)).unwrap();
+    assert_eq!(vertices.len(), 1);
+    assert_eq!(vertices[0].t, t);
+
+    let rocksdb_datastore = RocksdbDatastore::new(&generate_temporary_path(), Some(1), false).unwrap();
+    rocksdb_datastore.import_snapshot(Cursor::new(&snapshot)).unwrap();
+    let trans = rocksdb_datastore.transaction().unwrap();
+    let vertices = trans.get_vertices(SpecificVertexQuery::single(id)).unwrap();
+    assert_eq!(vertices.len(), 1);
+    assert_eq!(vertices[0].t, t);
+}
+
+#[test]
+fn should_skip_unknown_records_from_later_snapshot_versions() {
+    use super::import_snapshot;
+    use super::snapshot::{write_record, Record};
+    use crate::memory::MemoryDatastore;
+    use crate::models::{Type, Vertex};
+    use crate::traits::{Datastore, Transaction};
+    use std::io::Cursor;
+
+    // A version 2 header, followed by a record kind version 1 doesn't know
+    // about, and then a vertex
+    let mut snapshot = b"IDBS\x02".to_vec();
+    snapshot.extend_from_slice(&[255, 0, 0, 0, 3, 1, 2, 3]);
+    let vertex = Vertex::new(Type::new("test_vertex_type").unwrap());
+    write_record(&mut snapshot, &Record::Vertex(vertex)).unwrap();
+
+    let datastore = MemoryDatastore::default();
+    import_snapshot(&datastore, Cursor::new(&snapshot)).unwrap();
+    assert_eq!(datastore.transaction().unwrap().get_vertex_count().unwrap(), 1);
+}
+
+#[test]
+fn should_not_trust_snapshot_record_lengths() {
+    use super::import_snapshot;
+    use crate::errors::Error;
+    use crate::memory::MemoryDatastore;
+    use std::io::Cursor;
+
+    // A vertex record that claims to be 4 GiB long, but is cut short
+    let mut snapshot = b"IDBS\x01".to_vec();
+    snapshot.extend_from_slice(&[0, 255, 255, 255, 255, 1, 2, 3]);
+
+    match import_snapshot(&MemoryDatastore::default(), Cursor::new(&snapshot)) {
+        Err(Error::InvalidSnapshot) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn should_dedup_edges() {
+    use super::{RocksdbConfig, RocksdbDatastore};
+    use crate::models::{EdgeKey, SpecificEdgeQuery, Type};
+    use crate::traits::{Datastore, Transaction};
+    use crate::util::generate_temporary_path;
+
+    let config = RocksdbConfig {
+        dedup_edges: true,
+        ..RocksdbConfig::default()
+    };
+
+    let datastore = RocksdbDatastore::new_with_options(&generate_temporary_path(), config).unwrap();
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = Type::new("test_vertex_type").unwrap();
+    let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let inbound_id = trans.create_vertex_from_type(vertex_t).unwrap();
+    let key = EdgeKey::new(outbound_id, Type::new("test_edge_type").unwrap(), inbound_id);
+
+    assert!(trans.create_edge(&key).unwrap());
+    let original = trans.get_edges(SpecificEdgeQuery::single(key.clone())).unwrap();
+
+    assert!(!trans.create_edge(&key).unwrap());
+    assert_eq!(trans.create_edges(&[key.clone()]).unwrap(), vec![false]);
+
+    let edges = trans.get_edges(SpecificEdgeQuery::single(key)).unwrap();
+    assert_eq!(edges.len(), 1);
+    assert!(edges[0].eq_exact(&original[0]));
+}
+
+#[test]
+fn should_write_with_each_durability() {
+    use super::{Durability, RocksdbConfig, RocksdbDatastore};
+    use crate::models::{EdgeKey, Type};
+    use crate::traits::{Datastore, Transaction};
+    use crate::util::generate_temporary_path;
+
+    for &durability in &[Durability::Sync, Durability::Async, Durability::NoWal] {
+        let path = generate_temporary_path();
+        let t = Type::new("test_vertex_type").unwrap();
+
+        let config = RocksdbConfig {
+            durability,
+            ..RocksdbConfig::default()
+        };
+
+        {
+            let datastore = RocksdbDatastore::new_with_options(&path, config.clone()).unwrap();
+            let trans = datastore.transaction().unwrap();
+            let outbound_id = trans.create_vertex_from_type(t.clone()).unwrap();
+            let inbound_id = trans.create_vertex_from_type(t.clone()).unwrap();
+            let key = EdgeKey::new(outbound_id, Type::new("test_edge_type").unwrap(), inbound_id);
+            assert!(trans.create_edge(&key).unwrap());
+        }
+
+        // Memtables are flushed when the database is closed, so even writes
+        // that skipped the write-ahead log should still be there
+        let datastore = RocksdbDatastore::new_with_options(&path, config).unwrap();
+        let trans = datastore.transaction().unwrap();
+        assert_eq!(trans.get_vertex_count().unwrap(), 2);
+        assert_eq!(trans.count_edges(None).unwrap(), 1);
+    }
+}
+
+#[test]
+fn should_get_column_stats() {
+    use super::RocksdbDatastore;
+    use crate::models::Type;
+    use crate::traits::{Datastore, Transaction};
+    use crate::util::generate_temporary_path;
+
+    let datastore = RocksdbDatastore::new(&generate_temporary_path(), Some(1), false).unwrap();
+    let trans = datastore.transaction().unwrap();
+    let t = Type::new("test_vertex_type").unwrap();
+
+    for _ in 0..10 {
+        trans.create_vertex_from_type(t.clone()).unwrap();
+    }
+
+    // Compacting flushes the memtables, so there are SST files to report on
+    datastore.compact().unwrap();
+    let stats = datastore.stats().unwrap();
+    assert_eq!(stats.vertex_count, 10);
+    assert_eq!(stats.columns.len(), 8);
+
+    let vertices = stats.columns.iter().find(|c| c.name == "vertices:v1").unwrap();
+    assert!(vertices.approximate_entries > 0);
+    assert!(vertices.sst_file_count.unwrap() > 0);
+    assert!(!vertices.level_bytes.is_empty());
+}
+
+#[test]
+fn should_page_through_edges_with_the_same_datetime() {
+    use super::managers::EdgeManager;
+    use super::RocksdbDatastore;
+    use crate::models::{Edge, Type};
+    use crate::traits::{Datastore, Transaction};
+    use crate::util::generate_temporary_path;
+    use chrono::offset::Utc;
+    use chrono::DateTime;
+    use rocksdb::{Options, WriteBatch, DB};
+    use std::sync::Arc;
+
+    let path = generate_temporary_path();
+    let vertex_t = Type::new("test_vertex_type").unwrap();
+    let edge_t = Type::new("test_edge_type").unwrap();
+
+    let (outbound_id, inbound_ids) = {
+        let datastore = RocksdbDatastore::new(&path, Some(1), false).unwrap();
+        let trans = datastore.transaction().unwrap();
+        let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        let inbound_ids: Vec<_> = (0..6)
+            .map(|_| trans.create_vertex_from_type(vertex_t.clone()).unwrap())
+            .collect();
+        (outbound_id, inbound_ids)
+    };
+
+    // Edges created at the same time can only be set up by writing them
+    // directly
+    {
+        let cf_names = DB::list_cf(&Options::default(), &path).unwrap();
+        let cf_names: Vec<&str> = cf_names.iter().map(|name| &name[..]).collect();
+        let db = Arc::new(DB::open_cf(&Options::default(), &path, &cf_names).unwrap());
+        let edge_manager = EdgeManager::new(db.clone());
+        let tied_datetime: DateTime<Utc> = "2019-01-01T00:00:00Z".parse().unwrap();
+        let later_datetime: DateTime<Utc> = "2019-01-02T00:00:00Z".parse().unwrap();
+        let mut batch = WriteBatch::default();
+
+        for (i, inbound_id) in inbound_ids.iter().enumerate() {
+            let datetime = if i < 5 { tied_datetime } else { later_datetime };
+            edge_manager
+                .set(&mut batch, outbound_id, &edge_t, *inbound_id, datetime)
+                .unwrap();
+        }
+
+        db.write(batch).unwrap();
+    }
+
+    let datastore = RocksdbDatastore::new(&path, Some(1), false).unwrap();
+    let trans = datastore.transaction().unwrap();
+    let all = trans.edges_by_time(None, 10).unwrap();
+    assert_eq!(all.len(), 6);
+
+    // Pages end partway through the edges with the same datetime
+    let mut paged = Vec::new();
+    let mut after = None;
+
+    loop {
+        let page = trans.edges_by_time(after, 2).unwrap();
+
+        match page.last() {
+            Some(last) => after = Some((last.created_datetime, last.key.clone())),
+            None => break,
+        }
+
+        paged.extend(page);
+    }
+
+    let keys = |edges: Vec<Edge>| edges.into_iter().map(|edge| edge.key).collect::<Vec<_>>();
+    assert_eq!(keys(paged), keys(all));
+}
@@ -0,0 +1,107 @@
+//! Full scans of a datastore, for datastores that don't have a faster way
+//! of walking all of their records.
+
+use crate::diff::{get_sorted_outbound_edges, get_vertex_page};
+use crate::errors::Result;
+use crate::models;
+use crate::traits::Datastore;
+use std::vec;
+use uuid::Uuid;
+
+/// Iterates over all of the vertices in a datastore, a page at a time in id
+/// order. See `Datastore::vertices_iter`.
+pub(crate) struct VertexScan<'a, D: Datastore> {
+    datastore: &'a D,
+    page: vec::IntoIter<models::Vertex>,
+    after: Option<Uuid>,
+    done: bool,
+}
+
+impl<'a, D: Datastore> VertexScan<'a, D> {
+    pub(crate) fn new(datastore: &'a D) -> Self {
+        VertexScan {
+            datastore,
+            page: Vec::new().into_iter(),
+            after: None,
+            done: false,
+        }
+    }
+
+    fn next_page(&mut self) -> Result<Vec<models::Vertex>> {
+        let trans = self.datastore.transaction()?;
+        let (vertices, full) = get_vertex_page(&trans, self.after)?;
+        self.done = !full;
+
+        if let Some(last) = vertices.last() {
+            self.after = Some(last.id);
+        }
+
+        Ok(vertices)
+    }
+}
+
+impl<'a, D: Datastore> Iterator for VertexScan<'a, D> {
+    type Item = Result<models::Vertex>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(vertex) = self.page.next() {
+                return Some(Ok(vertex));
+            } else if self.done {
+                return None;
+            }
+
+            match self.next_page() {
+                Ok(vertices) => self.page = vertices.into_iter(),
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Iterates over all of the edges in a datastore, grouped by their outbound
+/// vertices in id order. See `Datastore::edges_iter`.
+pub(crate) struct EdgeScan<'a, D: Datastore> {
+    vertices: VertexScan<'a, D>,
+    page: vec::IntoIter<models::Edge>,
+}
+
+impl<'a, D: Datastore> EdgeScan<'a, D> {
+    pub(crate) fn new(datastore: &'a D) -> Self {
+        EdgeScan {
+            vertices: VertexScan::new(datastore),
+            page: Vec::new().into_iter(),
+        }
+    }
+
+    fn next_page(&mut self) -> Result<Vec<models::Edge>> {
+        let vertices = self.vertices.next_page()?;
+        let trans = self.vertices.datastore.transaction()?;
+        get_sorted_outbound_edges(&trans, &vertices)
+    }
+}
+
+impl<'a, D: Datastore> Iterator for EdgeScan<'a, D> {
+    type Item = Result<models::Edge>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(edge) = self.page.next() {
+                return Some(Ok(edge));
+            } else if self.vertices.done {
+                return None;
+            }
+
+            match self.next_page() {
+                Ok(edges) => self.page = edges.into_iter(),
+                Err(err) => {
+                    self.vertices.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
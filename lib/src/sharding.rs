@@ -0,0 +1,867 @@
+//! Spreading a graph across several datastores.
+//!
+//! A `ShardedDatastore` hashes each vertex id to one of several underlying
+//! datastores, its home shard, so that writes are spread across them. Edges
+//! live in the home shard of their outbound vertex. Lookups by vertex id or
+//! outbound edge go to a single shard; anything else, such as inbound edges
+//! or range queries, fans out to every shard.
+//!
+//! The underlying datastores only create edges between vertices they have.
+//! So when an edge's inbound vertex lives in another shard, a stub of it
+//! with the `STUB_VERTEX_TYPE` type is created in the edge's shard. Stubs
+//! are never returned or counted, but aren't removed when the edges that
+//! needed them are deleted, only when their vertex is.
+
+use chrono::offset::Utc;
+use chrono::DateTime;
+use crate::errors::{Result, ValidationError};
+use crate::models;
+use crate::models::{EdgeQueryExt, VertexQueryExt};
+use crate::stats::DatastoreStats;
+use crate::traits::{Datastore, Transaction};
+use crate::util::next_uuid;
+use serde_json::value::Value as JsonValue;
+use std::collections::{HashMap, HashSet};
+use std::iter::Peekable;
+use std::u32;
+use uuid::Uuid;
+
+/// The type of stub vertices. Creating vertices of this type through a
+/// `ShardedDatastore` fails with a validation error.
+pub const STUB_VERTEX_TYPE: &str = "indradb-shard-stub";
+
+fn stub_vertex_type() -> models::Type {
+    models::Type::new(STUB_VERTEX_TYPE).unwrap()
+}
+
+// Hashes the vertex id with 64-bit FNV-1a, and then picks a shard with jump
+// consistent hashing (Lamping & Veach). Both are stable across platforms and
+// releases, which matters since vertices are persisted in their shard. Jump
+// hashing also moves as few vertices as possible when a shard is added.
+fn shard_index(id: Uuid, shard_count: usize) -> usize {
+    let mut key = id.as_bytes().iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+
+    let mut bucket: i64 = -1;
+    let mut next: i64 = 0;
+
+    while next < shard_count as i64 {
+        bucket = next;
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        next = ((bucket + 1) as f64 * (f64::from(1u32 << 31) / ((key >> 33) + 1) as f64)) as i64;
+    }
+
+    bucket as usize
+}
+
+// Lazily merges iterators that are each sorted by id into a single sorted
+// iterator, so that full scans come out in the same order as they would
+// from a single datastore. Errors are passed along as soon as they're seen.
+struct MergeById<'a, T> {
+    iters: Vec<Peekable<Box<dyn Iterator<Item = Result<T>> + 'a>>>,
+    id: fn(&T) -> Uuid,
+}
+
+impl<'a, T> MergeById<'a, T> {
+    fn new(iters: Vec<Box<dyn Iterator<Item = Result<T>> + 'a>>, id: fn(&T) -> Uuid) -> Self {
+        Self {
+            iters: iters.into_iter().map(Iterator::peekable).collect(),
+            id,
+        }
+    }
+}
+
+impl<'a, T> Iterator for MergeById<'a, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut next: Option<(usize, Uuid)> = None;
+
+        for (index, iter) in self.iters.iter_mut().enumerate() {
+            match iter.peek() {
+                Some(Ok(item)) => {
+                    let id = (self.id)(item);
+
+                    if next.map_or(true, |(_, next_id)| id < next_id) {
+                        next = Some((index, id));
+                    }
+                }
+                Some(Err(_)) => return iter.next(),
+                None => (),
+            }
+        }
+
+        let (index, _) = next?;
+        self.iters[index].next()
+    }
+}
+
+/// A datastore that spreads vertices and edges across several underlying
+/// datastores.
+#[derive(Debug)]
+pub struct ShardedDatastore<D: Datastore> {
+    shards: Vec<D>,
+}
+
+impl<D: Datastore> ShardedDatastore<D> {
+    /// Creates a sharded datastore.
+    ///
+    /// # Arguments
+    /// * `shards` - The underlying datastores. They should be empty, or
+    ///   have been used with a sharded datastore with the same shards in
+    ///   the same order before.
+    ///
+    /// # Panics
+    /// Panics if there are no shards.
+    pub fn new(shards: Vec<D>) -> Self {
+        assert!(!shards.is_empty(), "a sharded datastore needs at least one shard");
+        Self { shards }
+    }
+
+    /// Gets the underlying datastores.
+    pub fn shards(&self) -> &[D] {
+        &self.shards
+    }
+
+    /// Gets the index of the shard that a vertex lives in.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the vertex.
+    pub fn shard_for(&self, id: Uuid) -> usize {
+        shard_index(id, self.shards.len())
+    }
+
+    // Finds which of the vertices of edges that span shards exist, either
+    // in their home shard or among the items being bulk inserted. Fails if
+    // any of the items is a stub vertex.
+    fn existing_edge_vertex_ids(&self, items: &[models::BulkInsertItem]) -> Result<HashSet<Uuid>> {
+        let mut existing_ids = HashSet::new();
+        let mut ids_to_check: Vec<Vec<Uuid>> = self.shards.iter().map(|_| Vec::new()).collect();
+
+        for item in items {
+            if let models::BulkInsertItem::Vertex(ref vertex) = *item {
+                if vertex.t.0 == STUB_VERTEX_TYPE {
+                    return Err(ValidationError::InvalidValue.into());
+                }
+
+                existing_ids.insert(vertex.id);
+            }
+        }
+
+        for item in items {
+            if let models::BulkInsertItem::Edge(ref key) = *item {
+                if self.shard_for(key.outbound_id) != self.shard_for(key.inbound_id) {
+                    for id in &[key.outbound_id, key.inbound_id] {
+                        if !existing_ids.contains(id) {
+                            ids_to_check[self.shard_for(*id)].push(*id);
+                        }
+                    }
+                }
+            }
+        }
+
+        for (shard, ids) in self.shards.iter().zip(ids_to_check) {
+            if !ids.is_empty() {
+                let exist = shard.transaction()?.which_vertices_exist(&ids)?;
+
+                for (id, exists) in ids.into_iter().zip(exist) {
+                    if exists {
+                        existing_ids.insert(id);
+                    }
+                }
+            }
+        }
+
+        Ok(existing_ids)
+    }
+}
+
+impl<D: Datastore> Datastore for ShardedDatastore<D> {
+    type Trans = ShardedTransaction<D::Trans>;
+
+    fn transaction(&self) -> Result<Self::Trans> {
+        let shards: Result<Vec<D::Trans>> = self.shards.iter().map(|shard| shard.transaction()).collect();
+        Ok(ShardedTransaction { shards: shards? })
+    }
+
+    // As with `ShardedTransaction`, stub vertices can't be inserted
+    // directly, and edges that span shards are only inserted, along with a
+    // stub for their inbound vertex, if both of their vertices exist.
+    fn bulk_insert<I>(&self, items: I) -> Result<()>
+    where
+        I: Iterator<Item = models::BulkInsertItem>,
+    {
+        let items: Vec<models::BulkInsertItem> = items.collect();
+        let existing_ids = self.existing_edge_vertex_ids(&items)?;
+        let mut skipped_keys = HashSet::new();
+        let mut shard_items: Vec<Vec<models::BulkInsertItem>> = self.shards.iter().map(|_| Vec::new()).collect();
+
+        for item in items {
+            let index = match item {
+                models::BulkInsertItem::Vertex(ref vertex) => self.shard_for(vertex.id),
+                models::BulkInsertItem::Edge(ref key) => {
+                    let index = self.shard_for(key.outbound_id);
+
+                    if index != self.shard_for(key.inbound_id) {
+                        if !existing_ids.contains(&key.outbound_id) || !existing_ids.contains(&key.inbound_id) {
+                            skipped_keys.insert(key.clone());
+                            continue;
+                        }
+
+                        let stub = models::Vertex::with_id(key.inbound_id, stub_vertex_type());
+                        shard_items[index].push(models::BulkInsertItem::Vertex(stub));
+                    }
+
+                    index
+                }
+                models::BulkInsertItem::VertexProperty(id, _, _) => self.shard_for(id),
+                models::BulkInsertItem::EdgeProperty(ref key, _, _) => {
+                    if skipped_keys.contains(key) {
+                        continue;
+                    }
+
+                    self.shard_for(key.outbound_id)
+                }
+            };
+
+            shard_items[index].push(item);
+        }
+
+        for (shard, items) in self.shards.iter().zip(shard_items) {
+            shard.bulk_insert(items.into_iter())?;
+        }
+
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        for shard in &self.shards {
+            shard.clear()?;
+        }
+
+        Ok(())
+    }
+
+    fn compact(&self) -> Result<()> {
+        for shard in &self.shards {
+            shard.compact()?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<()> {
+        for shard in &self.shards {
+            shard.flush()?;
+        }
+
+        Ok(())
+    }
+
+    // Counts come from a transaction so that stubs aren't included, while
+    // each shard's columns are reported separately, prefixed by the shard's
+    // index
+    fn stats(&self) -> Result<DatastoreStats> {
+        let trans = self.transaction()?;
+        let mut columns = Vec::new();
+
+        for (index, shard) in self.shards.iter().enumerate() {
+            for mut column in shard.stats()?.columns {
+                column.name = format!("{}/{}", index, column.name);
+                columns.push(column);
+            }
+        }
+
+        Ok(DatastoreStats {
+            vertex_count: trans.get_vertex_count()?,
+            edge_count: trans.count_edges(None)?,
+            columns,
+        })
+    }
+
+    fn vertices_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Vertex>> + 'a> {
+        let iters = self.shards.iter().enumerate().map(move |(index, shard)| {
+            let iter = shard.vertices_iter().filter(move |vertex| match vertex {
+                Ok(vertex) => self.shard_for(vertex.id) == index,
+                Err(_) => true,
+            });
+
+            Box::new(iter) as Box<dyn Iterator<Item = Result<models::Vertex>> + 'a>
+        });
+
+        Box::new(MergeById::new(iters.collect(), |vertex| vertex.id))
+    }
+
+    fn edges_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Edge>> + 'a> {
+        let iters = self.shards.iter().map(|shard| shard.edges_iter()).collect();
+        Box::new(MergeById::new(iters, |edge| edge.key.outbound_id))
+    }
+}
+
+/// A transaction against every shard of a sharded datastore.
+#[derive(Debug)]
+pub struct ShardedTransaction<T: Transaction> {
+    shards: Vec<T>,
+}
+
+impl<T: Transaction> ShardedTransaction<T> {
+    fn shard_for(&self, id: Uuid) -> usize {
+        shard_index(id, self.shards.len())
+    }
+
+    fn home(&self, id: Uuid) -> &T {
+        &self.shards[self.shard_for(id)]
+    }
+
+    // Splits vertex ids up by the shard they live in
+    fn group_ids<I: IntoIterator<Item = Uuid>>(&self, ids: I) -> Vec<Vec<Uuid>> {
+        let mut groups: Vec<Vec<Uuid>> = self.shards.iter().map(|_| Vec::new()).collect();
+
+        for id in ids {
+            groups[self.shard_for(id)].push(id);
+        }
+
+        groups
+    }
+
+    // Splits edge keys up by the shard they live in
+    fn group_keys<I: IntoIterator<Item = models::EdgeKey>>(&self, keys: I) -> Vec<Vec<models::EdgeKey>> {
+        let mut groups: Vec<Vec<models::EdgeKey>> = self.shards.iter().map(|_| Vec::new()).collect();
+
+        for key in keys {
+            groups[self.shard_for(key.outbound_id)].push(key);
+        }
+
+        groups
+    }
+
+    fn vertex_ids<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<Vec<Uuid>> {
+        Ok(self.get_vertices(q)?.into_iter().map(|vertex| vertex.id).collect())
+    }
+
+    fn edge_keys<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<Vec<models::EdgeKey>> {
+        Ok(self.get_edges(q)?.into_iter().map(|edge| edge.key).collect())
+    }
+
+    // Gets vertices by id, in the order given, skipping missing ones
+    fn get_specific_vertices(&self, ids: Vec<Uuid>) -> Result<Vec<models::Vertex>> {
+        let mut found = HashMap::new();
+
+        for (shard, ids) in self.shards.iter().zip(self.group_ids(ids.iter().cloned())) {
+            if !ids.is_empty() {
+                for vertex in shard.get_vertices(models::SpecificVertexQuery::new(ids))? {
+                    found.insert(vertex.id, vertex);
+                }
+            }
+        }
+
+        Ok(ids.into_iter().filter_map(|id| found.get(&id).cloned()).collect())
+    }
+
+    // Gets a range of the vertices that live in one shard. The shard's stubs
+    // count towards the range's limit, so this keeps paging through the
+    // shard until enough vertices are found.
+    fn get_shard_range(&self, index: usize, mut range: models::RangeVertexQuery) -> Result<Vec<models::Vertex>> {
+        let limit = range.limit as usize;
+        let mut vertices = Vec::new();
+
+        loop {
+            let page = self.shards[index].get_vertices(range.clone())?;
+            let is_last_page = page.len() < limit;
+
+            let last_id = match page.last() {
+                Some(vertex) => vertex.id,
+                None => break,
+            };
+
+            vertices.extend(page.into_iter().filter(|vertex| self.shard_for(vertex.id) == index));
+
+            if is_last_page || vertices.len() >= limit {
+                break;
+            }
+
+            range.start_id = match next_uuid(last_id) {
+                Ok(start_id) => Some(start_id),
+                Err(_) => break,
+            };
+        }
+
+        vertices.truncate(limit);
+        Ok(vertices)
+    }
+}
+
+impl<T: Transaction> Transaction for ShardedTransaction<T> {
+    fn create_vertex(&self, vertex: &models::Vertex) -> Result<bool> {
+        if vertex.t.0 == STUB_VERTEX_TYPE {
+            return Err(ValidationError::InvalidValue.into());
+        }
+
+        self.home(vertex.id).create_vertex(vertex)
+    }
+
+    fn get_or_create_vertex(&self, id: Uuid, t: models::Type) -> Result<(models::Vertex, bool)> {
+        if t.0 == STUB_VERTEX_TYPE {
+            return Err(ValidationError::InvalidValue.into());
+        }
+
+        self.home(id).get_or_create_vertex(id, t)
+    }
+
+    fn get_vertices<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<Vec<models::Vertex>> {
+        match q.into() {
+            models::VertexQuery::Range(range) => {
+                let limit = range.limit as usize;
+                let mut vertices = Vec::new();
+
+                for index in 0..self.shards.len() {
+                    vertices.extend(self.get_shard_range(index, range.clone())?);
+                }
+
+                vertices.sort_by_key(|vertex| vertex.id);
+                vertices.truncate(limit);
+                Ok(vertices)
+            }
+            models::VertexQuery::Specific(specific) => self.get_specific_vertices(specific.ids),
+            models::VertexQuery::Pipe(pipe) => {
+                let edges = self.get_edges(*pipe.inner)?;
+                let direction = pipe.direction;
+                let t = pipe.t;
+
+                let ids = edges.into_iter().map(|edge| match direction {
+                    models::EdgeDirection::Outbound => edge.key.outbound_id,
+                    models::EdgeDirection::Inbound => edge.key.inbound_id,
+                });
+
+                let vertices = self
+                    .get_specific_vertices(ids.collect())?
+                    .into_iter()
+                    .filter(|vertex| t.as_ref().map_or(true, |t| vertex.t == *t))
+                    .take(pipe.limit as usize)
+                    .collect();
+
+                Ok(vertices)
+            }
+        }
+    }
+
+    fn delete_vertices<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<()> {
+        let ids = self.vertex_ids(q)?;
+
+        // Deleting from every shard also deletes the vertices' stubs, along
+        // with the edges pointing to them
+        for shard in &self.shards {
+            shard.delete_vertices(models::SpecificVertexQuery::new(ids.clone()))?;
+        }
+
+        Ok(())
+    }
+
+    fn delete_vertices_by_type(&self, t: models::Type) -> Result<u64> {
+        let ids = self.vertex_ids(models::RangeVertexQuery::new(u32::MAX).t(t))?;
+        let count = ids.len() as u64;
+        self.delete_vertices(models::SpecificVertexQuery::new(ids))?;
+        Ok(count)
+    }
+
+    fn get_vertex_count(&self) -> Result<u64> {
+        self.count_vertices(None)
+    }
+
+    fn count_vertices(&self, t: Option<&models::Type>) -> Result<u64> {
+        let stub_t = stub_vertex_type();
+        let mut count = 0;
+
+        for shard in &self.shards {
+            count += match t {
+                Some(t) => shard.count_vertices(Some(t))?,
+                None => shard.count_vertices(None)? - shard.count_vertices(Some(&stub_t))?,
+            };
+        }
+
+        Ok(count)
+    }
+
+    fn which_vertices_exist(&self, ids: &[Uuid]) -> Result<Vec<bool>> {
+        let mut results = vec![false; ids.len()];
+        let mut positions: Vec<Vec<usize>> = self.shards.iter().map(|_| Vec::new()).collect();
+
+        for (position, id) in ids.iter().enumerate() {
+            positions[self.shard_for(*id)].push(position);
+        }
+
+        for (shard, positions) in self.shards.iter().zip(positions) {
+            let shard_ids: Vec<Uuid> = positions.iter().map(|position| ids[*position]).collect();
+
+            for (position, exists) in positions.into_iter().zip(shard.which_vertices_exist(&shard_ids)?) {
+                results[position] = exists;
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn create_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        let index = self.shard_for(key.outbound_id);
+
+        if index != self.shard_for(key.inbound_id) {
+            // Only stub the inbound vertex if the edge can actually be
+            // created
+            if !self.home(key.inbound_id).which_vertices_exist(&[key.inbound_id])?[0] {
+                return Ok(false);
+            }
+
+            if !self.shards[index].which_vertices_exist(&[key.outbound_id])?[0] {
+                return Ok(false);
+            }
+
+            let stub = models::Vertex::with_id(key.inbound_id, stub_vertex_type());
+            self.shards[index].create_vertex(&stub)?;
+        }
+
+        self.shards[index].create_edge(key)
+    }
+
+    fn get_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<Vec<models::Edge>> {
+        match q.into() {
+            models::EdgeQuery::Specific(specific) => {
+                let mut found = HashMap::new();
+
+                for (shard, keys) in self.shards.iter().zip(self.group_keys(specific.keys.iter().cloned())) {
+                    if !keys.is_empty() {
+                        for edge in shard.get_edges(models::SpecificEdgeQuery::new(keys))? {
+                            found.insert(edge.key.clone(), edge);
+                        }
+                    }
+                }
+
+                Ok(specific
+                    .keys
+                    .into_iter()
+                    .filter_map(|key| found.get(&key).cloned())
+                    .collect())
+            }
+            models::EdgeQuery::Pipe(pipe) => {
+                let ids = self.vertex_ids(*pipe.inner.clone())?;
+                let limit = pipe.limit as usize;
+
+                // Outbound edges live with their vertex. Inbound ones could
+                // be anywhere, but their shard has a stub for the vertex.
+                let shard_ids = if pipe.direction == models::EdgeDirection::Outbound && !pipe.undirected {
+                    self.group_ids(ids)
+                } else {
+                    self.shards.iter().map(|_| ids.clone()).collect()
+                };
+
+                let mut edges = Vec::new();
+
+                for (shard, ids) in self.shards.iter().zip(shard_ids) {
+                    // Sorted results can't stop early, since a later shard
+                    // could have edges that sort first
+                    if ids.is_empty() || (pipe.sort.is_none() && edges.len() >= limit) {
+                        continue;
+                    }
+
+                    let mut shard_pipe = pipe.clone();
+                    shard_pipe.inner = Box::new(models::SpecificVertexQuery::new(ids).into());
+                    edges.extend(shard.get_edges(shard_pipe)?);
+                }
+
+                if let Some(sort) = pipe.sort {
+                    edges.sort_by(|first, second| {
+                        sort.order(match sort {
+                            models::SortKey::UpdatedAt { .. } => first
+                                .created_datetime
+                                .cmp(&second.created_datetime)
+                                .then_with(|| first.key.cmp(&second.key)),
+                            models::SortKey::Id { .. } => first.key.cmp(&second.key),
+                        })
+                    });
+                }
+
+                edges.truncate(limit);
+                Ok(edges)
+            }
+        }
+    }
+
+    fn has_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        self.home(key.outbound_id).has_edge(key)
+    }
+
+    fn delete_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<()> {
+        let keys = self.edge_keys(q)?;
+
+        for (shard, keys) in self.shards.iter().zip(self.group_keys(keys)) {
+            if !keys.is_empty() {
+                shard.delete_edges(models::SpecificEdgeQuery::new(keys))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_edge_count(&self, id: Uuid, t: Option<&models::Type>, direction: models::EdgeDirection) -> Result<u64> {
+        if direction == models::EdgeDirection::Outbound {
+            return self.home(id).get_edge_count(id, t, direction);
+        }
+
+        let mut count = 0;
+
+        for shard in &self.shards {
+            count += shard.get_edge_count(id, t, direction)?;
+        }
+
+        Ok(count)
+    }
+
+    fn count_edges(&self, t: Option<&models::Type>) -> Result<u64> {
+        let mut count = 0;
+
+        for shard in &self.shards {
+            count += shard.count_edges(t)?;
+        }
+
+        Ok(count)
+    }
+
+    fn edges_by_time(
+        &self,
+        after: Option<(DateTime<Utc>, models::EdgeKey)>,
+        limit: usize,
+    ) -> Result<Vec<models::Edge>> {
+        let mut edges = Vec::new();
+
+        for shard in &self.shards {
+            edges.extend(shard.edges_by_time(after.clone(), limit)?);
+        }
+
+        edges.sort_by_key(|edge| (edge.created_datetime, edge.key.clone()));
+        edges.truncate(limit);
+        Ok(edges)
+    }
+
+    fn rename_edge_type(&self, from: models::Type, to: models::Type) -> Result<u64> {
+        let mut count = 0;
+
+        for shard in &self.shards {
+            count += shard.rename_edge_type(from.clone(), to.clone())?;
+        }
+
+        Ok(count)
+    }
+
+    fn get_vertex_properties(&self, q: models::VertexPropertyQuery) -> Result<Vec<models::VertexProperty>> {
+        let mut properties = Vec::new();
+
+        for (shard, ids) in self.shards.iter().zip(self.group_ids(self.vertex_ids(q.inner)?)) {
+            if !ids.is_empty() {
+                let shard_q = models::SpecificVertexQuery::new(ids).property(q.name.clone());
+                properties.extend(shard.get_vertex_properties(shard_q)?);
+            }
+        }
+
+        Ok(properties)
+    }
+
+    fn set_vertex_properties(&self, q: models::VertexPropertyQuery, value: &JsonValue) -> Result<()> {
+        for (shard, ids) in self.shards.iter().zip(self.group_ids(self.vertex_ids(q.inner)?)) {
+            if !ids.is_empty() {
+                let shard_q = models::SpecificVertexQuery::new(ids).property(q.name.clone());
+                shard.set_vertex_properties(shard_q, value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn delete_vertex_properties(&self, q: models::VertexPropertyQuery) -> Result<()> {
+        for (shard, ids) in self.shards.iter().zip(self.group_ids(self.vertex_ids(q.inner)?)) {
+            if !ids.is_empty() {
+                let shard_q = models::SpecificVertexQuery::new(ids).property(q.name.clone());
+                shard.delete_vertex_properties(shard_q)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_edge_properties(&self, q: models::EdgePropertyQuery) -> Result<Vec<models::EdgeProperty>> {
+        let mut properties = Vec::new();
+
+        for (shard, keys) in self.shards.iter().zip(self.group_keys(self.edge_keys(q.inner)?)) {
+            if !keys.is_empty() {
+                let shard_q = models::SpecificEdgeQuery::new(keys).property(q.name.clone());
+                properties.extend(shard.get_edge_properties(shard_q)?);
+            }
+        }
+
+        Ok(properties)
+    }
+
+    fn set_edge_properties(&self, q: models::EdgePropertyQuery, value: &JsonValue) -> Result<()> {
+        for (shard, keys) in self.shards.iter().zip(self.group_keys(self.edge_keys(q.inner)?)) {
+            if !keys.is_empty() {
+                let shard_q = models::SpecificEdgeQuery::new(keys).property(q.name.clone());
+                shard.set_edge_properties(shard_q, value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn delete_edge_properties(&self, q: models::EdgePropertyQuery) -> Result<()> {
+        for (shard, keys) in self.shards.iter().zip(self.group_keys(self.edge_keys(q.inner)?)) {
+            if !keys.is_empty() {
+                let shard_q = models::SpecificEdgeQuery::new(keys).property(q.name.clone());
+                shard.delete_edge_properties(shard_q)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_edge_property_names(&self, key: &models::EdgeKey) -> Result<Vec<String>> {
+        self.home(key.outbound_id).get_edge_property_names(key)
+    }
+}
+
+#[cfg(feature = "test-suite")]
+full_test_impl!(ShardedDatastore::new(
+    (0..4).map(|_| crate::memory::MemoryDatastore::default()).collect()
+));
+
+#[cfg(test)]
+mod tests {
+    use super::{ShardedDatastore, STUB_VERTEX_TYPE};
+    use crate::errors::Error;
+    use crate::memory::MemoryDatastore;
+    use crate::models::{BulkInsertItem, EdgeKey, RangeVertexQuery, SpecificVertexQuery, Type, Vertex, VertexQueryExt};
+    use crate::traits::{Datastore, Transaction};
+    use std::u32;
+
+    fn sharded_datastore() -> ShardedDatastore<MemoryDatastore> {
+        ShardedDatastore::new((0..4).map(|_| MemoryDatastore::default()).collect())
+    }
+
+    #[test]
+    fn should_store_vertices_in_their_shard() {
+        let datastore = sharded_datastore();
+        let trans = datastore.transaction().unwrap();
+        let t = Type::new("test_vertex_type").unwrap();
+        let vertices: Vec<Vertex> = (0..100).map(|_| Vertex::new(t.clone())).collect();
+
+        for vertex in &vertices {
+            assert!(trans.create_vertex(vertex).unwrap());
+        }
+
+        for vertex in &vertices {
+            let expected = datastore.shard_for(vertex.id);
+
+            for (index, shard) in datastore.shards().iter().enumerate() {
+                let q = SpecificVertexQuery::single(vertex.id);
+                let found = shard.transaction().unwrap().get_vertices(q).unwrap();
+                assert_eq!(found.len(), if index == expected { 1 } else { 0 });
+            }
+
+            let found = trans.get_vertices(SpecificVertexQuery::single(vertex.id)).unwrap();
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].t, t);
+        }
+
+        // Every shard should get some of the vertices
+        for shard in datastore.shards() {
+            assert!(shard.transaction().unwrap().get_vertex_count().unwrap() > 0);
+        }
+
+        assert_eq!(trans.get_vertex_count().unwrap(), 100);
+        let range = trans.get_vertices(RangeVertexQuery::new(u32::MAX)).unwrap();
+        let mut ids: Vec<_> = vertices.iter().map(|vertex| vertex.id).collect();
+        ids.sort();
+        assert_eq!(range.into_iter().map(|vertex| vertex.id).collect::<Vec<_>>(), ids);
+    }
+
+    #[test]
+    fn should_route_edges_across_shards() {
+        let datastore = sharded_datastore();
+        let trans = datastore.transaction().unwrap();
+        let vertex_t = Type::new("test_vertex_type").unwrap();
+        let edge_t = Type::new("test_edge_type").unwrap();
+        let ids: Vec<_> = (0..20)
+            .map(|_| trans.create_vertex_from_type(vertex_t.clone()).unwrap())
+            .collect();
+
+        for inbound_id in &ids[1..] {
+            let key = EdgeKey::new(ids[0], edge_t.clone(), *inbound_id);
+            assert!(trans.create_edge(&key).unwrap());
+        }
+
+        // Stubs of the inbound vertices don't show up anywhere
+        assert_eq!(trans.get_vertex_count().unwrap(), 20);
+        assert_eq!(trans.get_vertices(RangeVertexQuery::new(u32::MAX)).unwrap().len(), 20);
+        assert_eq!(datastore.vertices_iter().count(), 20);
+
+        let outbound = trans
+            .get_edges(SpecificVertexQuery::single(ids[0]).outbound(u32::MAX))
+            .unwrap();
+        assert_eq!(outbound.len(), 19);
+
+        for inbound_id in &ids[1..] {
+            let inbound = trans
+                .get_edges(SpecificVertexQuery::single(*inbound_id).inbound(u32::MAX))
+                .unwrap();
+            assert_eq!(inbound.len(), 1);
+            assert_eq!(inbound[0].key.outbound_id, ids[0]);
+        }
+
+        // Deleting an inbound vertex deletes its edge, wherever it lives
+        trans.delete_vertices(SpecificVertexQuery::single(ids[1])).unwrap();
+        assert_eq!(trans.count_edges(None).unwrap(), 18);
+        assert_eq!(trans.get_vertex_count().unwrap(), 19);
+    }
+
+    #[test]
+    fn should_check_bulk_inserts_like_transactions() {
+        let datastore = sharded_datastore();
+        let vertex_t = Type::new("test_vertex_type").unwrap();
+        let edge_t = Type::new("test_edge_type").unwrap();
+
+        let stub = Vertex::new(Type::new(STUB_VERTEX_TYPE).unwrap());
+        match datastore.bulk_insert(vec![BulkInsertItem::Vertex(stub)].into_iter()) {
+            Err(Error::Validation { .. }) => (),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        // Find a vertex that lives in another shard than the outbound one
+        let outbound_v = Vertex::new(vertex_t.clone());
+        let inbound_v = (0..)
+            .map(|_| Vertex::new(vertex_t.clone()))
+            .find(|v| datastore.shard_for(v.id) != datastore.shard_for(outbound_v.id))
+            .unwrap();
+        let key = EdgeKey::new(outbound_v.id, edge_t, inbound_v.id);
+
+        // The inbound vertex doesn't exist, so neither the edge nor a stub
+        // for it are inserted
+        let items = vec![
+            BulkInsertItem::Vertex(outbound_v.clone()),
+            BulkInsertItem::Edge(key.clone()),
+        ];
+        datastore.bulk_insert(items.into_iter()).unwrap();
+        let raw_count: u64 = datastore
+            .shards()
+            .iter()
+            .map(|shard| shard.transaction().unwrap().get_vertex_count().unwrap())
+            .sum();
+        assert_eq!(raw_count, 1);
+
+        let trans = datastore.transaction().unwrap();
+        assert!(!trans.has_edge(&key).unwrap());
+
+        // Once it does, they are
+        let items = vec![BulkInsertItem::Vertex(inbound_v), BulkInsertItem::Edge(key.clone())];
+        datastore.bulk_insert(items.into_iter()).unwrap();
+        assert!(trans.has_edge(&key).unwrap());
+        assert_eq!(trans.get_vertex_count().unwrap(), 2);
+    }
+}
@@ -0,0 +1,39 @@
+//! Statistics about a datastore's contents and storage footprint.
+
+/// Statistics about a datastore, for monitoring its footprint. See
+/// `Datastore::stats`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DatastoreStats {
+    /// The number of vertices.
+    pub vertex_count: u64,
+
+    /// The number of edges.
+    pub edge_count: u64,
+
+    /// Storage statistics for each of the datastore's columns, e.g. the
+    /// column families of a rocksdb datastore. This is empty for datastores
+    /// that don't report their storage.
+    pub columns: Vec<ColumnStats>,
+}
+
+/// Storage statistics for one column of a datastore, e.g. a rocksdb column
+/// family, or one of the maps of a memory datastore.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnStats {
+    /// The name of the column.
+    pub name: String,
+
+    /// The approximate number of entries in the column.
+    pub approximate_entries: u64,
+
+    /// The approximate size of the column's entries, in bytes.
+    pub approximate_bytes: u64,
+
+    /// The number of SST files holding the column, for rocksdb datastores.
+    pub sst_file_count: Option<u64>,
+
+    /// The size of each level of the column's LSM tree in bytes, from level
+    /// 0 down, for rocksdb datastores. Sizes are only accurate to the
+    /// nearest megabyte.
+    pub level_bytes: Vec<u64>,
+}
@@ -0,0 +1,120 @@
+//! Fixtures for tests and benchmarks.
+
+use crate::models::{Edge, EdgeKey, Type, Vertex};
+use chrono::offset::{TimeZone, Utc};
+use rand::prng::XorShiftRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+const TYPE_COUNT: usize = 8;
+const TYPE_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz_";
+
+fn random_uuid<R: Rng>(rng: &mut R) -> Uuid {
+    let mut bytes = [0u8; 16];
+    rng.fill(&mut bytes);
+    Uuid::from_slice(&bytes).unwrap()
+}
+
+fn random_type<R: Rng>(rng: &mut R) -> Type {
+    let len = rng.gen_range(4, 16);
+    let chars: Vec<u8> = (0..len).map(|_| *rng.choose(TYPE_CHARS).unwrap()).collect();
+    Type::new(String::from_utf8(chars).unwrap()).unwrap()
+}
+
+/// Builds a random graph, deterministically: the same arguments always
+/// produce the same graph, including vertex ids, types and edge datetimes.
+/// This makes it suitable for reproducible tests and benchmarks.
+///
+/// Vertex and edge types are each drawn from a small set of random types.
+/// Edges may be loops, but there are no duplicate edges, so the number of
+/// edges can fall slightly short of `vertices * avg_degree` on dense
+/// graphs.
+///
+/// # Arguments
+/// * `seed` - The seed for the random number generator.
+/// * `vertices` - The number of vertices to create.
+/// * `avg_degree` - The average number of outbound edges per vertex.
+pub fn random_graph(seed: u64, vertices: usize, avg_degree: f64) -> (Vec<Vertex>, Vec<Edge>) {
+    // The upper half of the seed is fixed, so that it's never all zeros
+    let mut seed_bytes = [0x5a; 16];
+
+    for (i, byte) in seed_bytes[..8].iter_mut().enumerate() {
+        *byte = (seed >> (i * 8)) as u8;
+    }
+
+    let mut rng = XorShiftRng::from_seed(seed_bytes);
+    let vertex_types: Vec<Type> = (0..TYPE_COUNT).map(|_| random_type(&mut rng)).collect();
+    let edge_types: Vec<Type> = (0..TYPE_COUNT).map(|_| random_type(&mut rng)).collect();
+
+    let vertices: Vec<Vertex> = (0..vertices)
+        .map(|_| {
+            let id = random_uuid(&mut rng);
+            let t = rng.choose(&vertex_types).unwrap().clone();
+            Vertex::with_id(id, t)
+        })
+        .collect();
+
+    let mut edges = Vec::new();
+
+    if vertices.is_empty() {
+        return (vertices, edges);
+    }
+
+    let edge_count = (vertices.len() as f64 * avg_degree).round() as usize;
+    let mut keys = HashSet::with_capacity(edge_count);
+
+    for _ in 0..edge_count {
+        let outbound_id = rng.choose(&vertices).unwrap().id;
+        let inbound_id = rng.choose(&vertices).unwrap().id;
+        let t = rng.choose(&edge_types).unwrap().clone();
+        let key = EdgeKey::new(outbound_id, t, inbound_id);
+
+        if keys.insert(key.clone()) {
+            // Datetimes are spread over a year, starting from a fixed point
+            let created_datetime = Utc.timestamp(1_500_000_000 + rng.gen_range(0, 31_536_000), 0);
+            edges.push(Edge::new(key, created_datetime));
+        }
+    }
+
+    (vertices, edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::random_graph;
+
+    #[test]
+    fn should_generate_the_same_graph_for_the_same_seed() {
+        let (first_vertices, first_edges) = random_graph(42, 100, 3.0);
+        let (second_vertices, second_edges) = random_graph(42, 100, 3.0);
+
+        assert_eq!(first_vertices.len(), 100);
+        assert!(first_edges.len() > 250);
+
+        assert_eq!(first_vertices.len(), second_vertices.len());
+        for (first, second) in first_vertices.iter().zip(second_vertices.iter()) {
+            assert_eq!(first.id, second.id);
+            assert_eq!(first.t, second.t);
+        }
+
+        assert_eq!(first_edges.len(), second_edges.len());
+        for (first, second) in first_edges.iter().zip(second_edges.iter()) {
+            assert!(first.eq_exact(second));
+        }
+    }
+
+    #[test]
+    fn should_generate_different_graphs_for_different_seeds() {
+        let (first_vertices, _) = random_graph(1, 10, 1.0);
+        let (second_vertices, _) = random_graph(2, 10, 1.0);
+        assert_ne!(first_vertices[0].id, second_vertices[0].id);
+    }
+
+    #[test]
+    fn should_generate_an_empty_graph() {
+        let (vertices, edges) = random_graph(42, 0, 3.0);
+        assert!(vertices.is_empty());
+        assert!(edges.is_empty());
+    }
+}
@@ -6,7 +6,9 @@ use super::util::{create_edge_from, create_edges, create_time_range_queryable_ed
 use chrono::offset::Utc;
 use chrono::Timelike;
 use crate::models;
+use serde_json::Value as JsonValue;
 use std::collections::HashSet;
+use std::u32;
 use uuid::Uuid;
 
 pub fn should_get_a_valid_edge<D: Datastore>(datastore: &mut D) {
@@ -52,7 +54,7 @@ pub fn should_not_get_an_invalid_edge<D: Datastore>(datastore: &mut D) {
             edge_t.clone(),
             Uuid::default(),
         )))
-        .unwrap();;
+        .unwrap();
     assert_eq!(e.len(), 0);
     let e = trans
         .get_edges(SpecificEdgeQuery::single(EdgeKey::new(
@@ -109,6 +111,67 @@ pub fn should_not_create_an_invalid_edge<D: Datastore>(datastore: &mut D) {
     assert_eq!(result.unwrap(), false);
 }
 
+pub fn should_create_edges<D: Datastore>(datastore: &mut D) {
+    let vertex_t = models::Type::new("test_vertex_type").unwrap();
+    let trans = datastore.transaction().unwrap();
+    let outbound_v = models::Vertex::new(vertex_t.clone());
+    let inbound_v = models::Vertex::new(vertex_t.clone());
+    trans.create_vertex(&outbound_v).unwrap();
+    trans.create_vertex(&inbound_v).unwrap();
+    let edge_t = models::Type::new("test_edge_type").unwrap();
+
+    let valid_key = models::EdgeKey::new(outbound_v.id, edge_t.clone(), inbound_v.id);
+    let reversed_key = models::EdgeKey::new(inbound_v.id, edge_t.clone(), outbound_v.id);
+    let invalid_key = models::EdgeKey::new(outbound_v.id, edge_t.clone(), Uuid::default());
+    let results = trans
+        .create_edges(&[
+            valid_key.clone(),
+            invalid_key.clone(),
+            reversed_key.clone(),
+            valid_key.clone(),
+        ])
+        .unwrap();
+    assert_eq!(results, vec![true, false, true, true]);
+
+    let e = trans
+        .get_edges(SpecificEdgeQuery::new(vec![
+            valid_key.clone(),
+            invalid_key,
+            reversed_key.clone(),
+        ]))
+        .unwrap();
+    assert_eq!(e.len(), 2);
+    assert_eq!(e[0].key, valid_key);
+    assert_eq!(e[1].key, reversed_key);
+
+    // Repeating a key in a single call should still only produce a single
+    // edge
+    let e = trans
+        .get_edges(SpecificVertexQuery::single(outbound_v.id).outbound(10))
+        .unwrap();
+    assert_eq!(e.len(), 1);
+    assert_eq!(e[0].key, valid_key);
+}
+
+pub fn should_check_whether_an_edge_exists<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = models::Type::new("test_vertex_type").unwrap();
+    let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let inbound_id = trans.create_vertex_from_type(vertex_t).unwrap();
+    let edge_t = models::Type::new("test_edge_type").unwrap();
+    let key = EdgeKey::new(outbound_id, edge_t.clone(), inbound_id);
+    assert!(!trans.has_edge(&key).unwrap());
+
+    trans.create_edge(&key).unwrap();
+    assert!(trans.has_edge(&key).unwrap());
+
+    let reversed_key = EdgeKey::new(inbound_id, edge_t, outbound_id);
+    assert!(!trans.has_edge(&reversed_key).unwrap());
+
+    trans.delete_edges(SpecificEdgeQuery::single(key.clone())).unwrap();
+    assert!(!trans.has_edge(&key).unwrap());
+}
+
 pub fn should_delete_a_valid_edge<D: Datastore>(datastore: &mut D) {
     let trans = datastore.transaction().unwrap();
     let vertex_t = models::Type::new("test_edge_type").unwrap();
@@ -177,6 +240,34 @@ pub fn should_get_an_inbound_edge_count<D: Datastore>(datastore: &mut D) {
     assert_eq!(count, 1);
 }
 
+pub fn should_count_edges_by_type<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = models::Type::new("test_vertex_type").unwrap();
+    let liked_t = models::Type::new("test_count_liked").unwrap();
+    let follows_t = models::Type::new("test_count_follows").unwrap();
+    let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+
+    for i in 0..3 {
+        let inbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        trans
+            .create_edge(&EdgeKey::new(outbound_id, liked_t.clone(), inbound_id))
+            .unwrap();
+
+        if i == 0 {
+            trans
+                .create_edge(&EdgeKey::new(outbound_id, follows_t.clone(), inbound_id))
+                .unwrap();
+        }
+    }
+
+    assert_eq!(trans.count_edges(Some(&liked_t)).unwrap(), 3);
+    assert_eq!(trans.count_edges(Some(&follows_t)).unwrap(), 1);
+    assert!(trans.count_edges(None).unwrap() >= 4);
+
+    let missing_t = models::Type::new("test_count_missing").unwrap();
+    assert_eq!(trans.count_edges(Some(&missing_t)).unwrap(), 0);
+}
+
 pub fn should_get_an_edge_range<D: Datastore>(datastore: &mut D) {
     let (outbound_id, start_time, end_time, _) = create_time_range_queryable_edges(datastore);
     let trans = datastore.transaction().unwrap();
@@ -324,6 +415,29 @@ pub fn should_get_edges_piped<D: Datastore>(datastore: &mut D) {
     );
 }
 
+pub fn should_get_inbound_edges<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = models::Type::new("test_vertex_type").unwrap();
+    let edge_t = models::Type::new("test_edge_type").unwrap();
+    let a_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let b_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let c_id = trans.create_vertex_from_type(vertex_t).unwrap();
+    let a_key = EdgeKey::new(a_id, edge_t.clone(), b_id);
+    let c_key = EdgeKey::new(c_id, edge_t.clone(), b_id);
+    trans.create_edge(&a_key).unwrap();
+    trans.create_edge(&c_key).unwrap();
+
+    let range = trans.get_edges(SpecificVertexQuery::single(b_id).inbound(10)).unwrap();
+    let keys: HashSet<EdgeKey> = range.into_iter().map(|e| e.key).collect();
+    assert_eq!(keys.len(), 2);
+    assert!(keys.contains(&a_key));
+    assert!(keys.contains(&c_key));
+
+    // B has no outbound edges
+    let range = trans.get_edges(SpecificVertexQuery::single(b_id).outbound(10)).unwrap();
+    assert_eq!(range.len(), 0);
+}
+
 fn check_edge_range(range: &[models::Edge], expected_outbound_id: Uuid, expected_length: usize) {
     assert_eq!(range.len(), expected_length);
     let mut covered_ids: HashSet<Uuid> = HashSet::new();
@@ -336,3 +450,366 @@ fn check_edge_range(range: &[models::Edge], expected_outbound_id: Uuid, expected
         covered_ids.insert(edge.key.inbound_id);
     }
 }
+
+pub fn should_get_edges_by_type_prefix<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = models::Type::new("test_vertex_type").unwrap();
+    let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let mut inbound_ids = Vec::new();
+
+    for t in &[
+        "test_prefix-admin",
+        "test_prefix-guest",
+        "test_prefix-owner",
+        "test_other",
+    ] {
+        let inbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        let key = models::EdgeKey::new(outbound_id, models::Type::new(*t).unwrap(), inbound_id);
+        trans.create_edge(&key).unwrap();
+        inbound_ids.push(inbound_id);
+    }
+
+    let e = trans
+        .get_edges(
+            SpecificVertexQuery::single(outbound_id)
+                .outbound(u32::MAX)
+                .with_type_prefix("test_prefix-"),
+        )
+        .unwrap();
+    assert_eq!(e.len(), 3);
+    assert!(e.iter().all(|e| e.key.t.0.starts_with("test_prefix-")));
+
+    let e = trans
+        .get_edges(
+            SpecificVertexQuery::new(inbound_ids)
+                .inbound(u32::MAX)
+                .with_type_prefix("test_prefix-"),
+        )
+        .unwrap();
+    assert_eq!(e.len(), 3);
+    assert!(e.iter().all(|e| e.key.t.0.starts_with("test_prefix-")));
+}
+
+pub fn should_get_edges_by_types<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = models::Type::new("test_vertex_type").unwrap();
+    let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let types: Vec<models::Type> = ["test_a", "test_b", "test_c"]
+        .iter()
+        .map(|t| models::Type::new(*t).unwrap())
+        .collect();
+
+    for t in &types {
+        let inbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        trans
+            .create_edge(&models::EdgeKey::new(outbound_id, t.clone(), inbound_id))
+            .unwrap();
+    }
+
+    let e = trans
+        .get_edges(
+            SpecificVertexQuery::single(outbound_id)
+                .outbound(u32::MAX)
+                .with_type_in(types[..2].to_vec()),
+        )
+        .unwrap();
+    assert_eq!(e.len(), 2);
+    assert!(e.iter().all(|e| e.key.t != types[2]));
+
+    let e = trans
+        .get_edges(
+            SpecificVertexQuery::single(outbound_id)
+                .outbound(u32::MAX)
+                .with_type_in(Vec::new()),
+        )
+        .unwrap();
+    assert_eq!(e.len(), 0);
+}
+
+pub fn should_get_undirected_edges<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = models::Type::new("test_vertex_type").unwrap();
+    let edge_t = models::Type::new("test_edge_type").unwrap();
+    let a = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let b = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let c = trans.create_vertex_from_type(vertex_t).unwrap();
+    let a_to_b = EdgeKey::new(a, edge_t.clone(), b);
+    let b_to_a = EdgeKey::new(b, edge_t.clone(), a);
+    let c_to_b = EdgeKey::new(c, edge_t, b);
+    trans
+        .create_edges(&[a_to_b.clone(), b_to_a.clone(), c_to_b.clone()])
+        .unwrap();
+
+    let e = trans
+        .get_edges(SpecificVertexQuery::single(b).outbound(u32::MAX))
+        .unwrap();
+    assert_eq!(e.len(), 1);
+    assert_eq!(e[0].key, b_to_a);
+
+    let expected_keys: HashSet<EdgeKey> = vec![a_to_b, b_to_a, c_to_b].into_iter().collect();
+
+    let e = trans
+        .get_edges(SpecificVertexQuery::single(b).outbound(u32::MAX).undirected())
+        .unwrap();
+    assert_eq!(e.len(), 3);
+    assert_eq!(
+        e.into_iter().map(|e| e.key).collect::<HashSet<EdgeKey>>(),
+        expected_keys
+    );
+
+    let e = trans
+        .get_edges(SpecificVertexQuery::single(b).inbound(u32::MAX).undirected())
+        .unwrap();
+    assert_eq!(e.len(), 3);
+    assert_eq!(
+        e.into_iter().map(|e| e.key).collect::<HashSet<EdgeKey>>(),
+        expected_keys
+    );
+
+    // Edges between `a` and `b` are found from both ends, but should only
+    // be returned once
+    let e = trans
+        .get_edges(SpecificVertexQuery::new(vec![a, b]).outbound(u32::MAX).undirected())
+        .unwrap();
+    assert_eq!(e.len(), 3);
+    assert_eq!(
+        e.into_iter().map(|e| e.key).collect::<HashSet<EdgeKey>>(),
+        expected_keys
+    );
+
+    let e = trans
+        .get_edges(SpecificVertexQuery::single(b).outbound(2).undirected())
+        .unwrap();
+    assert_eq!(e.len(), 2);
+}
+
+pub fn should_get_edges_by_time<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = models::Type::new("test_vertex_type").unwrap();
+    let edge_t = models::Type::new("test_edge_type").unwrap();
+    let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let mut keys = Vec::new();
+
+    for _ in 0..5 {
+        let inbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        let key = models::EdgeKey::new(outbound_id, edge_t.clone(), inbound_id);
+        trans.create_edge(&key).unwrap();
+        keys.push(key);
+    }
+
+    // Recreating an edge updates its datetime, moving it to the end
+    trans.create_edge(&keys[0]).unwrap();
+    let recreated_key = keys.remove(0);
+    keys.push(recreated_key);
+
+    let e = trans.edges_by_time(None, 10).unwrap();
+    assert_eq!(e.iter().map(|e| e.key.clone()).collect::<Vec<_>>(), keys);
+    assert!(e
+        .windows(2)
+        .all(|pair| pair[0].created_datetime < pair[1].created_datetime));
+
+    // Page through the edges, two at a time
+    let first_page = trans.edges_by_time(None, 2).unwrap();
+    assert_eq!(first_page.len(), 2);
+    assert_eq!(first_page[0].key, keys[0]);
+    assert_eq!(first_page[1].key, keys[1]);
+
+    let cursor = (first_page[1].created_datetime, first_page[1].key.clone());
+    let second_page = trans.edges_by_time(Some(cursor), 2).unwrap();
+    assert_eq!(second_page.len(), 2);
+    assert_eq!(second_page[0].key, keys[2]);
+    assert_eq!(second_page[1].key, keys[3]);
+
+    let last_page = trans
+        .edges_by_time(Some((e[4].created_datetime, e[4].key.clone())), 2)
+        .unwrap();
+    assert_eq!(last_page.len(), 0);
+}
+
+// Creates edges from one vertex, and gets their keys in the order they were
+// last updated
+fn create_sortable_edges<T: Transaction>(trans: &T) -> (Uuid, Vec<models::EdgeKey>) {
+    let vertex_t = models::Type::new("test_vertex_type").unwrap();
+    let edge_t = models::Type::new("test_edge_type").unwrap();
+    let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let mut keys = Vec::new();
+
+    for _ in 0..5 {
+        let inbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        let key = models::EdgeKey::new(outbound_id, edge_t.clone(), inbound_id);
+        trans.create_edge(&key).unwrap();
+        keys.push(key);
+    }
+
+    // Recreate the first edge, so that update order isn't creation order
+    trans.create_edge(&keys[0]).unwrap();
+    let recreated_key = keys.remove(0);
+    keys.push(recreated_key);
+    (outbound_id, keys)
+}
+
+pub fn should_sort_edges_by_updated_at<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let (outbound_id, keys) = create_sortable_edges(&trans);
+
+    let q = SpecificVertexQuery::single(outbound_id)
+        .outbound(u32::MAX)
+        .sort_by(models::SortKey::UpdatedAt { ascending: true });
+    let e = trans.get_edges(q).unwrap();
+    assert_eq!(e.iter().map(|e| e.key.clone()).collect::<Vec<_>>(), keys);
+
+    // The sort is applied before the limit
+    let q = SpecificVertexQuery::single(outbound_id)
+        .outbound(2)
+        .sort_by(models::SortKey::UpdatedAt { ascending: false });
+    let e = trans.get_edges(q).unwrap();
+    assert_eq!(e.len(), 2);
+    assert_eq!(e[0].key, keys[4]);
+    assert_eq!(e[1].key, keys[3]);
+    assert!(e[0].created_datetime > e[1].created_datetime);
+}
+
+pub fn should_sort_edges_by_id<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let (outbound_id, mut keys) = create_sortable_edges(&trans);
+    keys.sort();
+
+    let q = SpecificVertexQuery::single(outbound_id)
+        .outbound(3)
+        .sort_by(models::SortKey::Id { ascending: true });
+    let e = trans.get_edges(q).unwrap();
+    assert_eq!(e.iter().map(|e| e.key.clone()).collect::<Vec<_>>(), &keys[..3]);
+
+    let q = SpecificVertexQuery::single(outbound_id)
+        .outbound(3)
+        .sort_by(models::SortKey::Id { ascending: false });
+    let e = trans.get_edges(q).unwrap();
+    let expected: Vec<_> = keys.iter().rev().take(3).cloned().collect();
+    assert_eq!(e.iter().map(|e| e.key.clone()).collect::<Vec<_>>(), expected);
+}
+
+pub fn should_compact_after_deleting_edges<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = models::Type::new("test_vertex_type").unwrap();
+    let edge_t = models::Type::new("test_edge_type").unwrap();
+    let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let mut keys = Vec::new();
+
+    for _ in 0..100 {
+        let inbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        let key = models::EdgeKey::new(outbound_id, edge_t.clone(), inbound_id);
+        trans.create_edge(&key).unwrap();
+        keys.push(key);
+    }
+
+    trans.delete_edges(SpecificEdgeQuery::new(keys[..90].to_vec())).unwrap();
+    datastore.compact().unwrap();
+
+    // The remaining edges are still there
+    let trans = datastore.transaction().unwrap();
+    let e = trans
+        .get_edges(SpecificVertexQuery::single(outbound_id).outbound(u32::MAX))
+        .unwrap();
+    assert_eq!(e.len(), 10);
+    assert!(e.iter().all(|e| keys[90..].contains(&e.key)));
+    let count = trans
+        .get_edge_count(outbound_id, None, EdgeDirection::Outbound)
+        .unwrap();
+    assert_eq!(count, 10);
+}
+
+pub fn should_rename_edge_type<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = models::Type::new("test_vertex_type").unwrap();
+    let old_t = models::Type::new("test_old_edge_type").unwrap();
+    let new_t = models::Type::new("test_new_edge_type").unwrap();
+    let other_t = models::Type::new("test_other_edge_type").unwrap();
+    let outbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let mut old_keys = Vec::new();
+
+    for _ in 0..3 {
+        let inbound_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+        old_keys.push(EdgeKey::new(outbound_id, old_t.clone(), inbound_id));
+    }
+
+    let other_key = EdgeKey::new(outbound_id, other_t.clone(), old_keys[0].inbound_id);
+    trans.create_edges(&old_keys).unwrap();
+    trans.create_edge(&other_key).unwrap();
+    let old_edges = trans.get_edges(SpecificEdgeQuery::new(old_keys.clone())).unwrap();
+
+    let q = SpecificEdgeQuery::single(old_keys[0].clone()).property("foo");
+    trans.set_edge_properties(q, &JsonValue::Bool(true)).unwrap();
+
+    assert_eq!(trans.rename_edge_type(old_t.clone(), new_t.clone()).unwrap(), 3);
+
+    let e = trans
+        .get_edges(
+            SpecificVertexQuery::single(outbound_id)
+                .outbound(u32::MAX)
+                .t(old_t.clone()),
+        )
+        .unwrap();
+    assert_eq!(e.len(), 0);
+    assert_eq!(trans.count_edges(Some(&old_t)).unwrap(), 0);
+    assert_eq!(trans.count_edges(Some(&new_t)).unwrap(), 3);
+    assert_eq!(trans.count_edges(Some(&other_t)).unwrap(), 1);
+
+    let e = trans
+        .get_edges(
+            SpecificVertexQuery::single(outbound_id)
+                .outbound(u32::MAX)
+                .t(new_t.clone()),
+        )
+        .unwrap();
+    assert_eq!(e.len(), 3);
+
+    for old_edge in old_edges {
+        let new_key = EdgeKey::new(old_edge.key.outbound_id, new_t.clone(), old_edge.key.inbound_id);
+        let e = trans.get_edges(SpecificEdgeQuery::single(new_key.clone())).unwrap();
+        assert_eq!(e.len(), 1);
+        assert_eq!(e[0].created_datetime, old_edge.created_datetime);
+
+        let e = trans
+            .get_edges(
+                SpecificVertexQuery::single(new_key.inbound_id)
+                    .inbound(u32::MAX)
+                    .t(new_t.clone()),
+            )
+            .unwrap();
+        assert_eq!(e.len(), 1);
+    }
+
+    let new_key = EdgeKey::new(outbound_id, new_t, old_keys[0].inbound_id);
+    let p = trans
+        .get_edge_properties(SpecificEdgeQuery::single(new_key).property("foo"))
+        .unwrap();
+    assert_eq!(p.len(), 1);
+    assert_eq!(p[0].value, JsonValue::Bool(true));
+    let p = trans
+        .get_edge_properties(SpecificEdgeQuery::single(old_keys[0].clone()).property("foo"))
+        .unwrap();
+    assert_eq!(p.len(), 0);
+
+    // An existing edge of the new type is replaced, along with its
+    // properties
+    let other_edge = trans.get_edges(SpecificEdgeQuery::single(other_key.clone())).unwrap();
+    let replaced_key = EdgeKey::new(outbound_id, old_t.clone(), other_key.inbound_id);
+    trans.create_edge(&replaced_key).unwrap();
+    let q = SpecificEdgeQuery::single(replaced_key.clone()).property("bar");
+    trans.set_edge_properties(q, &JsonValue::Bool(true)).unwrap();
+    assert_eq!(trans.count_edges(Some(&old_t)).unwrap(), 1);
+
+    assert_eq!(trans.rename_edge_type(other_t.clone(), old_t.clone()).unwrap(), 1);
+    assert_eq!(trans.count_edges(Some(&other_t)).unwrap(), 0);
+    assert_eq!(trans.count_edges(Some(&old_t)).unwrap(), 1);
+
+    let e = trans
+        .get_edges(SpecificEdgeQuery::single(replaced_key.clone()))
+        .unwrap();
+    assert_eq!(e.len(), 1);
+    assert_eq!(e[0].created_datetime, other_edge[0].created_datetime);
+    let p = trans
+        .get_edge_properties(SpecificEdgeQuery::single(replaced_key).property("bar"))
+        .unwrap();
+    assert_eq!(p.len(), 0);
+}
@@ -21,6 +21,8 @@ macro_rules! full_test_impl {
 
         // Vertices
         define_test!(should_create_vertex_from_type, $code);
+        define_test!(should_create_vertex_with_id, $code);
+        define_test!(should_get_or_create_vertex, $code);
         define_test!(should_get_range_vertices, $code);
         define_test!(should_get_no_vertices_with_zero_limit, $code);
         define_test!(should_get_range_vertices_out_of_range, $code);
@@ -30,20 +32,33 @@ macro_rules! full_test_impl {
         define_test!(should_get_vertices, $code);
         define_test!(should_get_vertices_piped, $code);
         define_test!(should_get_a_vertex_count, $code);
+        define_test!(should_count_vertices_by_type, $code);
+        define_test!(should_check_which_vertices_exist, $code);
+        define_test!(should_get_range_vertices_by_type, $code);
+        define_test!(should_get_range_vertices_by_type_prefix, $code);
+        define_test!(should_get_range_vertices_by_id_range, $code);
         define_test!(should_delete_a_valid_vertex, $code);
+        define_test!(should_delete_edges_with_a_vertex, $code);
         define_test!(should_not_delete_an_invalid_vertex, $code);
+        define_test!(should_delete_vertices_by_type, $code);
+        define_test!(should_clear, $code);
+        define_test!(should_get_stats, $code);
+        define_test!(should_iterate_over_all_vertices_and_edges, $code);
 
         // Edges
         define_test!(should_get_a_valid_edge, $code);
         define_test!(should_not_get_an_invalid_edge, $code);
         define_test!(should_create_a_valid_edge, $code);
         define_test!(should_not_create_an_invalid_edge, $code);
+        define_test!(should_create_edges, $code);
+        define_test!(should_check_whether_an_edge_exists, $code);
         define_test!(should_delete_a_valid_edge, $code);
         define_test!(should_not_delete_an_invalid_edge, $code);
         define_test!(should_get_an_edge_count, $code);
         define_test!(should_get_an_edge_count_with_no_type, $code);
         define_test!(should_get_an_edge_count_for_an_invalid_edge, $code);
         define_test!(should_get_an_inbound_edge_count, $code);
+        define_test!(should_count_edges_by_type, $code);
         define_test!(should_get_an_edge_range, $code);
         define_test!(should_get_edges_with_no_type, $code);
         define_test!(should_get_no_edges_for_an_invalid_range, $code);
@@ -52,6 +67,15 @@ macro_rules! full_test_impl {
         define_test!(should_get_edges_with_no_time, $code);
         define_test!(should_get_no_edges_for_reversed_time, $code);
         define_test!(should_get_edges, $code);
+        define_test!(should_get_inbound_edges, $code);
+        define_test!(should_get_edges_by_type_prefix, $code);
+        define_test!(should_get_edges_by_types, $code);
+        define_test!(should_get_undirected_edges, $code);
+        define_test!(should_get_edges_by_time, $code);
+        define_test!(should_rename_edge_type, $code);
+        define_test!(should_compact_after_deleting_edges, $code);
+        define_test!(should_sort_edges_by_updated_at, $code);
+        define_test!(should_sort_edges_by_id, $code);
 
         // Properties
         define_test!(should_handle_vertex_properties, $code);
@@ -60,5 +84,6 @@ macro_rules! full_test_impl {
         define_test!(should_handle_edge_properties, $code);
         define_test!(should_not_set_invalid_edge_properties, $code);
         define_test!(should_not_delete_invalid_edge_properties, $code);
+        define_test!(should_set_and_get_edge_labels, $code);
     };
 }
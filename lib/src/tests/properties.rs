@@ -1,5 +1,6 @@
 use super::super::{
-    Datastore, EdgeKey, EdgeQueryExt, SpecificEdgeQuery, SpecificVertexQuery, Transaction, Type, Vertex, VertexQueryExt,
+    Datastore, EdgeKey, EdgeLabel, EdgeQueryExt, SpecificEdgeQuery, SpecificVertexQuery, Transaction, Type,
+    ValidationError, Vertex, VertexQueryExt, DEFAULT_MAX_EDGE_LABEL_LENGTH,
 };
 use serde_json::Value as JsonValue;
 use crate::util::generate_random_secret;
@@ -122,3 +123,45 @@ pub fn should_not_delete_invalid_edge_properties<D: Datastore>(datastore: &mut D
         .delete_edge_properties(SpecificEdgeQuery::single(key).property("bleh"))
         .unwrap();
 }
+
+pub fn should_set_and_get_edge_labels<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let t = Type::new("test_edge_type").unwrap();
+    let outbound_v = Vertex::new(t.clone());
+    let inbound_v = Vertex::new(t.clone());
+    trans.create_vertex(&outbound_v).unwrap();
+    trans.create_vertex(&inbound_v).unwrap();
+    let key = EdgeKey::new(outbound_v.id, t.clone(), inbound_v.id);
+    trans.create_edge(&key).unwrap();
+
+    // Check to make sure there's no initial label
+    assert_eq!(trans.get_edge_label(&key).unwrap(), None);
+
+    let label = EdgeLabel::new("liked on mobile").unwrap();
+    trans.set_edge_label(&key, Some(&label)).unwrap();
+    assert_eq!(trans.get_edge_label(&key).unwrap(), Some(label));
+
+    // The label isn't part of the edge's identity
+    let edges = trans.get_edges(SpecificEdgeQuery::single(key.clone())).unwrap();
+    assert_eq!(edges.len(), 1);
+    assert_eq!(edges[0].key, key);
+
+    // Over-length labels are rejected before they can be set
+    match EdgeLabel::new("a".repeat(DEFAULT_MAX_EDGE_LABEL_LENGTH + 1)) {
+        Err(ValidationError::ValueTooLong) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+    assert_eq!(
+        trans.get_edge_label(&key).unwrap(),
+        Some(EdgeLabel::new("liked on mobile").unwrap())
+    );
+
+    trans.set_edge_label(&key, None).unwrap();
+    assert_eq!(trans.get_edge_label(&key).unwrap(), None);
+
+    // Labels can't be set on edges that don't exist
+    let missing_key = EdgeKey::new(inbound_v.id, t, outbound_v.id);
+    let label = EdgeLabel::new("missing").unwrap();
+    trans.set_edge_label(&missing_key, Some(&label)).unwrap();
+    assert_eq!(trans.get_edge_label(&missing_key).unwrap(), None);
+}
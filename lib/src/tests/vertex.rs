@@ -1,6 +1,10 @@
-use super::super::{Datastore, EdgeQueryExt, RangeVertexQuery, SpecificVertexQuery, Transaction, VertexQueryExt};
+use super::super::{
+    Datastore, EdgeQueryExt, RangeVertexQuery, SpecificEdgeQuery, SpecificVertexQuery, Transaction, VertexQueryExt,
+};
 use super::util::{create_edge_from, create_edges};
+use crate::errors::Error;
 use crate::models;
+use serde_json::Value as JsonValue;
 use std::collections::HashSet;
 use std::u32;
 use uuid::Uuid;
@@ -11,6 +15,43 @@ pub fn should_create_vertex_from_type<D: Datastore>(datastore: &mut D) {
     trans.create_vertex_from_type(t).unwrap();
 }
 
+pub fn should_create_vertex_with_id<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let t = models::Type::new("test_vertex_type").unwrap();
+    let id = models::Vertex::new(t.clone()).id;
+    trans.create_vertex_with_id(id, t.clone()).unwrap();
+
+    let range = trans.get_vertices(SpecificVertexQuery::single(id)).unwrap();
+    assert_eq!(range.len(), 1);
+    assert_eq!(range[0].t, t);
+
+    match trans.create_vertex_with_id(id, t) {
+        Err(Error::UuidTaken) => (),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+}
+
+pub fn should_get_or_create_vertex<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let t = models::Type::new("test_vertex_type").unwrap();
+    let id = models::Vertex::new(t.clone()).id;
+
+    let (v, created) = trans.get_or_create_vertex(id, t.clone()).unwrap();
+    assert!(created);
+    assert_eq!(v.id, id);
+    assert_eq!(v.t, t);
+
+    let other_t = models::Type::new("other_vertex_type").unwrap();
+    let (v, created) = trans.get_or_create_vertex(id, other_t).unwrap();
+    assert!(!created);
+    assert_eq!(v.id, id);
+    assert_eq!(v.t, t);
+
+    let range = trans.get_vertices(SpecificVertexQuery::single(id)).unwrap();
+    assert_eq!(range.len(), 1);
+    assert_eq!(range[0].t, t);
+}
+
 pub fn should_get_range_vertices<D: Datastore>(datastore: &mut D) {
     let trans = datastore.transaction().unwrap();
     let mut inserted_ids = create_vertices(&trans);
@@ -168,6 +209,24 @@ pub fn should_delete_a_valid_vertex<D: Datastore>(datastore: &mut D) {
     assert_eq!(count, 0);
 }
 
+pub fn should_delete_edges_with_a_vertex<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let vertex_t = models::Type::new("test_vertex_type").unwrap();
+    let a_id = trans.create_vertex_from_type(vertex_t.clone()).unwrap();
+    let b_id = trans.create_vertex_from_type(vertex_t).unwrap();
+    let key = models::EdgeKey::new(a_id, models::Type::new("test_edge_type").unwrap(), b_id);
+    trans.create_edge(&key).unwrap();
+
+    trans.delete_vertices(SpecificVertexQuery::single(a_id)).unwrap();
+
+    let e = trans.get_edges(SpecificEdgeQuery::single(key)).unwrap();
+    assert_eq!(e.len(), 0);
+    let e = trans.get_edges(SpecificVertexQuery::single(b_id).inbound(10)).unwrap();
+    assert_eq!(e.len(), 0);
+    let v = trans.get_vertices(SpecificVertexQuery::single(b_id)).unwrap();
+    assert_eq!(v.len(), 1);
+}
+
 pub fn should_not_delete_an_invalid_vertex<D: Datastore>(datastore: &mut D) {
     let trans = datastore.transaction().unwrap();
     trans
@@ -175,6 +234,107 @@ pub fn should_not_delete_an_invalid_vertex<D: Datastore>(datastore: &mut D) {
         .unwrap();
 }
 
+pub fn should_delete_vertices_by_type<D: Datastore>(datastore: &mut D) {
+    let (outbound_id, inbound_ids) = create_edges(datastore);
+    let trans = datastore.transaction().unwrap();
+    let inbound_t = models::Type::new("test_inbound_vertex_type").unwrap();
+
+    assert_eq!(trans.delete_vertices_by_type(inbound_t.clone()).unwrap(), 5);
+    assert_eq!(trans.count_vertices(Some(&inbound_t)).unwrap(), 0);
+    let v = trans
+        .get_vertices(SpecificVertexQuery::new(inbound_ids.to_vec()))
+        .unwrap();
+    assert_eq!(v.len(), 0);
+
+    // Vertices of other types are untouched, but lose their edges to the
+    // deleted vertices
+    let v = trans.get_vertices(SpecificVertexQuery::single(outbound_id)).unwrap();
+    assert_eq!(v.len(), 1);
+    let e = trans
+        .get_edges(SpecificVertexQuery::single(outbound_id).outbound(u32::MAX))
+        .unwrap();
+    assert_eq!(e.len(), 0);
+
+    assert_eq!(trans.delete_vertices_by_type(inbound_t).unwrap(), 0);
+}
+
+pub fn should_clear<D: Datastore>(datastore: &mut D) {
+    let (outbound_id, inbound_ids) = create_edges(datastore);
+
+    {
+        let trans = datastore.transaction().unwrap();
+        let q = SpecificVertexQuery::single(outbound_id).property("foo");
+        trans.set_vertex_properties(q, &JsonValue::Bool(true)).unwrap();
+        let q = SpecificVertexQuery::single(outbound_id).outbound(1).property("bar");
+        trans.set_edge_properties(q, &JsonValue::Bool(true)).unwrap();
+    }
+
+    datastore.clear().unwrap();
+
+    let trans = datastore.transaction().unwrap();
+    assert_eq!(trans.get_vertex_count().unwrap(), 0);
+    assert_eq!(trans.count_vertices(None).unwrap(), 0);
+    assert_eq!(trans.count_edges(None).unwrap(), 0);
+    let t = models::Type::new("test_outbound_vertex_type").unwrap();
+    assert_eq!(trans.count_vertices(Some(&t)).unwrap(), 0);
+    let e = trans
+        .get_edges(SpecificVertexQuery::new(inbound_ids.to_vec()).inbound(u32::MAX))
+        .unwrap();
+    assert_eq!(e.len(), 0);
+
+    // The datastore should still be usable afterwards
+    trans.create_vertex_from_type(t).unwrap();
+    assert_eq!(trans.get_vertex_count().unwrap(), 1);
+}
+
+pub fn should_get_stats<D: Datastore>(datastore: &mut D) {
+    let stats = datastore.stats().unwrap();
+    assert_eq!(stats.vertex_count, 0);
+    assert_eq!(stats.edge_count, 0);
+
+    let (outbound_id, _) = create_edges(datastore);
+    let stats = datastore.stats().unwrap();
+    assert_eq!(stats.vertex_count, 6);
+    assert_eq!(stats.edge_count, 5);
+
+    let trans = datastore.transaction().unwrap();
+    trans
+        .delete_edges(SpecificVertexQuery::single(outbound_id).outbound(1))
+        .unwrap();
+    let stats = datastore.stats().unwrap();
+    assert_eq!(stats.vertex_count, 6);
+    assert_eq!(stats.edge_count, 4);
+}
+
+pub fn should_iterate_over_all_vertices_and_edges<D: Datastore>(datastore: &mut D) {
+    create_edges(datastore);
+    create_edges(datastore);
+
+    let vertices: Vec<models::Vertex> = datastore.vertices_iter().collect::<Result<_, _>>().unwrap();
+    let edges: Vec<models::Edge> = datastore.edges_iter().collect::<Result<_, _>>().unwrap();
+
+    let trans = datastore.transaction().unwrap();
+    assert_eq!(vertices.len() as u64, trans.count_vertices(None).unwrap());
+    assert_eq!(edges.len() as u64, trans.count_edges(None).unwrap());
+    assert!(vertices.windows(2).all(|pair| pair[0].id < pair[1].id));
+    assert!(edges
+        .windows(2)
+        .all(|pair| pair[0].key.outbound_id <= pair[1].key.outbound_id));
+}
+
+pub fn should_check_which_vertices_exist<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let t = models::Type::new("test_vertex_type").unwrap();
+    let first_id = trans.create_vertex_from_type(t.clone()).unwrap();
+    let second_id = trans.create_vertex_from_type(t).unwrap();
+
+    let ids = [Uuid::default(), first_id, Uuid::default(), second_id, first_id];
+    let exist = trans.which_vertices_exist(&ids).unwrap();
+    assert_eq!(exist, vec![false, true, false, true, true]);
+
+    assert_eq!(trans.which_vertices_exist(&[]).unwrap(), Vec::<bool>::new());
+}
+
 pub fn should_get_a_vertex_count<D: Datastore>(datastore: &mut D) {
     let trans = datastore.transaction().unwrap();
     let vertex_t = models::Type::new("test_vertex_type").unwrap();
@@ -184,6 +344,151 @@ pub fn should_get_a_vertex_count<D: Datastore>(datastore: &mut D) {
     assert!(count >= 1);
 }
 
+pub fn should_count_vertices_by_type<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let user_t = models::Type::new("test_count_user").unwrap();
+    let movie_t = models::Type::new("test_count_movie").unwrap();
+
+    for _ in 0..3 {
+        trans.create_vertex_from_type(user_t.clone()).unwrap();
+    }
+
+    for _ in 0..2 {
+        trans.create_vertex_from_type(movie_t.clone()).unwrap();
+    }
+
+    assert_eq!(trans.count_vertices(Some(&user_t)).unwrap(), 3);
+    assert_eq!(trans.count_vertices(Some(&movie_t)).unwrap(), 2);
+    assert!(trans.count_vertices(None).unwrap() >= 5);
+
+    let missing_t = models::Type::new("test_count_missing").unwrap();
+    assert_eq!(trans.count_vertices(Some(&missing_t)).unwrap(), 0);
+}
+
+pub fn should_get_range_vertices_by_type<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let types = vec![
+        models::Type::new("test_index_a").unwrap(),
+        models::Type::new("test_index_b").unwrap(),
+        models::Type::new("test_index_c").unwrap(),
+    ];
+
+    for i in 0..1000 {
+        trans.create_vertex_from_type(types[i % 3].clone()).unwrap();
+    }
+
+    let range = trans
+        .get_vertices(RangeVertexQuery::new(u32::MAX).t(types[0].clone()))
+        .unwrap();
+    assert_eq!(range.len(), 334);
+    assert!(range.iter().all(|v| v.t == types[0]));
+    assert_eq!(trans.count_vertices(Some(&types[1])).unwrap(), 333);
+
+    // Page through one type, and make sure the pages line up
+    let first_page = trans
+        .get_vertices(RangeVertexQuery::new(100).t(types[2].clone()))
+        .unwrap();
+    assert_eq!(first_page.len(), 100);
+    let second_page = trans
+        .get_vertices(
+            RangeVertexQuery::new(u32::MAX)
+                .t(types[2].clone())
+                .start_id(first_page[99].id),
+        )
+        .unwrap();
+    assert!(second_page.len() >= 233);
+    assert!(second_page.iter().all(|v| v.t == types[2] && v.id >= first_page[99].id));
+
+    // Deleted vertices should drop out of type-scoped queries
+    trans
+        .delete_vertices(RangeVertexQuery::new(u32::MAX).t(types[0].clone()))
+        .unwrap();
+    let range = trans
+        .get_vertices(RangeVertexQuery::new(u32::MAX).t(types[0].clone()))
+        .unwrap();
+    assert_eq!(range.len(), 0);
+    assert_eq!(trans.count_vertices(Some(&types[1])).unwrap(), 333);
+}
+
+pub fn should_get_range_vertices_by_type_prefix<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let mut inserted_ids = Vec::new();
+
+    for t in &["test_prefix-admin", "test_prefix-guest", "test_prefix-owner"] {
+        let t = models::Type::new(*t).unwrap();
+        inserted_ids.push(trans.create_vertex_from_type(t).unwrap());
+    }
+
+    let other_t = models::Type::new("test_other").unwrap();
+    trans.create_vertex_from_type(other_t).unwrap();
+
+    // The prefix doesn't need to be a valid type
+    let range = trans
+        .get_vertices(RangeVertexQuery::new(u32::MAX).with_type_prefix("test_prefix-"))
+        .unwrap();
+    assert_eq!(range.len(), 3);
+    let mut range_ids: Vec<Uuid> = range.iter().map(|v| v.id).collect();
+    range_ids.sort();
+    inserted_ids.sort();
+    assert_eq!(range_ids, inserted_ids);
+
+    let range = trans
+        .get_vertices(
+            RangeVertexQuery::new(u32::MAX)
+                .t(models::Type::new("test_prefix-guest").unwrap())
+                .with_type_prefix("test_prefix-"),
+        )
+        .unwrap();
+    assert_eq!(range.len(), 1);
+
+    let range = trans
+        .get_vertices(RangeVertexQuery::new(u32::MAX).with_type_prefix("test_nonexistent"))
+        .unwrap();
+    assert_eq!(range.len(), 0);
+}
+
+pub fn should_get_range_vertices_by_id_range<D: Datastore>(datastore: &mut D) {
+    let trans = datastore.transaction().unwrap();
+    let t = models::Type::new("test_vertex_type").unwrap();
+    let mut inserted_ids: Vec<Uuid> = (0..100)
+        .map(|_| trans.create_vertex_from_type(t.clone()).unwrap())
+        .collect();
+    inserted_ids.sort();
+
+    let min_id = Uuid::default();
+    let split_id = inserted_ids[50];
+    let max_id = Uuid::parse_str("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap();
+
+    let first_half = trans
+        .get_vertices(RangeVertexQuery::new(u32::MAX).with_id_range(min_id, split_id))
+        .unwrap();
+    let second_half = trans
+        .get_vertices(RangeVertexQuery::new(u32::MAX).with_id_range(split_id, max_id))
+        .unwrap();
+    assert_eq!(first_half.len(), 50);
+    assert_eq!(second_half.len(), 50);
+
+    let mut range_ids: Vec<Uuid> = first_half.iter().chain(second_half.iter()).map(|v| v.id).collect();
+    range_ids.sort();
+    assert_eq!(range_ids, inserted_ids);
+
+    // Type-scoped queries should be split the same way
+    let first_half = trans
+        .get_vertices(
+            RangeVertexQuery::new(u32::MAX)
+                .t(t.clone())
+                .with_id_range(min_id, split_id),
+        )
+        .unwrap();
+    assert_eq!(first_half.len(), 50);
+    assert!(first_half.iter().all(|v| v.id < split_id));
+
+    let range = trans
+        .get_vertices(RangeVertexQuery::new(u32::MAX).with_id_range(split_id, split_id))
+        .unwrap();
+    assert_eq!(range.len(), 0);
+}
+
 fn create_vertices<T>(trans: &T) -> Vec<Uuid>
 where
     T: Transaction,
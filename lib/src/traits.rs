@@ -1,7 +1,15 @@
-use crate::errors::{Error, Result};
+use chrono::offset::Utc;
+use chrono::DateTime;
+use crate::diff;
+use crate::diff::Diff;
+use crate::errors::{Error, Result, ValidationError};
 use crate::models;
 use crate::models::{EdgeQueryExt, VertexQueryExt};
+use crate::scan;
+use crate::stats::DatastoreStats;
 use serde_json::value::Value as JsonValue;
+use std::collections::HashSet;
+use std::u32;
 use std::vec::Vec;
 use uuid::Uuid;
 
@@ -18,6 +26,12 @@ pub trait Datastore {
 
     /// Bulk inserts many vertices, edges, and/or properties.
     ///
+    /// This is meant for seeding a datastore, and trades safety for speed.
+    /// Implementations may skip validation, e.g. inserting an edge without
+    /// checking that its vertices exist, and may skip durability guarantees.
+    /// It should only be used on a datastore that is empty, or that nothing
+    /// else is writing to.
+    ///
     /// # Arguments
     /// * `items`: The items to insert.
     fn bulk_insert<I>(&self, items: I) -> Result<()>
@@ -47,6 +61,91 @@ pub trait Datastore {
 
         Ok(())
     }
+
+    /// Deletes all of the vertices, edges and properties in the datastore.
+    /// This is meant for resetting a datastore between tests or
+    /// benchmarks. Implementations may not do this atomically, so nothing
+    /// else should be writing to the datastore in the meantime.
+    fn clear(&self) -> Result<()> {
+        let trans = self.transaction()?;
+
+        // Deleting a vertex deletes its edges and properties too
+        while trans.get_vertex_count()? > 0 {
+            trans.delete_vertices(models::RangeVertexQuery::new(u32::MAX))?;
+        }
+
+        Ok(())
+    }
+
+    /// Compacts the datastore's storage, e.g. to reclaim space and speed up
+    /// reads after a large number of deletions. This can take a while on a
+    /// large datastore, but other operations can run in the meantime. By
+    /// default, this does nothing.
+    fn compact(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Makes sure that every write made so far is on disk, e.g. before
+    /// shutting down. By default, this does nothing.
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Gets statistics about the datastore's contents and storage
+    /// footprint. By default, only the vertex and edge counts are
+    /// reported, and these are found by scanning the datastore.
+    fn stats(&self) -> Result<DatastoreStats> {
+        let trans = self.transaction()?;
+
+        Ok(DatastoreStats {
+            vertex_count: trans.get_vertex_count()?,
+            edge_count: trans.count_edges(None)?,
+            columns: Vec::new(),
+        })
+    }
+
+    /// Iterates over every vertex in the datastore, in id order. Vertices
+    /// are read lazily as the iterator is consumed; writes made in the
+    /// meantime may or may not be reflected.
+    ///
+    /// Errors, e.g. from the underlying database, are returned as items
+    /// rather than ending the iteration silently. Iteration may stop after
+    /// an error.
+    fn vertices_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Vertex>> + 'a>
+    where
+        Self: Sized,
+    {
+        Box::new(scan::VertexScan::new(self))
+    }
+
+    /// Iterates over every edge in the datastore, grouped by outbound vertex
+    /// in id order. Like `vertices_iter`, edges are read lazily, and errors
+    /// are returned as items.
+    fn edges_iter<'a>(&'a self) -> Box<dyn Iterator<Item = Result<models::Edge>> + 'a>
+    where
+        Self: Sized,
+    {
+        Box::new(scan::EdgeScan::new(self))
+    }
+
+    /// Computes the differences between this datastore and another, i.e.
+    /// the vertices and edges that would have to be added, removed or
+    /// changed for this datastore to match the other. Properties are not
+    /// compared.
+    ///
+    /// Vertices are read from both datastores a page at a time in id order,
+    /// so memory use is bounded by the page size and the number of edges
+    /// per vertex, rather than by the size of the datastores. Neither
+    /// datastore should be written to in the meantime.
+    ///
+    /// # Arguments
+    /// * `other`: The datastore to compare against.
+    fn diff<O: Datastore>(&self, other: &O) -> Result<Diff>
+    where
+        Self: Sized,
+    {
+        diff::diff(self, other)
+    }
 }
 
 /// Specifies a transaction implementation, which are returned by datastores.
@@ -82,6 +181,51 @@ pub trait Transaction {
         }
     }
 
+    /// Creates a new vertex with a caller-specified UUID. As opposed to
+    /// `create_vertex`, this returns an error rather than `false` if the
+    /// UUID is already taken.
+    ///
+    /// # Arguments
+    /// * `id`: The id of the vertex to create.
+    /// * `t`: The type of the vertex to create.
+    ///
+    /// # Errors
+    /// Returns `Error::UuidTaken` if a vertex with the same UUID already
+    /// exists.
+    fn create_vertex_with_id(&self, id: Uuid, t: models::Type) -> Result<()> {
+        let v = models::Vertex::with_id(id, t);
+
+        if !self.create_vertex(&v)? {
+            Err(Error::UuidTaken)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Gets the vertex with the given UUID, creating it with the given type
+    /// if it does not exist yet. Returns the vertex, and whether it was
+    /// newly created. If the vertex already exists, its type is left as-is,
+    /// even if it differs from `t`.
+    ///
+    /// # Arguments
+    /// * `id`: The id of the vertex to get or create.
+    /// * `t`: The type of the vertex, if it needs to be created.
+    fn get_or_create_vertex(&self, id: Uuid, t: models::Type) -> Result<(models::Vertex, bool)> {
+        let v = models::Vertex::with_id(id, t);
+
+        loop {
+            if self.create_vertex(&v)? {
+                return Ok((v, true));
+            }
+
+            // The vertex may have been deleted in between, in which case
+            // we try to create it again.
+            if let Some(existing) = self.get_vertices(models::SpecificVertexQuery::single(id))?.pop() {
+                return Ok((existing, false));
+            }
+        }
+    }
+
     /// Gets a range of vertices specified by a query.
     ///
     /// # Arguments
@@ -94,24 +238,87 @@ pub trait Transaction {
     /// * `q` - The query to run.
     fn delete_vertices<Q: Into<models::VertexQuery>>(&self, q: Q) -> Result<()>;
 
+    /// Deletes all of the vertices of a type, along with their edges and
+    /// properties. Returns the number of vertices deleted.
+    ///
+    /// # Arguments
+    /// * `t` - The type of the vertices to delete.
+    fn delete_vertices_by_type(&self, t: models::Type) -> Result<u64> {
+        let count = self.count_vertices(Some(&t))?;
+        self.delete_vertices(models::RangeVertexQuery::new(u32::MAX).t(t))?;
+        Ok(count)
+    }
+
     /// Gets the number of vertices in the datastore..
     fn get_vertex_count(&self) -> Result<u64>;
 
+    /// Counts the vertices in the datastore, without fetching them.
+    ///
+    /// The default implementation fetches the vertices of a type to count
+    /// them, so datastores should override it where possible.
+    ///
+    /// # Arguments
+    /// * `t` - Only count vertices of the specified type. If `None`, all
+    ///   vertices are counted.
+    fn count_vertices(&self, t: Option<&models::Type>) -> Result<u64> {
+        match t {
+            Some(t) => {
+                let q = models::RangeVertexQuery::new(u32::MAX).t(t.clone());
+                Ok(self.get_vertices(q)?.len() as u64)
+            }
+            None => self.get_vertex_count(),
+        }
+    }
+
+    /// Checks which of a set of vertices exist, without fetching them one at
+    /// a time. Returns whether each vertex exists, in the same order as
+    /// `ids`.
+    ///
+    /// # Arguments
+    /// * `ids`: The ids of the vertices to check.
+    fn which_vertices_exist(&self, ids: &[Uuid]) -> Result<Vec<bool>> {
+        let vertices = self.get_vertices(models::SpecificVertexQuery::new(ids.to_vec()))?;
+        let existing: HashSet<Uuid> = vertices.into_iter().map(|vertex| vertex.id).collect();
+        Ok(ids.iter().map(|id| existing.contains(id)).collect())
+    }
+
     /// Creates a new edge. If the edge already exists, this will update it
     /// with a new update datetime. Returns whether the edge was successfully
     /// created - if this is false, it's because one of the specified vertices
-    /// is missing.
+    /// is missing, or because the edge already exists and the datastore is
+    /// configured to deduplicate edges.
     ///
     /// # Arguments
     /// * `key`: The edge to create.
     fn create_edge(&self, key: &models::EdgeKey) -> Result<bool>;
 
+    /// Creates many edges at once. As with `create_edge`, existing edges are
+    /// updated with a new update datetime. Returns whether each edge was
+    /// successfully created, in the same order as `keys` - an entry is
+    /// false if one of the edge's vertices is missing, or if the edge was
+    /// deduplicated.
+    ///
+    /// # Arguments
+    /// * `keys`: The edges to create.
+    fn create_edges(&self, keys: &[models::EdgeKey]) -> Result<Vec<bool>> {
+        keys.iter().map(|key| self.create_edge(key)).collect()
+    }
+
     /// Gets a range of edges specified by a query.
     ///
     /// # Arguments
     /// * `q` - The query to run.
     fn get_edges<Q: Into<models::EdgeQuery>>(&self, q: Q) -> Result<Vec<models::Edge>>;
 
+    /// Checks whether an edge exists, without fetching it.
+    ///
+    /// # Arguments
+    /// * `key`: The key of the edge to check.
+    fn has_edge(&self, key: &models::EdgeKey) -> Result<bool> {
+        let edges = self.get_edges(models::SpecificEdgeQuery::single(key.clone()))?;
+        Ok(!edges.is_empty())
+    }
+
     /// Deletes a set of edges specified by a query.
     ///
     /// # Arguments
@@ -126,6 +333,110 @@ pub trait Transaction {
     /// * `direction`: The direction of edges to get.
     fn get_edge_count(&self, id: Uuid, t: Option<&models::Type>, direction: models::EdgeDirection) -> Result<u64>;
 
+    /// Counts the edges in the datastore, without fetching them.
+    ///
+    /// The default implementation fetches the edges to count them, so
+    /// datastores should override it where possible.
+    ///
+    /// # Arguments
+    /// * `t` - Only count edges of the specified type. If `None`, all edges
+    ///   are counted.
+    fn count_edges(&self, t: Option<&models::Type>) -> Result<u64> {
+        let q = models::RangeVertexQuery::new(u32::MAX).outbound(u32::MAX);
+
+        let q = match t {
+            Some(t) => q.t(t.clone()),
+            None => q,
+        };
+
+        Ok(self.get_edges(q)?.len() as u64)
+    }
+
+    /// Gets edges in chronological order of their datetimes, oldest first,
+    /// for consuming the datastore as a change feed. Edges with the same
+    /// datetime are ordered by their keys. To page through the edges, pass
+    /// the datetime and key of the last edge in a page as `after` for the
+    /// next page.
+    ///
+    /// The default implementation fetches and sorts every edge, so
+    /// datastores should override it with an index where possible.
+    ///
+    /// # Arguments
+    /// * `after` - Only get edges that come strictly after this datetime and
+    ///   key in the order above. If `None`, edges are returned from the
+    ///   oldest.
+    /// * `limit` - The maximum number of edges to return.
+    fn edges_by_time(
+        &self,
+        after: Option<(DateTime<Utc>, models::EdgeKey)>,
+        limit: usize,
+    ) -> Result<Vec<models::Edge>> {
+        let q = models::RangeVertexQuery::new(u32::MAX).outbound(u32::MAX);
+
+        let mut edges: Vec<models::Edge> = self
+            .get_edges(q)?
+            .into_iter()
+            .filter(|edge| match after {
+                Some((ref datetime, ref key)) => (edge.created_datetime, &edge.key) > (*datetime, key),
+                None => true,
+            })
+            .collect();
+
+        edges.sort_by_key(|edge| (edge.created_datetime, edge.key.clone()));
+        edges.truncate(limit);
+        Ok(edges)
+    }
+
+    /// Renames an edge type, rewriting every edge of type `from` to be of
+    /// type `to`. Renamed edges keep their datetimes and properties. If an
+    /// edge of type `to` already exists between the same vertices, it's
+    /// replaced, along with its properties. Returns the number of edges
+    /// renamed.
+    ///
+    /// The default implementation recreates each edge under the new type,
+    /// so renamed edges get new datetimes, and only the properties listed
+    /// by `get_edge_property_names` are copied over. Datastores should
+    /// override it where possible.
+    ///
+    /// # Arguments
+    /// * `from` - The type to rename.
+    /// * `to` - The new type.
+    ///
+    /// # Errors
+    /// Returns `Error::Validation` if `from` and `to` are the same, or if
+    /// `to` is not a valid type.
+    fn rename_edge_type(&self, from: models::Type, to: models::Type) -> Result<u64> {
+        to.validate()?;
+
+        if from == to {
+            return Err(ValidationError::InvalidValue.into());
+        }
+
+        let edges = self.get_edges(models::RangeVertexQuery::new(u32::MAX).outbound(u32::MAX).t(from))?;
+
+        for edge in &edges {
+            let old_key = &edge.key;
+            let new_key = models::EdgeKey::new(old_key.outbound_id, to.clone(), old_key.inbound_id);
+
+            // Clear out the edge being replaced, if there is one
+            self.delete_edges(models::SpecificEdgeQuery::single(new_key.clone()))?;
+            self.create_edge(&new_key)?;
+
+            for name in self.get_edge_property_names(old_key)? {
+                let q = models::SpecificEdgeQuery::single(old_key.clone()).property(name.as_str());
+
+                for property in self.get_edge_properties(q)? {
+                    let q = models::SpecificEdgeQuery::single(new_key.clone()).property(name.as_str());
+                    self.set_edge_properties(q, &property.value)?;
+                }
+            }
+
+            self.delete_edges(models::SpecificEdgeQuery::single(old_key.clone()))?;
+        }
+
+        Ok(edges.len() as u64)
+    }
+
     /// Gets vertex properties.
     ///
     /// # Arguments
@@ -169,4 +480,49 @@ pub trait Transaction {
     /// * `q` - The query to run.
     /// * `name` - The property name.
     fn delete_edge_properties(&self, q: models::EdgePropertyQuery) -> Result<()>;
+
+    /// Gets the names of the properties set on an edge, in name order. The
+    /// default implementation doesn't know of any, so datastores that can
+    /// list an edge's properties should override it.
+    ///
+    /// # Arguments
+    /// * `key`: The key of the edge.
+    fn get_edge_property_names(&self, _key: &models::EdgeKey) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Sets or clears the label of an edge. Labels are stored as an edge
+    /// property named `EDGE_LABEL_PROPERTY`, so they don't affect the
+    /// edge's identity. Nothing is changed if the edge doesn't exist.
+    ///
+    /// # Arguments
+    /// * `key`: The key of the edge.
+    /// * `label`: The label, or `None` to clear it.
+    fn set_edge_label(&self, key: &models::EdgeKey, label: Option<&models::EdgeLabel>) -> Result<()> {
+        let q = models::SpecificEdgeQuery::single(key.clone()).property(models::EDGE_LABEL_PROPERTY);
+
+        match label {
+            Some(label) => self.set_edge_properties(q, &JsonValue::String(label.as_str().to_string())),
+            None => self.delete_edge_properties(q),
+        }
+    }
+
+    /// Gets the label of an edge, if it exists and has one.
+    ///
+    /// # Arguments
+    /// * `key`: The key of the edge.
+    fn get_edge_label(&self, key: &models::EdgeKey) -> Result<Option<models::EdgeLabel>> {
+        let q = models::SpecificEdgeQuery::single(key.clone()).property(models::EDGE_LABEL_PROPERTY);
+
+        let label = self
+            .get_edge_properties(q)?
+            .into_iter()
+            .filter_map(|property| match property.value {
+                JsonValue::String(label) => Some(models::EdgeLabel(label)),
+                _ => None,
+            })
+            .next();
+
+        Ok(label)
+    }
 }